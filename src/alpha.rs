@@ -9,6 +9,7 @@ use crate::{
     name::*,
     util::{assoc::Assoc, mbe::EnvMBE},
 };
+use std::collections::HashMap;
 
 // A renaming that only affects names at the "current" quotation level
 #[derive(Clone, Debug, PartialEq)]
@@ -264,6 +265,22 @@ pub fn freshen_binders_inside_node(parts: &EnvMBE<Ast>, mentioned: &[Name]) -> E
     })
 }
 
+fn freshen_binders_inside_node_memo(
+    parts: &EnvMBE<Ast>,
+    mentioned: &[Name],
+    memo: &mut HashMap<Name, Name>,
+    q_lev: i16,
+) -> EnvMBE<(Ast, Ren)>
+{
+    parts.named_map(&mut |n: &Name, a: &Ast| {
+        if mentioned.contains(n) {
+            freshen_binders_memo(a, memo, q_lev)
+        } else {
+            (a.clone(), Ren::new())
+        }
+    })
+}
+
 pub fn freshen_binders_inside_node_with(
     p_lhs: &EnvMBE<Ast>,
     p_rhs: &EnvMBE<Ast>,
@@ -287,11 +304,16 @@ pub fn freshen_binders_inside_node_with(
 
 /// Returns an `Ast` like `a`, but with fresh `Atom`s
 ///  and a map to change references in the same manner
-pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
+pub fn freshen_binders(a: &Ast) -> (Ast, Ren) { freshen_binders_memo(a, &mut HashMap::new(), 0) }
+
+// A name that appears more than once in `a` gets the same fresh name each time.
+// (Otherwise, the alternatives of an or-pattern would disagree about what they bind.)
+// `q_lev` is how much more quoted we are than `a` was.
+fn freshen_binders_memo(a: &Ast, memo: &mut HashMap<Name, Name>, q_lev: i16) -> (Ast, Ren) {
     match *a {
         Trivial | VariableReference(_) => (a.clone(), Ren::new()),
         Atom(old_name) => {
-            let new_name = old_name.freshen();
+            let new_name = *memo.entry(old_name).or_insert_with(|| old_name.freshen());
             (Atom(new_name), Ren::single(old_name, VariableReference(new_name)))
         }
         Node(ref f, ref parts, ref export) => {
@@ -300,7 +322,7 @@ pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
             }
             let exported = export.names_mentioned(); // Unmentioned atoms shouldn't be touched
 
-            let fresh_pairs = freshen_binders_inside_node(parts, &exported);
+            let fresh_pairs = freshen_binders_inside_node_memo(parts, &exported, memo, q_lev);
             let fresh_ast = fresh_pairs.map(&mut |&(ref a, _): &(Ast, _)| a.clone());
             let renaming = export.extract_from_mbe(&fresh_pairs, &|&(_, ref r): &(_, Ren)| &r);
 
@@ -308,21 +330,24 @@ pub fn freshen_binders(a: &Ast) -> (Ast, Ren) {
         }
         IncompleteNode(_) | Shape(_) => icp!("didn't think this was needed"),
         QuoteMore(ref body, pos) => {
-            let (a, r) = freshen_binders(body);
+            let (a, r) = freshen_binders_memo(body, memo, q_lev + 1);
             (QuoteMore(Box::new(a), pos), r.q_less(1))
         }
+        // An interpolated binder (e.g. `.[ ,[name], : Int ⋯ ].` inside a quotation pattern)
+        //  binds at the level it escapes to. If that's outside of `a`, it isn't ours to freshen.
+        QuoteLess(_, depth) if q_lev < i16::from(depth) => (a.clone(), Ren::new()),
         QuoteLess(ref body, depth) => {
-            let (a, r) = freshen_binders(body);
+            let (a, r) = freshen_binders_memo(body, memo, q_lev - i16::from(depth));
             (QuoteLess(Box::new(a), depth), r.q_more(depth))
         }
         ExtendEnv(ref sub, ref beta) => {
             // We're only looking at `Atom`s, so this is transparent
-            let (new_sub, subst) = freshen_binders(&*sub);
+            let (new_sub, subst) = freshen_binders_memo(sub, memo, q_lev);
             (ExtendEnv(Box::new(new_sub), beta.clone()), subst)
         }
         ExtendEnvPhaseless(ref sub, ref beta) => {
             // We're only looking at `Atom`s, so this is transparent
-            let (new_sub, subst) = freshen_binders(&*sub);
+            let (new_sub, subst) = freshen_binders_memo(sub, memo, q_lev);
             (ExtendEnvPhaseless(Box::new(new_sub), beta.clone()), subst.become_phaseless())
         }
     }
@@ -343,6 +368,12 @@ pub fn freshen_binders_with(lhs: &Ast, rhs: &Ast) -> Option<(Ast, Ren, Ast, Ren)
                 Ren::single(old_name_rhs, VariableReference(new_name)),
             ))
         }
+        // An interpolated binder (e.g. `.[ ,[name], : Int ⋯ ].` in a quotation pattern)
+        //  matches whatever binders `lhs` has, so only they get freshened:
+        (_, Node(f_rhs, _, _)) if f_rhs.name == n("unquote") => {
+            let (lhs_fresh, ren_lhs) = freshen_binders(lhs);
+            Some((lhs_fresh, ren_lhs, rhs.clone(), Ren::new()))
+        }
         // TODO: Handle matching `'[let (a,b) = ⋯]'` against the pattern `'[let ,[p], = ⋯]'` !!
         (
             &Node(ref f, ref parts_lhs, ref export),
@@ -426,6 +457,34 @@ pub fn freshen_binders_with(lhs: &Ast, rhs: &Ast) -> Option<(Ast, Ren, Ast, Ren)
     }
 }
 
+/// Are `lhs` and `rhs` the same, except (perhaps) for the names of their binders?
+pub fn alpha_equiv(lhs: &Ast, rhs: &Ast) -> bool {
+    match (lhs, rhs) {
+        (&Node(_, _, _), &Node(_, _, _)) => match freshen_with(lhs, rhs) {
+            (Node(ref f, ref parts, ref export), Node(ref f_rhs, ref parts_rhs, ref export_rhs)) =>
+            {
+                f == f_rhs
+                    && export == export_rhs
+                    && parts.can_map_with(parts_rhs)
+                    && parts.map_reduce_with(parts_rhs, &alpha_equiv, &|l, r| *l && *r, true)
+            }
+            _ => icp!(),
+        },
+        (&QuoteMore(ref body, pos), &QuoteMore(ref body_rhs, pos_rhs)) => {
+            pos == pos_rhs && alpha_equiv(body, body_rhs)
+        }
+        (&QuoteLess(ref body, depth), &QuoteLess(ref body_rhs, depth_rhs)) => {
+            depth == depth_rhs && alpha_equiv(body, body_rhs)
+        }
+        (&ExtendEnv(ref body, ref beta), &ExtendEnv(ref body_rhs, ref beta_rhs))
+        | (
+            &ExtendEnvPhaseless(ref body, ref beta),
+            &ExtendEnvPhaseless(ref body_rhs, ref beta_rhs),
+        ) => beta == beta_rhs && alpha_equiv(body, body_rhs),
+        _ => lhs == rhs,
+    }
+}
+
 #[test]
 fn basic_substitution() {
     crate::name::enable_fake_freshness(true);
@@ -484,6 +543,18 @@ fn basic_binder_freshening() {
             Ren::from(assoc_n!("a" => ast!((vr "a🍅")), "b" => ast!((vr "b🍅"))))
         )
     );
+
+    // The alternatives of an or-pattern bind the same (fresh) names:
+    crate::name::enable_fake_freshness(false);
+    let (fresh, _) = freshen_binders(&ast!({ "Pat" "or_pat" => ["first" "rest"] :
+        "first" => "a", "rest" => "a" }));
+    match fresh {
+        Node(_, ref parts, _) => {
+            assert_eq!(parts.get_leaf_or_panic(&n("first")), parts.get_leaf_or_panic(&n("rest")));
+            assert_ne!(parts.get_leaf_or_panic(&n("first")), &ast!("a"));
+        }
+        _ => panic!("not a node"),
+    }
 }
 
 #[test]
@@ -700,3 +771,59 @@ fn alpha_quote_more_or_less() {
                                                  "rand" => [(vr "a🍅"), (vr "b🍅")]})))})
     );
 }
+
+#[test]
+fn interpolated_binder_freshening() {
+    use crate::core_qq_forms::unquote_form;
+    crate::name::enable_fake_freshness(true);
+
+    // `.[ ,[x], : ⋯ . ,[b], ].`, as it appears in a quotation pattern
+    let pat = ast!({"Expr" "lambda" :
+        "param" => [{unquote_form(n("Atom"), false, 1) ;
+            "nt" => (vr "Atom"), "body" => (-- 1 "x")}],
+        "body" => (import [* ["param" : "[ignored]"]]
+            {unquote_form(n("Expr"), false, 1) ; "nt" => (vr "Expr"), "body" => (-- 1 "b")})});
+
+    // `x` is bound outside of the quotation, so it's not ours to freshen:
+    assert_eq!(freshen_binders(&pat), (pat.clone(), Ren::new()));
+
+    assert_eq!(
+        freshen_with(
+            &ast!({"Expr" "lambda" :
+                "param" => ["y"],
+                "body" => (import [* ["param" : "[ignored]"]] (vr "y"))}),
+            &pat
+        ),
+        (
+            ast!({"Expr" "lambda" :
+                "param" => ["y🍅"],
+                "body" => (import [* ["param" : "[ignored]"]] (vr "y🍅"))}),
+            pat.clone()
+        )
+    );
+}
+
+#[test]
+fn basic_alpha_equiv() {
+    let lam = |param: &str, body: Ast| {
+        ast!({"Expr" "lambda" :
+            "param" => [@"p" (, Atom(n(param)))],
+            "p_t" => [@"p" (vr "Int")],
+            "body" => (import [* ["param" : "p_t"]] (, body))})
+    };
+
+    assert!(alpha_equiv(&lam("a", ast!((vr "a"))), &lam("b", ast!((vr "b")))));
+    assert!(!alpha_equiv(&lam("a", ast!((vr "a"))), &lam("b", ast!((vr "a")))));
+    assert!(alpha_equiv(&lam("a", ast!((vr "free"))), &lam("b", ast!((vr "free")))));
+    // Nested binders:
+    assert!(alpha_equiv(
+        &lam("a", lam("b", ast!({"Expr" "apply" : "rator" => (vr "a"), "rand" => [(vr "b")]}))),
+        &lam("x", lam("y", ast!({"Expr" "apply" : "rator" => (vr "x"), "rand" => [(vr "y")]})))
+    ));
+    assert!(!alpha_equiv(
+        &lam("a", lam("b", ast!({"Expr" "apply" : "rator" => (vr "a"), "rand" => [(vr "b")]}))),
+        &lam("x", lam("y", ast!({"Expr" "apply" : "rator" => (vr "y"), "rand" => [(vr "x")]})))
+    ));
+    // Different forms:
+    assert!(!alpha_equiv(&lam("a", ast!((vr "a"))), &ast!((vr "a"))));
+}
@@ -139,7 +139,7 @@ pub fn walk<Mode: WalkMode>(
                     match walk_ctxt.this_ast {
                         // `this_ast` might be `NotWalked` (and non-literal) if under `switch_mode`.
                         // It's weird, but seems to be the right thing
-                        Node(ref f, _, _) => Some(Mode::get_walk_rule(f).is_literally()),
+                        Node(ref f, _, _) => Some(Mode::walks_wrappers_literally(f)),
                         _ => None
                     }
                 }
@@ -153,16 +153,16 @@ pub fn walk<Mode: WalkMode>(
                 heal__lwr_splices(&mut new_walk_ctxt)?;
 
                 // certain walks only work on certain kinds of AST nodes
-                match Mode::get_walk_rule(f) {
+                Mode::recover(match Mode::get_walk_rule(f) {
                     Custom(ref ts_fn) =>  ts_fn(new_walk_ctxt),
                     Body(n) =>            walk(parts.get_leaf(n).unwrap(), &new_walk_ctxt),
                     LiteralLike =>        Mode::walk_quasi_literally(a.clone(), &new_walk_ctxt),
                     NotWalked =>          icp!("{:#?} should not be walked at all!", a)
-                }
+                })
             }
             IncompleteNode(ref parts) => { icp!("{:#?} isn't a complete node", parts)}
 
-            VariableReference(n) => { Mode::walk_var(n, &walk_ctxt) }
+            VariableReference(n) => { Mode::recover(Mode::walk_var(n, &walk_ctxt)) }
             Atom(n) => { Mode::walk_atom(n, &walk_ctxt) }
 
             // TODO: we need to preserve these in LiteralLike contexts!!
@@ -403,7 +403,7 @@ pub enum WalkRule<Mode: WalkMode> {
 }
 
 impl<Mode: WalkMode> WalkRule<Mode> {
-    fn is_literally(&self) -> bool {
+    pub fn is_literally(&self) -> bool {
         match self {
             LiteralLike => true,
             _ => false,
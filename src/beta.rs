@@ -182,6 +182,14 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
                 let rhs = parts.switch_to_positive().get_res(rhs_source)?;
 
                 Ok(Assoc::new().set(*name, rhs))
+            } else if is_interpolated_binder(&parts.parts.get_leaf_or_panic(&name_source).term) {
+                // The name isn't known until the quotation is evaluated, so nothing is bound here.
+                // But the interpolation itself still needs to be checked against `rhs`:
+                let rhs = parts.switch_to_positive().get_res(rhs_source)?;
+                crate::ty::without_recovery(|| {
+                    parts.switch_to_negative().with_context(rhs).get_res(name_source)
+                })?;
+                Ok(Assoc::new())
             } else {
                 panic!(
                     "User error: {:#?} is supposed to supply names, but is not an Atom.",
@@ -200,7 +208,15 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
             // Probably ought to just use `susbsitute` anyways.
             let mut env_for_parts = parts.env.clone();
             for n in res_source.free_vrs() {
-                env_for_parts = env_for_parts.set(n, parts.switch_to_positive().get_res(n)?);
+                let res = if parts.parts.get_leaf(n).is_none() {
+                    // A repeated part is a tuple, to be taken apart by `:::[ ⋯ >> ⋯ ]:::`:
+                    let components = parts.switch_to_positive().get_rep_res(n)?;
+                    Mode::Elt::from_ast(&ast!({"Type" "tuple" :
+                        "component" => (,seq components.iter().map(Mode::Elt::to_ast))}))
+                } else {
+                    parts.switch_to_positive().get_res(n)?
+                };
+                env_for_parts = env_for_parts.set(n, res);
             }
 
             let rhs_parts = parts.switch_to_positive().with_environment(env_for_parts);
@@ -208,7 +224,9 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
                 crate::ast_walk::walk::<<Mode as WalkMode>::AsPositive>(&res_source, &rhs_parts)?;
 
             // Do the actual work:
-            let res = parts.switch_to_negative().with_context(ctxt).get_res(name_source)?;
+            let res = crate::ty::without_recovery(|| {
+                parts.switch_to_negative().with_context(ctxt).get_res(name_source)
+            })?;
 
             // ... and then check that it's the right set of names!
             // Somewhat awkward (but not unsound!) run-time error in the case that
@@ -219,7 +237,13 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
             //    because the errors in question aren't that bad to debug.)
 
             // For our purposes, this syntax is "real", so `quote_depth` is 0:
-            let expected_res_keys = names_exported_by(parts.get_term_ref(name_source), 0);
+            let mut expected_res_keys = vec![];
+            for name in names_exported_by(parts.get_term_ref(name_source), 0) {
+                // (The alternatives of an or-pattern all bind the same names.)
+                if !expected_res_keys.contains(&name) {
+                    expected_res_keys.push(name);
+                }
+            }
             let mut count = 0;
             for (k, _) in res.iter_pairs() {
                 if !expected_res_keys.contains(k) {
@@ -257,6 +281,8 @@ pub fn env_from_beta<Mode: crate::walk_mode::WalkMode>(
                 **parts.parts.get_leaf_or_panic(name_source)
             {
                 Ok(Assoc::new().set(*name, Mode::underspecified(*name)))
+            } else if is_interpolated_binder(&parts.parts.get_leaf_or_panic(name_source).term) {
+                Ok(Assoc::new())
             } else {
                 panic!(
                     "{:#?} is supposed to supply names, but is not an Atom.",
@@ -357,6 +383,15 @@ impl ExportBeta {
     }
 }
 
+/// Is `ast` an unquotation (or an abstract repetition of them) standing in for a binder?
+/// (e.g. `.[ ,[name], : Int . ⋯ ].` or `.[ ...[,names, >> ,[names],]... : ⋯ . ⋯ ].`)
+fn is_interpolated_binder(ast: &Ast) -> bool {
+    match *ast {
+        Ast::Node(ref f, _, _) => f.name == n("unquote") || f.name == n("dotdotdot"),
+        _ => false,
+    }
+}
+
 // Helper for `bound_from_[export_]beta`:
 fn names_exported_by(ast: &Ast, quote_depth: i16) -> Vec<Name> {
     use tap::TapOps;
@@ -405,8 +440,9 @@ pub fn bound_from_beta(b: &Beta, parts: &EnvMBE<crate::ast::Ast>, quote_depth: i
             names_exported_by(parts.get_leaf_or_panic(n_s), quote_depth)
         }
         Protected(ref _n_s) => vec![], // Non-binding
+        // Usually an atom, but an interpolated (unquoted) binder binds nothing at this level:
         Basic(ref n_s, _) | Underspecified(ref n_s) => {
-            vec![crate::core_forms::ast_to_name(parts.get_leaf_or_panic(n_s))]
+            names_exported_by(parts.get_leaf_or_panic(n_s), quote_depth)
         }
     }
 }
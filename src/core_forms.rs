@@ -49,6 +49,29 @@ pub fn strip_ee(a: &Ast) -> &Ast {
     }
 }
 
+/// Named recursive types are folded and unfolded implicitly, but only where a value is
+///  constructed (with the recursive type as its annotation) or taken apart (by a pattern).
+/// This pulls one layer of `mu` off of `ty`, the way that an `unfold` inserted there would.
+/// (Elsewhere, a recursive type and its unfolding are still different types.)
+fn unfold_mu<Mode: crate::walk_mode::WalkMode<Elt = Ty>>(
+    ty: Ty,
+    part_types: &crate::ast_walk::LazyWalkReses<Mode>,
+) -> Result<Ty, TypeError>
+{
+    if let Node(ref f, ref parts, _) = ty.0 {
+        if f.name == n("mu_type") {
+            // Inside a quotation, `ty` might mention types from outside it:
+            let mut env = Assoc::new();
+            for less_quoted_env in &part_types.less_quoted_env {
+                env = env.set_assoc(less_quoted_env);
+            }
+            let env = env.set_assoc(&part_types.env);
+            return synth_type(strip_ee(parts.get_leaf_or_panic(&n("body"))), env);
+        }
+    }
+    Ok(ty)
+}
+
 /// This is the Unseemly language.
 pub fn make_core_syn_env() -> SynEnv {
     color_backtrace::install(); // HACK: this is around the first thing that happens in any test.
@@ -66,6 +89,10 @@ pub fn make_core_syn_env() -> SynEnv {
     let ctf_6 = ctf.clone();
     let ctf_7 = ctf.clone();
     let ctf_8 = ctf.clone();
+    let ctf_9 = ctf.clone();
+    let ctf_10 = ctf.clone();
+    let ctf_11 = ctf.clone();
+    let ctf_12 = ctf.clone();
 
     // Unseemly expressions
     let main_expr_forms = forms_to_form_pat![
@@ -147,7 +174,10 @@ pub fn make_core_syn_env() -> SynEnv {
         typed_form!("match",
             [(lit "match"), (named "scrutinee", (call "Expr")),
              (delim "{", "{",
-                 (plus [(named "p", (call "Pat")), (lit "=>"),
+                 (plus [(named "p", (call "Pat")),
+                        (alt [], [(lit "if"),
+                                  (named "guard", (import ["p" = "scrutinee"], (call "Expr")))]),
+                        (lit "=>"),
                         (named "arm", (import ["p" = "scrutinee"], (call "Expr")))]))],
             /* Typesynth: */
             cust_rc_box!(move | part_types | {
@@ -158,33 +188,63 @@ pub fn make_core_syn_env() -> SynEnv {
                     //  that the arm patterns match the scrutinee;
                     //  the import handles that for us.
 
+                    if arm_part_types.has(n("guard")) {
+                        let guard_ty = arm_part_types.get_res(n("guard"))?;
+                        crate::ty_compare::is_subtype(&guard_ty, &ty!((vr "Bool")), &arm_part_types)
+                            .map_err(|e| crate::util::err::sp(
+                                e, arm_part_types.get_term(n("guard"))))?;
+                    }
+
                     let arm_res = arm_part_types.get_res(n("arm"))?;
 
                     match res {
                         None => { res = Some(arm_res) }
                         Some(ref old_res) => {
-                            ty_exp!(old_res, &arm_res, arm_part_types.get_term(n("arm")));
+                            // (Not just `ty_exp!`, so that e.g. holes can get unified:)
+                            crate::ty_compare::is_subtype(&arm_res, old_res, &arm_part_types)
+                                .map_err(|e| crate::util::err::sp(
+                                    e, arm_part_types.get_term(n("arm"))))?;
                         }
                     }
                 }
-                match res {
-                    None => { // TODO #2: this isn't anywhere near exhaustive
-                        ty_err!(NonExhaustiveMatch(part_types.get_res(n("scrutinee")).unwrap())
-                            at Trivial /* TODO */)
-                    },
-                    Some(ty_res) => Ok(ty_res)
+
+                let scrutinee_ty = part_types.get_res(n("scrutinee"))?;
+                let pats = part_types.get_rep_term(n("p"));
+                let guarded : Vec<bool> = part_types.march_parts(&[n("arm")]).iter()
+                    .map(|arm_part_types| arm_part_types.has(n("guard"))).collect();
+                let coverage = crate::exhaustiveness::check_match(
+                    &scrutinee_ty, &pats, &guarded, &part_types.env);
+                for arm_idx in coverage.redundant {
+                    crate::ty::warn(TyWarning::RedundantArm, pats[arm_idx].clone());
                 }
+                if let Some(missing) = coverage.missing {
+                    ty_err!(NonExhaustiveMatch(scrutinee_ty, format!("{}", missing))
+                        at part_types.this_ast)
+                }
+
+                Ok(res.unwrap()) // `plus` ensures that there's at least one arm
             }),
             /* Evaluation: */
             cust_rc_box!( move | part_values | {
+                use crate::runtime::eval::runtime_error_pending;
                 for arm_values in part_values.march_all(&[n("arm")]) {
+                    if arm_values.has(n("guard")) {
+                        match arm_values.get_res(n("guard")) {
+                            Ok(Enum(ref choice, _)) if choice.is("True") => {}
+                            Err(()) if runtime_error_pending() => { return Err(()); }
+                            _ => { continue; } // The pattern didn't match, or the guard failed
+                        }
+                    }
                     // TODO: don't we need to set a context?
                     match arm_values.get_res(n("arm")) {
                         Ok(res) => { return Ok(res); }
+                        Err(()) if runtime_error_pending() => { return Err(()); }
                         Err(()) => { /* try the next one */ }
                     }
                 }
-                panic!("No arms matched! TODO #2");
+                // Only possible for patterns that can't be checked until after macro expansion:
+                crate::runtime::eval::runtime_error(format!(
+                    "no arm of `match` matched `{}`", part_values.get_res(n("scrutinee"))?))
             })
         ),
         // Note that we inconveniently require the user to specify the type.
@@ -197,7 +257,8 @@ pub fn make_core_syn_env() -> SynEnv {
         /* Typesynth: */
         cust_rc_box!( move | part_types | {
             let res : Ty = part_types.get_res(n("t"))?;
-            expect_ty_node!( (res ; find_type(&ctf_2, "enum") ; &part_types.this_ast)
+            let enum_ty = unfold_mu(res.clone(), &part_types)?;
+            expect_ty_node!( (enum_ty ; find_type(&ctf_2, "enum") ; &part_types.this_ast)
                 enum_type_parts;
                 {
                     for enum_type_part in enum_type_parts.march_all(&[n("name")]) {
@@ -220,7 +281,7 @@ pub fn make_core_syn_env() -> SynEnv {
                     }
 
                     ty_err!(NonexistentEnumArm
-                                (ast_to_name(&part_types.get_term(n("name"))), res)
+                                (ast_to_name(&part_types.get_term(n("name"))), enum_ty)
                             at part_types.this_ast);
                 }
             )
@@ -284,6 +345,39 @@ pub fn make_core_syn_env() -> SynEnv {
             crate::ast_walk::walk::<Eval>(
                 strip_ee(&let_type_parts.get_term(n("body"))), &let_type_parts)
         })),
+        // e.g.
+        // let_type rec
+        //   Expr = enum { Lit(Int) Block(Stmt) }
+        //   Stmt = enum { Eval(Expr) Seq(Stmt Stmt) }
+        // in ...
+        // Each definition becomes a `mu_type` over the whole group (its own name first).
+        typed_form!("let_type_rec",
+        [(lit "let_type"), (lit "rec"),
+         (star [(named "type_name", atom),
+                (lit "="),
+                (named "type_def", (import [* [prot "type_name"]], (call "Type")))]),
+         (lit "in"),
+         (named "body", (import [* ["type_name" = "type_def"]], (call "Expr")))],
+        cust_rc_box!( move | let_type_parts | {
+            let names = let_type_parts.get_rep_term(n("type_name"));
+            let defs = let_type_parts.get_rep_term(n("type_def"));
+
+            let mut body_env = let_type_parts.env.clone();
+            for (name, def) in names.iter().zip(defs.iter()) {
+                let group = std::iter::once(name).chain(names.iter().filter(|o| *o != name));
+                let params: Vec<Ast> = group.map(|member| ast!(
+                    (import [prot "param"] (, VariableReference(ast_to_name(member)))))).collect();
+                body_env = body_env.set(ast_to_name(name), ty!({ find_type(&ctf_12, "mu_type") ;
+                    "param" => (,seq params),
+                    "body" => (import [* [prot "param"]] (, strip_ee(def).clone()))}));
+            }
+            crate::ast_walk::walk::<SynthTy>(strip_ee(&let_type_parts.get_term(n("body"))),
+                &let_type_parts.with_environment(body_env))
+        }),
+        cust_rc_box!( move | let_type_parts | {
+            crate::ast_walk::walk::<Eval>(
+                strip_ee(&let_type_parts.get_term(n("body"))), &let_type_parts)
+        })),
         // e.g. where List = ∀ X. μ List. enum { Nil(), Cons(X, List<X>) }
         // .[x : List<X>  . match (unfold x) ... ].
         // (unfold is needed because `match` wants an `enum`, not a `μ`)
@@ -343,8 +437,110 @@ pub fn make_core_syn_env() -> SynEnv {
                 }))
             }),
             Body(n("body"))),
+        // e.g. where Counter = ∃ T. *[init: T  next: [T -> T]  read: [T -> Int]]*
+        // pack *[init: zero  next: .[x : Int . (plus x one)].  read: .[x : Int . x]. ]* : Counter
+        // Hides the representation type; it's the one chosen by unification.
+        typed_form!("pack",
+            [(lit "pack"), (named "body", (call "Expr")), (lit ":"), (named "t", (call "Type"))],
+            cust_rc_box!( move |pack_parts| {
+                let goal_type = pack_parts.get_res(n("t"))?;
+                let _ = expect_ty_node!(
+                    (goal_type ; find_type(&ctf_9, "exists_type") ; &pack_parts.this_ast)
+                    exists_parts; exists_parts);
+
+                let _ = crate::ty_compare::is_subtype(
+                    &pack_parts.get_res(n("body"))?, &goal_type, &pack_parts)
+                        .map_err(|e| crate::util::err::sp(e, pack_parts.this_ast.clone()))?;
+                Ok(goal_type)
+            }),
+            Body(n("body"))),
+        // e.g. unpack (counter) as T . c in match c { *[init: i  next: nx  read: r]* => (r i) }
+        // Opens a package, binding its hidden types as new, opaque, type names.
+        typed_form!("unpack",
+            [(lit "unpack"), (named "package", (call "Expr")),
+             (lit "as"), (star (named "param", atom)), (lit "."), (named "var", atom), (lit "in"),
+             (named "body", (import [* [forall "param"]], (import ["var" : "package"],
+                 (call "Expr"))))],
+            cust_rc_box!( move |unpack_parts| {
+                let package_type = unpack_parts.get_res(n("package"))?;
+                let params = unpack_parts.get_rep_term(n("param"));
+
+                expect_ty_node!(
+                    (package_type ; find_type(&ctf_10, "exists_type") ; &unpack_parts.this_ast)
+                    exists_parts;
+                    {
+                        let exists_params = exists_parts.get_rep_leaf_or_panic(n("param"));
+                        if exists_params.len() != params.len() {
+                            ty_err!(LengthMismatch(
+                                params.iter().map(|p| Ty(VariableReference(ast_to_name(p))))
+                                    .collect(),
+                                exists_params.len()) at unpack_parts.this_ast);
+                        }
+
+                        // The hidden types are protected (bound to themselves),
+                        //  so they can't be confused with anything else:
+                        let mut renaming = Assoc::new();
+                        let mut body_env = unpack_parts.env.clone();
+                        for (exists_param, param) in exists_params.iter().zip(params.iter()) {
+                            let param = ast_to_name(param);
+                            renaming = renaming.set(ast_to_name(exists_param),
+                                                    VariableReference(param));
+                            body_env = body_env.set(param, Ty(VariableReference(param)));
+                        }
+                        let opened_type = Ty(crate::alpha::substitute(
+                            strip_ee(exists_parts.get_leaf_or_panic(&n("body"))), &renaming));
+                        body_env = body_env.set(
+                            ast_to_name(&unpack_parts.get_term(n("var"))), opened_type);
+
+                        // HACK: like `get_res(n("body"))`, but `var` gets the opened type.
+                        // (The `import` still describes the binding structure, e.g. for evaluation.)
+                        let body = unpack_parts.get_term(n("body"));
+                        let res = crate::ast_walk::walk::<SynthTy>(
+                            strip_ee(strip_ee(&body)),
+                            &unpack_parts.with_environment(body_env))?;
+
+                        for param in &params {
+                            let param = ast_to_name(param);
+                            if res.0.free_vrs().contains(&param) {
+                                ty_err!(TypeEscapes(param, res) at unpack_parts.this_ast);
+                            }
+                        }
+                        Ok(res)
+                    })
+            }),
+            Body(n("body"))),
+        // A placeholder for an expression that hasn't been written yet.
+        // Typechecking reports what it ought to be (and what's in scope), instead of its type.
+        typed_form!("hole",
+            [(call "DefaultSeparator"),
+             (named "name", (scan r"(\?\p{Letter}(?:\p{Letter}|\p{Number}|[_?])*)"))],
+            cust_rc_box!( move |hole_parts| {
+                use crate::walk_mode::WalkMode;
+                let expected = crate::ty_compare::Subtype::underspecified(n("<hole>"));
+                let mut locals : Vec<(Name, Ty)> = hole_parts.env.iter_pairs()
+                    .filter(|&(name, ty)| hole_parts.prelude_env.find(name) != Some(ty)
+                        && *name != negative_ret_val())
+                    .map(|(name, ty)| (*name, ty.clone())).collect();
+                locals.sort_by_key(|&(name, _)| name.orig_sp());
+                let name = ast_to_name(&hole_parts.get_term(n("name")));
+                if crate::ty::record_hole(ty_err_val!(
+                        Hole(name, Some(expected.clone()), locals.clone())
+                            at hole_parts.this_ast)) {
+                    Ok(expected) // The error will say what `expected` turns out to be.
+                } else {
+                    ty_err!(Hole(name, None, locals) at hole_parts.this_ast)
+                }
+            }),
+            cust_rc_box!( move |hole_parts| {
+                crate::runtime::eval::runtime_error(format!(
+                    "evaluated the hole `{}`, which needs to be filled in",
+                    ast_to_name(&hole_parts.get_term(n("name")))))
+            })),
         crate::core_qq_forms::quote(/* positive= */ true),
-        crate::core_macro_forms::extend_syntax()
+        crate::core_macro_forms::extend_syntax(),
+        crate::core_macro_forms::for_syntax(),
+        crate::core_module_forms::import(),
+        crate::core_module_forms::export()
     ];
 
     let main_pat_forms = forms_to_form_pat_export![
@@ -352,9 +548,9 @@ pub fn make_core_syn_env() -> SynEnv {
             (delim "+[", "[", [(named "name", atom),
                                (star (named "component", (call "Pat")))]),
             /* (Negatively) Typecheck: */
-            cust_rc_box!( move | part_types |
-                expect_ty_node!( (part_types.context_elt() ; find_type(&ctf_6, "enum") ;
-                                      &part_types.this_ast)
+            cust_rc_box!( move | part_types | {
+                let enum_ty = unfold_mu(part_types.context_elt().clone(), &part_types)?;
+                expect_ty_node!( (enum_ty ; find_type(&ctf_6, "enum") ; &part_types.this_ast)
                     enum_type_parts;
                     {
                         let arm_name = &part_types.get_term(n("name"));
@@ -377,11 +573,10 @@ pub fn make_core_syn_env() -> SynEnv {
 
                             return Ok(res);
                         }
-                        ty_err!(NonexistentEnumArm(ast_to_name(arm_name),
-                            Ty::new(Trivial)) /* TODO `LazyWalkReses` needs more information */
+                        ty_err!(NonexistentEnumArm(ast_to_name(arm_name), enum_ty)
                             at arm_name.clone())
-                }
-            )),
+                })
+            }),
             /* (Negatively) Evaluate: */
             cust_rc_box!( move | part_values | {
                 match *part_values.context_elt() /* : Value */ {
@@ -407,9 +602,9 @@ pub fn make_core_syn_env() -> SynEnv {
                  (star [(named "component_name", atom), (lit ":"),
                         (named "component", (call "Pat"))]))],
             /* (Negatively) typesynth: */
-            cust_rc_box!( move | part_types |
-                expect_ty_node!( (part_types.context_elt() ; find_type(&ctf_7, "struct") ;
-                                      &part_types.this_ast)
+            cust_rc_box!( move | part_types | {
+                let struct_ty = unfold_mu(part_types.context_elt().clone(), &part_types)?;
+                expect_ty_node!( (struct_ty ; find_type(&ctf_7, "struct") ; &part_types.this_ast)
                     struct_type_parts;
                     {
                         let mut res = Assoc::new();
@@ -433,12 +628,13 @@ pub fn make_core_syn_env() -> SynEnv {
                             if !component_found {
                                 ty_err!(NonexistentStructField(
                                         ast_to_name(&component_ctx.get_term(n("component_name"))),
-                                        part_types.context_elt().clone())
+                                        struct_ty)
                                     at part_types.get_rep_term(n("component"))[0].clone());
                             }
                         }
                         Ok(res)
-                    })),
+                    })
+            }),
             cust_rc_box!( move | part_values | {
                 match *part_values.context_elt() {
                     Struct(ref contents) => {
@@ -462,8 +658,8 @@ pub fn make_core_syn_env() -> SynEnv {
         negative_typed_form!("tuple_pat",
             (delim "**[", "[", (star (named "component", (call "Pat")))),
             cust_rc_box!( move |part_types|
-                expect_ty_node!( (part_types.context_elt() ; find_type(&ctf_8, "tuple") ;
-                                    &part_types.this_ast)
+                expect_ty_node!( (unfold_mu(part_types.context_elt().clone(), &part_types)? ;
+                                  find_type(&ctf_8, "tuple") ; &part_types.this_ast)
                     ctxt_type_parts;
                     {
                         let component_types : Vec<Ty> =
@@ -497,11 +693,70 @@ pub fn make_core_syn_env() -> SynEnv {
             })
 
         ) => [* ["component"]],
+        negative_typed_form!("wildcard_pat",
+            (lit "_"),
+            cust_rc_box!( move |_| Ok(Assoc::new()) ),
+            cust_rc_box!( move |_| Ok(Assoc::new()) )) => [],
+        negative_typed_form!("int_pat",
+            [(call "DefaultSeparator"), (named "value", (scan r"(-?[0-9]+)"))],
+            cust_rc_box!( move |part_types|
+                expect_ty_node!( (part_types.context_elt() ; find_type(&ctf_11, "Int") ;
+                                    &part_types.this_ast)
+                    _int_parts;
+                    Ok(Assoc::new()))),
+            cust_rc_box!( move |part_values| {
+                let expected : num::BigInt =
+                    ast_to_name(&part_values.get_term(n("value"))).orig_sp().parse()
+                        .unwrap_or_else(|_| icp!("malformed integer pattern"));
+                match *part_values.context_elt() {
+                    Int(ref i) if i == &expected => Ok(Assoc::new()),
+                    Int(_) => Err(()), // "Try another branch"
+                    _ => icp!("[type error] non-integer")
+                }
+            })) => [],
+        negative_typed_form!("as_pat",
+            [(named "name", atom), (lit "@"), (named "body", (call "SimplePat"))],
+            cust_rc_box!( move |part_types|
+                Ok(part_types.get_res(n("name"))?.set_assoc(&part_types.get_res(n("body"))?))),
+            cust_rc_box!( move |part_values|
+                Ok(part_values.get_res(n("name"))?.set_assoc(&part_values.get_res(n("body"))?)))
+        ) => ["name" "body"],
             // TODO #16: We need a pattern for destructuring tuples.
             crate::core_qq_forms::quote(/*positive=*/false) => ["body"]];
 
+    // `a | b | c` is `a | (b | c)`; the left side is a `SimplePat`, so it only parses one way.
+    let or_pat_forms = forms_to_form_pat_export![
+        negative_typed_form!("or_pat",
+            [(named "first", (call "SimplePat")), (lit "|"), (named "rest", (call "Pat"))],
+            cust_rc_box!( move |part_types| {
+                let first_env = part_types.get_res(n("first"))?;
+                let rest_env = part_types.get_res(n("rest"))?;
+                let rest = part_types.get_term(n("rest"));
+
+                // Both sides must bind the same names, at the same types:
+                for (name, ty) in rest_env.iter_pairs() {
+                    match first_env.find(name) {
+                        Some(first_ty) => ty_exp!(ty, first_ty, rest.clone()),
+                        None => ty_err!(InconsistentBinding(*name) at rest.clone())
+                    }
+                }
+                for name in first_env.iter_keys() {
+                    if rest_env.find(name).is_none() {
+                        ty_err!(InconsistentBinding(*name) at rest.clone())
+                    }
+                }
+                Ok(first_env)
+            }),
+            cust_rc_box!( move |part_values| {
+                match part_values.get_res(n("first")) {
+                    Ok(res) => Ok(res),
+                    Err(()) => part_values.get_res(n("rest"))
+                }
+            })) => ["first" "rest"]];
+
     let reserved_names = vec![
         n("forall"),
+        n("exists"),
         n("mu_type"),
         n("Int"),
         n("Ident"),
@@ -511,15 +766,23 @@ pub fn make_core_syn_env() -> SynEnv {
         n("struct"),
         n("fold"),
         n("unfold"),
+        n("pack"),
+        n("unpack"),
         n("extend_syntax"),
+        n("for_syntax"),
+        n("import"),
+        n("export"),
         n("in"),
     ];
 
     assoc_n!(
-        "Pat" => Rc::new(Biased(Rc::new(main_pat_forms), Rc::new(Call(n("DefaultAtom"))))),
+        // (Only an `or_pat` has a `|` after a `SimplePat`, so this isn't ambiguous.)
+        "Pat" => Rc::new(Alt(vec![Rc::new(or_pat_forms), Rc::new(Call(n("SimplePat")))])),
+        "SimplePat" => Rc::new(Biased(Rc::new(main_pat_forms), Rc::new(Call(n("DefaultAtom"))))),
         "Expr" => Rc::new(Biased(Rc::new(main_expr_forms), Rc::new(Call(n("DefaultReference"))))),
         "Ident" => Rc::new(Call(n("DefaultAtom"))),
         "AtomNotInPat" => Rc::new(Call(n("DefaultAtom"))),
+        "Atom" => Rc::new(Call(n("DefaultAtom"))),
         "DefaultReference" => Rc::new(VarRef(Rc::new(Call(n("DefaultAtom"))))),
         "DefaultSeparator" => Rc::new(crate::grammar::new_scan(r"(\s*)")),
         "DefaultAtom" => Rc::new(
@@ -541,7 +804,7 @@ pub fn make_core_syn_env() -> SynEnv {
 /// Mostly for testing purposes, this looks up forms by name.
 /// In the "real world", programmers look up forms by syntax, using a parser.
 pub fn find_form(se: &SynEnv, nt: &str, form_name: &str) -> Rc<Form> {
-    fn find_form_rec(f: &FormPat, form_name: &str) -> Option<Rc<Form>> {
+    fn find_form_rec(se: &SynEnv, f: &FormPat, form_name: &str) -> Option<Rc<Form>> {
         match *f {
             Scope(ref f, _) => {
                 if f.name.is(form_name) {
@@ -552,7 +815,7 @@ pub fn find_form(se: &SynEnv, nt: &str, form_name: &str) -> Rc<Form> {
             }
             Alt(ref vf) => {
                 for f in vf {
-                    let res = find_form_rec(f, form_name);
+                    let res = find_form_rec(se, f, form_name);
                     if res.is_some() {
                         return res;
                     }
@@ -560,19 +823,21 @@ pub fn find_form(se: &SynEnv, nt: &str, form_name: &str) -> Rc<Form> {
                 None
             }
             Biased(ref lhs, ref rhs) => {
-                let l_res = find_form_rec(lhs, form_name);
+                let l_res = find_form_rec(se, lhs, form_name);
                 if l_res.is_some() {
                     l_res
                 } else {
-                    find_form_rec(rhs, form_name)
+                    find_form_rec(se, rhs, form_name)
                 }
             }
+            // e.g. `Pat` delegates most of its forms to `SimplePat`:
+            Call(nt) => se.find(&nt).and_then(|f| find_form_rec(se, f, form_name)),
             _ => None,
         }
     }
     let pat = se.find_or_panic(&n(nt));
 
-    find_form_rec(pat, form_name)
+    find_form_rec(se, pat, form_name)
         .unwrap_or_else(|| icp!("{:#?} not found in {:#?}", form_name, pat))
 }
 
@@ -912,7 +1177,8 @@ fn recursive_types() {
 
     let ty_env = assoc_n!(
         "IntList" => int_list_ty.clone(),  // this is a type definition...
-        "il_direct" => int_list_ty.clone()  // ...and this is a value with a type
+        "il_direct" => int_list_ty.clone(),  // ...and this is a value with a type
+        "zero" => ty!({"Type" "Int":})
         // TODO #3: ... distinguish between these kinds in the environment!
 
         // We should never have `vr`s in the environment unless "protected" by a μ.
@@ -949,8 +1215,9 @@ fn recursive_types() {
                 "name" => "Cons",
                 "component" => ["car", "cdr"],
                 "t" => (vr "IntList")
-            }],
-            "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "car"))]
+            }, "other"],
+            "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "car")),
+                             (import ["p" = "scrutinee"] (vr "zero"))]
         }),
         ty_env.clone()),
         Ok(ty!({"Type" "Int":})));
@@ -963,16 +1230,17 @@ fn recursive_types() {
                 "name" => "Cons",
                 "component" => ["car", "cdr"],
                 "t" => (vr "IntList")
-            }],
-            "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "cdr"))]
+            }, "other"],
+            "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "cdr")),
+                             (import ["p" = "scrutinee"] (vr "il_direct"))]
         }),
         ty_env.clone()),
         Ok(int_list_ty.clone())
     );
     };
 
-    // Test that missing an unfold fails
-    assert_m!(
+    // The unfold can be left implicit
+    assert_eq!(
         synth_type(
             &ast!( { "Expr" "match" :
                     "scrutinee" =>  (vr "il_direct") ,
@@ -980,6 +1248,24 @@ fn recursive_types() {
                     "name" => "Cons",
                     "component" => ["car", "cdr"],
                     "t" => (vr "IntList")
+                }, "other"],
+                "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "car")),
+                                 (import ["p" = "scrutinee"] (vr "zero"))]
+            }),
+            ty_env.clone()
+        ),
+        Ok(ty!({"Type" "Int":}))
+    );
+
+    // ...but you still can't destructure something that isn't recursive at all
+    assert_m!(
+        synth_type(
+            &ast!( { "Expr" "match" :
+                    "scrutinee" =>  (vr "zero") ,
+                    "p" => [@"arm" { "Pat" "enum_pat" => [* ["component"]] :
+                    "name" => "Cons",
+                    "component" => ["car", "cdr"],
+                    "t" => (vr "IntList")
                 }],
                 "arm" => [@"arm" (import ["p" = "scrutinee"] (vr "car"))]
             }),
@@ -14,14 +14,14 @@ use crate::{
     },
     name::*,
     runtime::{
-        eval::{Closure, Eval},
+        eval::{Closure, Eval, QQuote},
         reify::Reifiable,
     },
     ty::{SynthTy, Ty, TyErr},
     util::assoc::Assoc,
     walk_mode::WalkElt,
 };
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
 
 // Macros!
 //
@@ -74,7 +74,8 @@ macro_rules! syntax_syntax {
             eval: Positive(cust_rc_box!(|_parts| {
                 Ok($syntax_name.reify())}
             )),
-            quasiquote: Both(LiteralLike, LiteralLike)
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
         })
     };
 
@@ -96,7 +97,8 @@ macro_rules! syntax_syntax {
                     $( { let $arg = parts.get_res(n(&stringify!($arg)))?; $e } ),*
                 ).reify())}
             )),
-            quasiquote: Both(LiteralLike, LiteralLike)
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
         })
     };
     // FormPat with arguments, and just doing `get_res` on everything doesn't work:
@@ -107,7 +109,8 @@ macro_rules! syntax_syntax {
             type_compare: Both(NotWalked,NotWalked), // Not a type
             synth_type: Negative(cust_rc_box!( $type )), // Produces a typed value
             eval: Positive(cust_rc_box!( $eval )),
-            quasiquote: Both(LiteralLike, LiteralLike)
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
         })
     };
 }
@@ -183,12 +186,16 @@ fn type_macro_invocation(
     // This is lifted almost verbatim from "Expr" "apply". Maybe they should be unified?
     use crate::walk_mode::WalkMode;
 
+    // (Cite the macro by the name that the programmer wrote.)
+    let macro_name = vr_to_name(&parts.get_term(n("macro_name"))).unhygienic_orig();
     let _ = crate::ty_compare::is_subtype(
         &macro_type(&[], q_arguments.clone(), expected_return),
         &parts.get_res(n("macro_name"))?,
         &parts,
     )
-    .map_err(|e| crate::util::err::sp(e, parts.this_ast.clone()))?;
+    .map_err(|e| {
+        crate::util::err::sp(TyErr::InMacro(macro_name, Box::new(e)), parts.this_ast.clone())
+    })?;
 
     // TODO: I think `Assoc` should implement `From<Vec<(K,V)>>`, maybe?
     let mut res = Assoc::new();
@@ -199,6 +206,88 @@ fn type_macro_invocation(
     Ok(res)
 }
 
+/// The line that `a` was written on, if it's a `Node` whose form asks for a "source_line" part.
+pub fn source_line(a: &Ast) -> Option<usize> {
+    match *a {
+        Node(_, ref parts, _) => match parts.get_leaf(n("source_line")) {
+            Some(&Atom(line)) => line.orig_sp().parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A macro invocation being expanded: the macro's name, the line it's invoked on,
+///  and the `Expr` quotations it has evaluated so far (with the lines they're written on).
+type Expansion = (Name, Option<usize>, Vec<(Ast, Option<usize>)>);
+
+thread_local! {
+    /// The macro invocations being expanded right now (innermost last).
+    static expanding: RefCell<Vec<Expansion>> = RefCell::new(vec![]);
+
+    pub static expansion_origin_form: Rc<Form> = Rc::new(Form {
+        name: n("expansion_origin"),
+        // Never parsed; the `anyways`es just keep the extra parts from being unparsed.
+        grammar: Rc::new(form_pat!([
+            (named "macro_name", (anyways (, Ast::Trivial))),
+            (named "invocation_line", (anyways (, Ast::Trivial))),
+            (named "template_line", (anyways (, Ast::Trivial))),
+            (named "body", (call "Expr"))
+        ])),
+        type_compare: Both(NotWalked, NotWalked),
+        // Only appears after macro expansion, which is after typechecking:
+        synth_type: Positive(Body(n("body"))),
+        eval: Positive(cust_rc_box!(|parts| {
+            let line = |part: &str| match parts.get_term(n(part)) {
+                Atom(line) => line.orig_sp().parse().ok(),
+                _ => None,
+            };
+            let origin = crate::runtime::eval::Origin {
+                macro_name: ast_to_name(&parts.get_term(n("macro_name"))),
+                invocation_line: line("invocation_line"),
+                template_line: line("template_line"),
+            };
+            crate::runtime::eval::eval_from_origin(origin, || parts.get_res(n("body")))
+        })),
+        quasiquote: Both(LiteralLike, LiteralLike),
+        macro_definition: None,
+    });
+}
+
+/// If a macro is being expanded, remember that the `Expr` quotation on `template_line`
+///  produced `expr`, in case it turns out to be what the macro expands to.
+/// (The syntax value itself is left alone, so the macro can still take it apart.)
+pub fn note_template(expr: &crate::runtime::eval::Value, template_line: Option<usize>) {
+    expanding.with(|e| {
+        if let Some((_, _, ref mut templates)) = e.borrow_mut().last_mut() {
+            templates.push((Ast::reflect(expr), template_line))
+        }
+    })
+}
+
+/// Mark `expr` (the fully-expanded result of `macro_name`, invoked on `invocation_line`,
+///  which came from the template on `template_line`) with where it came from,
+///  so that runtime errors can say so.
+fn mark_expansion_origin(
+    expr: Ast,
+    macro_name: Name,
+    invocation_line: Option<usize>,
+    template_line: Option<usize>,
+) -> Ast {
+    if invocation_line.is_none() && template_line.is_none() {
+        return expr; // Hand-built syntax; there's nothing interesting to say
+    }
+    let line = |l: Option<usize>| l.map(|l| Atom(n(&l.to_string()))).unwrap_or(Ast::Trivial);
+    Node(
+        expansion_origin_form.with(|f| f.clone()),
+        mbe!("macro_name" => (, Atom(macro_name)),
+             "invocation_line" => (, line(invocation_line)),
+             "template_line" => (, line(template_line)),
+             "body" => (, expr)),
+        ExportBeta::Nothing,
+    )
+}
+
 // This will be called at parse-time to generate the `Ast` for a macro invocation.
 // The form it emits is analogous to the "Expr" "apply" form.
 // Public for use in `expand.rs` tests.
@@ -209,10 +298,26 @@ pub fn macro_invocation(
     export_names: Vec<Name>,
 ) -> Rc<Form>
 {
+    macro_from_definition(MacroDefinition {
+        grammar,
+        macro_name,
+        nt: n("Expr"),
+        implementation,
+        export_names,
+        syntax: crate::core_forms::get_core_forms(),
+    })
+}
+
+/// Make a macro invocation `Form` from its definition (e.g. after deserialization).
+pub fn macro_from_definition(definition: MacroDefinition) -> Rc<Form> {
     use crate::{ty_compare, walk_mode::WalkMode};
 
+    let MacroDefinition { grammar, macro_name, nt, implementation, export_names, syntax } =
+        definition.clone();
     let grammar1 = grammar.clone();
     let grammar2 = grammar.clone();
+    let implementation2 = implementation.clone();
+    let definition2 = definition.clone();
     Rc::new(Form {
         name: n("macro_invocation"), // TODO: maybe generate a fresh name?
         grammar: Rc::new(form_pat!([
@@ -220,6 +325,7 @@ pub fn macro_invocation(
             (named "macro_name", (anyways (,
                 Ast::VariableReference(macro_name)
             ))),
+            (named "source_line", (anyways (, Ast::Trivial))),
             (, grammar.clone())
         ])),
         type_compare: Both(NotWalked, NotWalked),
@@ -230,6 +336,7 @@ pub fn macro_invocation(
         synth_type: Both(
             cust_rc_box!(move |parts| {
                 let return_type = ty_compare::Subtype::underspecified(n("<return_type>"));
+                let errors_before = crate::ty::recorded_error_count();
                 let _ = type_macro_invocation(&parts, return_type.clone(), &grammar1)?;
 
                 // What return type made that work?
@@ -240,11 +347,20 @@ pub fn macro_invocation(
                     );
 
                     // Canonicalize the type in its environment:
-                    let resolved = ty_compare::canonicalize(&resolved.it, resolved.env);
-                    resolved.map_err(|e| crate::util::err::sp(e, parts.this_ast.clone()))
+                    match ty_compare::canonicalize(&resolved.it, resolved.env) {
+                        // An error in the arguments can leave the return type undetermined;
+                        //  that's not worth reporting separately.
+                        Err(_) if crate::ty::recorded_error_count() > errors_before => {
+                            Ok(crate::ty::error_ty())
+                        }
+                        resolved => {
+                            resolved.map_err(|e| crate::util::err::sp(e, parts.this_ast.clone()))
+                        }
+                    }
                 })?;
 
-                less_quoted_ty(&q_result, Some(n("Expr")), &parts.this_ast)
+                // A `Type` macro's "type" is the type it expands to, so it's unquoted the same way:
+                less_quoted_ty(&q_result, Some(nt), &parts.this_ast)
             }),
             cust_rc_box!(move |parts| {
                 // From the macro's point of view, its parts are all positive;
@@ -267,11 +383,13 @@ pub fn macro_invocation(
                         let binder_ty = ty_compare::canonicalize(&binder_clo.it, binder_clo.env)
                             .map_err(|e| crate::util::err::sp(e, parts.this_ast.clone()))?;
 
+                        // Unquote the context (rather than the result), in case the argument
+                        //  is itself a `Pat` macro invocation:
+                        let binder_ty = less_quoted_ty(&binder_ty, None, &parts.this_ast)?;
                         for (ty_n, ty) in
                             parts.with_context(binder_ty).get_res(*binder)?.iter_pairs()
                         {
-                            res = res
-                                .set(*ty_n, less_quoted_ty(ty, Some(n("Pat")), &parts.this_ast)?);
+                            res = res.set(*ty_n, ty.clone());
                         }
                     }
 
@@ -309,15 +427,288 @@ pub fn macro_invocation(
                     env = env.set(*param, rhs);
                 }
             }
-            let expanded = Ast::reflect(&crate::runtime::eval::eval(&implementation.body, env)?);
+            expanding.with(|e| {
+                e.borrow_mut().push((
+                    macro_name.unhygienic_orig(),
+                    source_line(&parts.this_ast),
+                    vec![],
+                ))
+            });
+            let expanded = crate::runtime::eval::eval(&implementation.body, env);
+            let (_, invocation_line, templates) = expanding.with(|e| e.borrow_mut().pop()).unwrap();
+            let expanded = Ast::reflect(&expanded?);
+            // Only `Expr`s are marked (by being found here), since only they can be evaluated:
+            let template_line = templates
+                .into_iter()
+                .rev()
+                .find(|(template_result, _)| *template_result == expanded)
+                .map(|(_, template_line)| template_line);
+
+            if crate::expand::tracing() {
+                let arguments = grammar
+                    .binders()
+                    .into_iter()
+                    .map(|(param, depth)| {
+                        let args = parts.map_flatten_term_at_depth(
+                            param,
+                            depth,
+                            &|a: &Ast| vec![a.clone()],
+                            &|vecs: Vec<Vec<Ast>>| vecs.into_iter().flatten().collect(),
+                        );
+                        (param, args)
+                    })
+                    .collect();
+                crate::expand::record_step(crate::expand::ExpansionStep {
+                    macro_name,
+                    invocation: parts.this_ast.clone(),
+                    arguments,
+                    result: expanded.clone(),
+                    grammar: syntax.clone(),
+                });
+            }
 
             // Expand any macros produced by expansion, or that were already present in subterms:
-            Ok(crate::expand::expand(&expanded)?.reify())
+            let expanded = crate::expand::expand(&expanded)?;
+            Ok(match template_line {
+                Some(template_line) => mark_expansion_origin(
+                    expanded,
+                    macro_name.unhygienic_orig(),
+                    invocation_line,
+                    template_line,
+                ),
+                None => expanded,
+            }
+            .reify())
         })),
-        quasiquote: Both(LiteralLike, LiteralLike),
+        // If this macro was defined inside a quotation (i.e. it's being defined by another macro),
+        //  its implementation may interpolate syntax from outside, e.g. with `,,[ ⋯ ],,`.
+        // Those interpolations need to happen to the implementation that will actually run:
+        quasiquote: Both(
+            cust_rc_box!(move |parts| {
+                let walked = QQuote::walk_quasi_literally(parts.this_ast.clone(), &parts)?;
+                if !escapes_quotation(&implementation2.body, 0) {
+                    return Ok(walked);
+                }
+                // `extend_syntax` has already quasiquoted the definition; find the result:
+                let quasiquoted = quoted_implementations.with(|impls| {
+                    impls.borrow().iter().rev().flatten().find_map(|(name, impl_ast)| {
+                        if *name == macro_name.unhygienic_orig() {
+                            Some(impl_ast.clone())
+                        } else {
+                            None
+                        }
+                    })
+                });
+                let quasiquoted = match quasiquoted {
+                    Some(quasiquoted) => quasiquoted,
+                    None => return Ok(walked),
+                };
+                let form = macro_from_definition(MacroDefinition {
+                    implementation: Closure {
+                        body: splice_escapes(&implementation2.body, &quasiquoted, 0),
+                        ..implementation2.clone()
+                    },
+                    ..definition2.clone()
+                });
+                match walked.to_ast() {
+                    Node(_, parts, export) => Ok(WalkElt::from_ast(&Node(form, parts, export))),
+                    _ => icp!(),
+                }
+            }),
+            LiteralLike,
+        ),
+        macro_definition: Some(Rc::new(definition)),
     })
 }
 
+thread_local! {
+    /// For each quoted `extend_syntax` being quasiquoted, the (quasiquoted) implementations
+    ///  of the macros it defines, by name.
+    static quoted_implementations: RefCell<Vec<Vec<(Name, Ast)>>> = RefCell::new(vec![]);
+}
+
+/// The implementations of the macros defined in `syntax`, by (unfreshened) name.
+fn implementations_in(syntax: &Ast) -> Vec<(Name, Ast)> {
+    match *syntax {
+        Node(ref f, ref parts, _) if f.name == n("scope") => vec![(
+            ast_to_name(parts.get_leaf_or_panic(&n("macro_name"))).unhygienic_orig(),
+            strip_ee(strip_ee(parts.get_leaf_or_panic(&n("implementation")))).clone(),
+        )],
+        Node(_, ref parts, _) => parts.map_reduce(
+            &|sub: &Ast| implementations_in(sub),
+            &|lhs, rhs| lhs.iter().chain(rhs.iter()).cloned().collect(),
+            vec![],
+        ),
+        Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) => {
+            implementations_in(body)
+        }
+        _ => vec![],
+    }
+}
+
+/// `template`, except that each unquotation that escapes it is replaced by
+///  whatever it turned into in `quasiquoted`, which is a quasiquoted copy of `template`.
+/// (The names in `template` are the ones that the rest of the macro invocation agrees with.)
+fn splice_escapes(template: &Ast, quasiquoted: &Ast, depth: u8) -> Ast {
+    match (template, quasiquoted) {
+        (&Node(ref f, ref parts, _), _)
+            if f.name == n("unquote")
+                && escapes_quotation(parts.get_leaf_or_panic(&n("body")), depth) =>
+        {
+            quasiquoted.clone()
+        }
+        (&Node(ref f, ref parts, ref export), &Node(_, ref q_parts, _))
+            if parts.can_map_with(q_parts) =>
+        {
+            Node(
+                f.clone(),
+                parts.map_with(q_parts, &|part, q_part| splice_escapes(part, q_part, depth)),
+                export.clone(),
+            )
+        }
+        (&Ast::QuoteMore(ref body, pos), &Ast::QuoteMore(ref q_body, _)) => {
+            Ast::QuoteMore(Box::new(splice_escapes(body, q_body, depth + 1)), pos)
+        }
+        (&Ast::QuoteLess(ref body, less), &Ast::QuoteLess(ref q_body, _)) if less <= depth => {
+            Ast::QuoteLess(Box::new(splice_escapes(body, q_body, depth - less)), less)
+        }
+        (&Ast::ExtendEnv(ref body, ref beta), &Ast::ExtendEnv(ref q_body, _)) => {
+            Ast::ExtendEnv(Box::new(splice_escapes(body, q_body, depth)), beta.clone())
+        }
+        (&Ast::ExtendEnvPhaseless(ref body, ref beta), &Ast::ExtendEnvPhaseless(ref q_body, _)) => {
+            Ast::ExtendEnvPhaseless(Box::new(splice_escapes(body, q_body, depth)), beta.clone())
+        }
+        _ => template.clone(),
+    }
+}
+
+/// Does `a` contain an unquotation that escapes further than the quotations inside `a`?
+fn escapes_quotation(a: &Ast, depth: u8) -> bool {
+    match *a {
+        Node(_, ref parts, _) => parts.map_reduce(
+            &|sub: &Ast| escapes_quotation(sub, depth),
+            &|lhs: &bool, rhs: &bool| *lhs || *rhs,
+            false,
+        ),
+        Ast::QuoteMore(ref body, _) => escapes_quotation(body, depth + 1),
+        Ast::QuoteLess(ref body, less) => less > depth || escapes_quotation(body, depth - less),
+        Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) => {
+            escapes_quotation(body, depth)
+        }
+        _ => false,
+    }
+}
+
+custom_derive! {
+    /// What a macro invocation `Form` is made from;
+    ///  enough to rebuild it (e.g. after serialization).
+    #[derive(Clone, Reifiable)]
+    pub struct MacroDefinition {
+        pub grammar: FormPat,
+        pub macro_name: Name,
+        /// The nonterminal that the macro extends (and so what its invocations produce).
+        pub nt: Name,
+        pub implementation: Closure,
+        pub export_names: Vec<Name>,
+        /// The grammar where the macro was defined, which is needed to unparse its syntax.
+        /// (Its own invocation form isn't in it, so there's no reference cycle.)
+        pub syntax: SynEnv,
+    }
+}
+
+/// `pat`, but with the macros invoked by the first form of each pair in `replacements`
+///  invoked by the second form instead.
+fn replacing_macros(pat: &Rc<FormPat>, replacements: &[(Rc<Form>, Rc<Form>)]) -> Rc<FormPat> {
+    match **pat {
+        Scope(ref f, ref export) => match replacements.iter().find(|(old, _)| old == f) {
+            Some((_, new)) => Rc::new(Scope(new.clone(), export.clone())),
+            None => pat.clone(),
+        },
+        Alt(ref subs) => {
+            Rc::new(Alt(subs.iter().map(|sub| replacing_macros(sub, replacements)).collect()))
+        }
+        Biased(ref plan_a, ref plan_b) => Rc::new(Biased(
+            replacing_macros(plan_a, replacements),
+            replacing_macros(plan_b, replacements),
+        )),
+        _ => pat.clone(),
+    }
+}
+
+/// Is `f` a macro invocation (as opposed to a core form)?
+pub fn is_macro_invocation(f: &Form) -> bool { f.name.unhygienic_orig() == n("macro_invocation") }
+
+/// The name of the macro that `f` invokes, if it's a macro invocation.
+pub fn invoked_macro_name(f: &Form) -> Option<Name> {
+    if !is_macro_invocation(f) {
+        return None;
+    }
+    match *f.grammar {
+        Seq(ref subs) => match *subs[0] {
+            Named(_, ref body) => match **body {
+                Anyways(Ast::VariableReference(name)) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The macros that are alternatives of `pat` (which is probably a nonterminal's definition),
+///  as `Scope`s.
+pub fn macros_in(pat: &Rc<FormPat>) -> Vec<(Name, Rc<FormPat>)> {
+    match **pat {
+        Scope(ref f, _) => invoked_macro_name(f).map(|name| (name, pat.clone())).into_iter().collect(),
+        Alt(ref subs) => subs.iter().flat_map(|sub| macros_in(sub)).collect(),
+        Biased(ref plan_a, ref plan_b) => {
+            let mut res = macros_in(plan_a);
+            res.append(&mut macros_in(plan_b));
+            res
+        }
+        _ => vec![],
+    }
+}
+
+/// `pat`, without the macros that `remove` picks out.
+pub fn without_macros(pat: &Rc<FormPat>, remove: &dyn Fn(Name, &Rc<Form>) -> bool) -> Rc<FormPat> {
+    match **pat {
+        Scope(ref f, _) => match invoked_macro_name(f) {
+            Some(name) if remove(name, f) => Rc::new(Impossible),
+            _ => pat.clone(),
+        },
+        Alt(ref subs) => Rc::new(Alt(subs
+            .iter()
+            .map(|sub| without_macros(sub, remove))
+            .filter(|sub| **sub != Impossible)
+            .collect())),
+        Biased(ref plan_a, ref plan_b) => {
+            Rc::new(Biased(without_macros(plan_a, remove), without_macros(plan_b, remove)))
+        }
+        _ => pat.clone(),
+    }
+}
+
+/// The names of the macros defined (not merely re-used) in `syntax`, which is unevaluated.
+/// (Each comes with the `Ast` that names it, for error reporting.)
+fn macros_defined_in(syntax: &Ast) -> Vec<(Name, Ast)> {
+    match *syntax {
+        Node(ref f, ref parts, _) if f.name == n("scope") => {
+            let name_ast = parts.get_leaf_or_panic(&n("macro_name"));
+            vec![(ast_to_name(name_ast), name_ast.clone())]
+        }
+        Node(_, ref parts, _) => parts.map_reduce(
+            &|sub: &Ast| macros_defined_in(sub),
+            &|lhs, rhs| lhs.iter().chain(rhs.iter()).cloned().collect(),
+            vec![],
+        ),
+        Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) => {
+            macros_defined_in(body)
+        }
+        _ => vec![],
+    }
+}
+
 /// What should `t` be, if matched under a repetition?
 /// A tuple, driven by whatever names are `forall`ed in `env`.
 fn repeated_type(t: &Ty, env: &Assoc<Name, Ty>) -> Result<Ty, crate::ty::TypeError> {
@@ -329,7 +720,7 @@ fn repeated_type(t: &Ty, env: &Assoc<Name, Ty>) -> Result<Ty, crate::ty::TypeErr
     }
 
     if drivers.is_empty() {
-        ty_err!(NeedsDriver (()) at t.0);
+        ty_err!(NeedsDriver at t.0);
     }
 
     Ok(ty!({"Type" "tuple" : "component" => (,seq vec![ast!({"Type" "dotdotdot_type" :
@@ -339,7 +730,7 @@ fn repeated_type(t: &Ty, env: &Assoc<Name, Ty>) -> Result<Ty, crate::ty::TypeErr
 }
 
 pub fn make_core_macro_forms() -> SynEnv {
-    let trivial_type_form = crate::core_type_forms::type_defn("unused", form_pat!((impossible)));
+    let trivial_type_form = crate::core_type_forms::unused_type.with(|u_t| u_t.clone());
 
     let beta_grammar = forms_to_form_pat_export![
         syntax_syntax!( ((lit "nothing")) Nothing ) => [],
@@ -543,7 +934,35 @@ pub fn make_core_macro_forms() -> SynEnv {
                 let nt = ast_to_name(&parts.get_term(n("nt")));
                 Ok(Rc::new(Call(nt)).reify())
             })),
-            quasiquote: Both(LiteralLike, LiteralLike)
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
+        }) => [],
+        // `Capture` is positive (has to be under a `Named`).
+        // It's the escape hatch from hygiene: the part is a binder that the macro user didn't write,
+        //  but that their syntax can refer to (as long as a `Beta` says where).
+        Rc::new(Form {
+            name: n("capture"),
+            grammar: Rc::new(form_pat!(
+                [(lit "capture"), (named "body", (call "Syntax")),
+                 (lit "="), (scan r"(\s*')"), (named "captured", (scan r"([^']+)")), (scan r"(')")])),
+            type_compare: Both(NotWalked,NotWalked), // Not a type
+            synth_type: Both(cust_rc_box!(|parts| {
+                match parts.get_term(n("body")) {
+                    Node(ref f, ref body_parts, _) if f.name == n("call_with_type") => {
+                        let nt = ast_to_name(body_parts.get_leaf_or_panic(&n("nt")));
+                        if nt != n("Atom") && nt != n("Pat") {
+                            ty_err!(NonBinderCapture(nt) at parts.get_term(n("body")));
+                        }
+                    }
+                    _ => ty_err!(AnnotationRequired(parts.get_term(n("body"))) at parts.this_ast)
+                }
+                parts.get_res(n("body"))
+            }), NotWalked),
+            eval: Positive(cust_rc_box!(|parts| {
+                Ok(Anyways(Atom(ast_to_name(&parts.get_term(n("captured"))))).reify())
+            })),
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
         }) => [],
         // `Import` is positive (has to be under a `Named`)
         Rc::new(Form {
@@ -559,7 +978,28 @@ pub fn make_core_macro_forms() -> SynEnv {
                 Ok(NameImport(Rc::new(FormPat::reflect(&parts.get_res(n("body"))?)),
                               Beta::reflect(&parts.get_res(n("imported"))?)).reify())
             })),
-            quasiquote: Both(LiteralLike, LiteralLike)
+            quasiquote: Both(LiteralLike, LiteralLike),
+            macro_definition: None,
+        }) => [],
+        // Re-use a macro that's already defined (e.g. to add it to another nonterminal):
+        syntax_syntax!( ([(lit "macro"), (named "macro_name", varref)]) Macro {
+            |parts| {
+                let name = vr_to_name(&parts.get_term(n("macro_name")));
+                // It's already bound, so this binds nothing new:
+                match parts.env.find(&name) {
+                    Some(_) => Ok(Assoc::new()),
+                    None => ty_err!(UnboundName(
+                        name, crate::ty::did_you_mean(name, parts.env.iter_keys().cloned()))
+                        at parts.this_ast)
+                }
+            }
+        } {
+            |parts| {
+                // `extend_syntax` binds the macros that are currently defined (if it's not one,
+                //  the typechecker will complain):
+                let name = vr_to_name(&parts.get_term(n("macro_name")));
+                Ok(parts.env.find(&name).cloned().unwrap_or_else(|| Impossible.reify()))
+            }
         }) => [],
         // TODO: implement syntax for ComputeSyntax
         // Not sure if `Scope` syntax should be positive or negative.
@@ -580,7 +1020,7 @@ pub fn make_core_macro_forms() -> SynEnv {
             |parts| {
                 let return_ty = parts.switch_mode::<SynthTy>().get_res(n("implementation"))?;
                 let mut arguments : Vec<(Name, Ty)> = parts.get_res(n("syntax"))?
-                    .iter_pairs().cloned().collect();
+                    .iter_pairs().map(|(k, v)| (*k, v.clone())).collect();
                 arguments.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0) ); // Pick a canonical order
                 let ty_params = &parts.get_rep_term(n("param")).iter().map(
                             |p| ast_to_name(p)).collect::<Vec<_>>();
@@ -595,9 +1035,17 @@ pub fn make_core_macro_forms() -> SynEnv {
                 let implementation = strip_ee(
                     &strip_ee(&parts.get_term(n("implementation")))).clone();
 
+                let grammar = FormPat::reflect(&parts.get_res(n("syntax"))?);
+
+                // The names in `syntax` may have been freshened; refer to them the same way:
+                let binders = grammar.binders();
+                let export_names = parts.get_rep_term(n("export")).iter().map(|e| {
+                    let name = ast_to_name(e);
+                    binders.iter().map(|(b, _)| *b)
+                        .find(|b| b.unhygienic_orig() == name.unhygienic_orig())
+                        .unwrap_or(name)
+                }).collect::<Vec<Name>>();
                 let mut export = ExportBeta::Nothing;
-                let export_names = parts.get_rep_term(n("export")).iter()
-                    .map(ast_to_name).collect::<Vec<Name>>();
                 for name in &export_names {
                     export = ExportBeta::Shadow(
                         Box::new(ExportBeta::Use(*name)),
@@ -606,7 +1054,7 @@ pub fn make_core_macro_forms() -> SynEnv {
 
                 // This macro invocation (will replace `syntax`):
                 Ok(Scope(macro_invocation(
-                        FormPat::reflect(&parts.get_res(n("syntax"))?),
+                        grammar,
                         ast_to_name(&parts.get_term(n("macro_name"))),
                         crate::runtime::eval::Closure{ body: implementation,
                             params: macro_params,
@@ -624,14 +1072,24 @@ pub fn make_core_macro_forms() -> SynEnv {
         "Beta" => Rc::new(beta_grammar))
 }
 
+/// The named parts of the partial parse that an `extend` hook gets.
+pub fn partial_parse_parts(info: &Ast) -> crate::util::mbe::EnvMBE<Ast> {
+    // TODO: getting a `Shape` (with an element for each `star`, and the `(lit "in")`)
+    //  must be a parser bug
+    extract!((info) Ast::Shape = (ref subs) =>
+        subs.iter().fold(crate::util::mbe::EnvMBE::new(), |parts, sub| match *sub {
+            Ast::IncompleteNode(ref sub_parts) => parts.merge(sub_parts),
+            _ => parts,
+        }))
+}
+
 pub fn extend_syntax() -> Rc<Form> {
-    use crate::earley::ParseContext;
+    use crate::{earley::ParseContext, walk_mode::WalkMode};
     let perform_extension = move |pc: ParseContext, extension_info: Ast| -> ParseContext {
-        let bnf_parts =
-            // TODO: getting a `Shape` (the second element is the `(lit "in")`) must be a parser bug
-            extract!((&extension_info) Ast::Shape = (ref subs) =>
-                extract!((&subs[0]) Ast::IncompleteNode = (ref parts) => parts));
+        let bnf_parts = partial_parse_parts(&extension_info);
 
+        let removed: Vec<Name> =
+            bnf_parts.get_rep_leaf_or_panic(n("removed")).iter().map(|a| vr_to_name(*a)).collect();
         let nts: Vec<Name> =
             bnf_parts.get_rep_leaf_or_panic(n("nt")).iter().map(|a| ast_to_name(*a)).collect();
         let ops: Vec<bool> = bnf_parts
@@ -641,11 +1099,51 @@ pub fn extend_syntax() -> Rc<Form> {
             .collect();
         let rhses: Vec<&Ast> = bnf_parts.get_rep_leaf_or_panic(n("rhs"));
 
+        // Macros are referred to by name (e.g. by the `macro` syntax), so bind them:
+        let mut existing_macros = vec![];
+        for (_, pat) in pc.grammar.iter_pairs() {
+            existing_macros.append(&mut macros_in(pat));
+        }
+        let mut macro_env = pc.eval_ctxt.env.clone();
+        for (name, pat) in &existing_macros {
+            macro_env = macro_env.set(*name, (**pat).reify());
+        }
+        let rhs_form_pats: Vec<FormPat> = rhses
+            .into_iter()
+            .map(|rhs| {
+                FormPat::reflect(
+                    &crate::ast_walk::walk(rhs, &pc.eval_ctxt.with_environment(macro_env.clone()))
+                        .unwrap(),
+                )
+            })
+            .collect();
+
+        // A newly-defined macro shadows any existing macro with the same name
+        //  (otherwise, invocations of the old one would be typechecked against the new one):
+        let mut shadowed = removed;
+        let mut new_macros = vec![];
+        for (nt, rhs_form_pat) in nts.iter().zip(rhs_form_pats.iter()) {
+            for (name, pat) in macros_in(&Rc::new(rhs_form_pat.clone())) {
+                if !existing_macros.iter().any(|(_, old)| old == &pat) {
+                    shadowed.push(name);
+                    new_macros.push((*nt, pat));
+                }
+            }
+        }
+        let mut syn_env = pc.grammar.clone();
+        if !shadowed.is_empty() {
+            for (nt, pat) in pc.grammar.iter_pairs() {
+                if macros_in(pat).iter().any(|(name, _)| shadowed.contains(name)) {
+                    syn_env =
+                        syn_env.set(*nt, without_macros(pat, &|name, _| shadowed.contains(&name)));
+                }
+            }
+        }
+
         // Figure out the  the syntax extension:
-        let mut syn_env = pc.grammar;
-        for ((nt, extend), rhs) in nts.into_iter().zip(ops.into_iter()).zip(rhses.into_iter()) {
-            let rhs_form_pat =
-                FormPat::reflect(&crate::ast_walk::walk(rhs, &pc.eval_ctxt).unwrap());
+        for ((nt, extend), rhs_form_pat) in
+            nts.into_iter().zip(ops.into_iter()).zip(rhs_form_pats.into_iter())
+        {
             syn_env = syn_env.set(
                 nt,
                 Rc::new(if extend {
@@ -656,17 +1154,31 @@ pub fn extend_syntax() -> Rc<Form> {
             )
         }
 
+        // The new macros need to know the grammar they're defined in (e.g. for expansion traces).
+        // It's `syn_env`, except that it contains the old versions of them:
+        let mut replacements = vec![];
+        for (nt, pat) in new_macros {
+            if let Scope(ref f, _) = *pat {
+                let def = (**f.macro_definition.as_ref().unwrap()).clone();
+                let new_form =
+                    macro_from_definition(MacroDefinition { nt, syntax: syn_env.clone(), ..def });
+                replacements.push((f.clone(), new_form));
+            }
+        }
+        let syn_env = syn_env.map(|pat| replacing_macros(pat, &replacements));
+
         ParseContext { grammar: syn_env, type_ctxt: pc.type_ctxt, eval_ctxt: pc.eval_ctxt }
     };
 
-    let trivial_type_form = crate::core_type_forms::type_defn("unused", form_pat!((impossible)));
+    let trivial_type_form = crate::core_type_forms::unused_type.with(|u_t| u_t.clone());
 
     Rc::new(Form {
         name: n("extend_syntax"),
         grammar: Rc::new(form_pat!(
             [(lit "extend_syntax"),
              (extend
-                [(star [(named "nt", atom),
+                [(star [(lit "without"), (named "removed", varref), (lit ";")]),
+                 (star [(named "nt", atom),
                    (named "operator", (alt (lit "::="), (lit "::=also"))),
                    (named "rhs", (call "Syntax")),
                    (lit ";")]),
@@ -675,7 +1187,28 @@ pub fn extend_syntax() -> Rc<Form> {
                     (import_phaseless [* ["rhs" == {trivial_type_form ; }]], (call "Expr"))),
                 perform_extension)])),
         type_compare: Both(NotWalked, NotWalked),
-        synth_type: Positive(Body(n("body"))),
+        synth_type: Positive(cust_rc_box!(|parts| {
+            // Macro names have to be unique, or we'd lose track of which is which:
+            let mut defined = vec![];
+            for rhs in parts.get_rep_term(n("rhs")) {
+                for (name, definition) in macros_defined_in(&rhs) {
+                    // (They've been freshened, so compare the names the programmer wrote.)
+                    if defined.contains(&name.unhygienic_orig()) {
+                        ty_err!(MacroNameCollision(name) at definition);
+                    }
+                    defined.push(name.unhygienic_orig());
+                }
+            }
+            for removed in parts.get_rep_term(n("removed")) {
+                let name = vr_to_name(&removed);
+                if parts.env.find(&name).is_none() {
+                    ty_err!(UnboundName(
+                        name, crate::ty::did_you_mean(name, parts.env.iter_keys().cloned()))
+                        at removed);
+                }
+            }
+            parts.get_res(n("body"))
+        })),
         eval: Positive(cust_rc_box!(move |extend_syntax_parts| {
             // HACK: since the macros have been expanded away, `rhs` needs to be be unbound
             crate::ast_walk::walk::<Eval>(
@@ -683,10 +1216,91 @@ pub fn extend_syntax() -> Rc<Form> {
                 &extend_syntax_parts,
             )
         })),
-        quasiquote: Both(LiteralLike, LiteralLike),
+        // Quoted (i.e. a macro is defining macros). The macro invocations in `body` need to know
+        //  what the definitions in `rhs` turn into (see `macro_invocation`):
+        quasiquote: Both(
+            cust_rc_box!(|parts| {
+                let mut impls = vec![];
+                for rhs in parts.get_rep_term(n("rhs")) {
+                    let rhs = crate::ast_walk::walk::<QQuote>(&rhs, &parts)?;
+                    impls.append(&mut implementations_in(&rhs.to_ast()));
+                }
+                quoted_implementations.with(|qi| qi.borrow_mut().push(impls));
+                let res = QQuote::walk_quasi_literally(parts.this_ast.clone(), &parts);
+                quoted_implementations.with(|qi| qi.borrow_mut().pop());
+                res
+            }),
+            LiteralLike,
+        ),
+        macro_definition: None,
     })
 }
 
+thread_local! {
+    /// The types of what macro implementations can use when they run:
+    ///  the core values, and whatever enclosing `for_syntax`es have bound.
+    static syntax_phase_types: RefCell<Assoc<Name, Ty>> =
+        RefCell::new(crate::runtime::core_values::core_types());
+}
+
+/// Replace the core types that macro implementations see (e.g. to add the standard library).
+pub fn set_syntax_phase_types(types: Assoc<Name, Ty>) {
+    syntax_phase_types.with(|t| *t.borrow_mut() = types)
+}
+
+/// `for_syntax name = value in body` makes `name` available to the macros defined in `body`
+///  (and, like an ordinary binding, to `body` itself).
+/// `value` is evaluated while `body` is being parsed (that's when macros are defined),
+///  so it can only refer to core values and names bound by enclosing `for_syntax`es.
+pub fn for_syntax() -> Rc<Form> {
+    use crate::earley::ParseContext;
+    let perform_binding = move |pc: ParseContext, binding_info: Ast| -> ParseContext {
+        let binding_parts = partial_parse_parts(&binding_info);
+        let name = ast_to_name(binding_parts.get_leaf_or_panic(&n("name")));
+        let value = binding_parts.get_leaf_or_panic(&n("value"));
+
+        // Don't evaluate it if it's ill-typed; the typechecker will complain about it later.
+        let value_ty = match crate::ty::synth_type(value, pc.type_ctxt.env.clone()) {
+            Ok(ty) => ty,
+            Err(_) => return pc,
+        };
+        let value = match crate::expand::expand(value)
+            .and_then(|value| crate::runtime::eval::eval(&value, pc.eval_ctxt.env.clone()))
+        {
+            Ok(value) => value,
+            Err(()) => return pc,
+        };
+        ParseContext {
+            type_ctxt: pc.type_ctxt.with_environment(pc.type_ctxt.env.set(name, value_ty)),
+            eval_ctxt: pc.eval_ctxt.with_environment(pc.eval_ctxt.env.set(name, value)),
+            ..pc
+        }
+    };
+
+    typed_form!("for_syntax",
+        (extend [(lit "for_syntax"), (named "name", atom), (lit "="),
+                 (named "value", (call "Expr")), (lit "in")],
+            (named "body", (import ["name" : "value"], (call "Expr"))),
+            perform_binding),
+        cust_rc_box!(|parts| {
+            let name = ast_to_name(&parts.get_term(n("name")));
+            let outer_types = syntax_phase_types.with(|types| types.borrow().clone());
+            let value_ty = parts.with_environment(outer_types.clone()).get_res(n("value"))?;
+            syntax_phase_types.with(|types| {
+                *types.borrow_mut() = outer_types.set(name, value_ty.clone())
+            });
+            // HACK: like `Body(n("body"))`, but without typechecking `value` again
+            //  (in the wrong environment):
+            let res = crate::ast_walk::walk::<SynthTy>(
+                strip_ee(&parts.get_term(n("body"))),
+                &parts.with_environment(parts.env.set(name, value_ty)),
+            );
+            syntax_phase_types.with(|types| *types.borrow_mut() = outer_types);
+            res
+        }),
+        Body(n("body")))
+}
+
 #[test]
 fn formpat_reflection() {
     use crate::{core_forms::find_form, runtime::eval::eval_top};
@@ -0,0 +1,494 @@
+// Modules!
+//
+// A module is an ordinary Unseemly file whose last form is an `export`:
+//
+// extend_syntax ⋯ dbl ⋯ in
+// let_type Maybe = ⋯ in
+// (.[ double : [Int -> Int] .
+//     export values double ; types Maybe ; syntax dbl ; ].
+//  .[ x : Int . (plus x x) ].)
+//
+// Another file can `import "that_file.≉" in ⋯` to use `double`, `Maybe`, and `dbl`.
+// Because `dbl` changes how the rest of the file parses,
+//  the module is loaded (parsed, typechecked, and evaluated) while the importer is being parsed.
+// Each module is only loaded once (per thread), so importing it twice is cheap.
+//
+// Modules don't see the importer's names (not even the prelude's); they start from the core.
+//
+// The standard library (`std.≉`) is a module, too, but it's built into the binary,
+//  and `main.rs` makes its exports available to every program (unless `--no-std` is passed).
+
+use crate::{
+    ast::Ast,
+    core_forms::{ast_to_name, vr_to_name},
+    core_macro_forms::{macros_in, partial_parse_parts, without_macros},
+    earley::ParseContext,
+    form::Form,
+    grammar::{FormPat, SynEnv},
+    name::*,
+    runtime::eval::{Eval, Value},
+    ty::{SynthTy, Ty},
+    util::assoc::Assoc,
+};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+/// What a module makes available to the files that import it.
+pub struct Module {
+    /// Exported macros, with the nonterminal each extends.
+    macros: Vec<(Name, Name, Rc<FormPat>)>,
+    /// Nonterminals that the module defined (probably for its macros to use),
+    ///  or replaced (e.g. `DefaultSeparator`, to add comments).
+    nonterminals: Vec<(Name, Rc<FormPat>)>,
+    /// The types of the exported values, and the definitions of the exported types.
+    types: Assoc<Name, Ty>,
+    values: Assoc<Name, Value>,
+    /// Changes whenever this module (or anything it imports) changes.
+    fingerprint: u64,
+}
+
+impl Module {
+    /// `grammar`, with the macros in this module available.
+    /// (They shadow any macros that have the same names.)
+    pub fn extend_grammar(&self, grammar: &SynEnv) -> SynEnv {
+        let mut res = grammar.clone();
+        let core_forms = crate::core_forms::get_core_forms();
+        for (nt, pat) in &self.nonterminals {
+            // (Don't clobber the importer's own nonterminals, unless this replaces a core one.)
+            if res.find(nt).is_none() || core_forms.find(nt).is_some() {
+                res = res.set(*nt, pat.clone());
+            }
+        }
+        let exported: Vec<Name> = self.macros.iter().map(|(name, _, _)| *name).collect();
+        for (nt, pat) in grammar.iter_pairs() {
+            if macros_in(pat).iter().any(|(name, _)| exported.contains(&name.unhygienic_orig())) {
+                res = res.set(
+                    *nt,
+                    without_macros(pat, &|name, _| exported.contains(&name.unhygienic_orig())),
+                );
+            }
+        }
+        for (_, nt, pat) in &self.macros {
+            let existing = (**res.find_or_panic(nt)).clone();
+            res = res.set(*nt, Rc::new(form_pat!((alt (, (**pat).clone()), (, existing)))));
+        }
+        res
+    }
+
+    /// The types of the exported values, and the definitions of the exported types.
+    pub fn types(&self) -> &Assoc<Name, Ty> { &self.types }
+
+    pub fn values(&self) -> &Assoc<Name, Value> { &self.values }
+}
+
+/// The source of the standard library.
+static STD_SOURCE: &str = include_str!("std.≉");
+
+/// Change this whenever the standard library changes.
+pub const STD_VERSION: u32 = 2;
+
+/// The modules that a module imports (as written), and their fingerprints.
+type Imports = Vec<(String, u64)>;
+
+thread_local! {
+    /// Where `import` paths are relative to (outside of a module; inside, it's the module's).
+    pub static import_base_dir: RefCell<PathBuf> = RefCell::new(PathBuf::from("."));
+    /// Where compiled modules are saved, if anywhere.
+    pub static cache_dir: RefCell<Option<PathBuf>> = RefCell::new(None);
+    /// Modules that have already been loaded, by canonical path.
+    static loaded_modules: RefCell<HashMap<PathBuf, Rc<Module>>> = RefCell::new(HashMap::new());
+    /// Modules that are in the middle of being loaded (to catch import cycles),
+    ///  and the modules that each has imported so far (with their fingerprints).
+    static loading_modules: RefCell<Vec<(PathBuf, Imports)>> = RefCell::new(vec![]);
+    /// The grammar at the most recently parsed `export`.
+    static exported_syntax: RefCell<Option<SynEnv>> = RefCell::new(None);
+}
+
+/// Find the module at `path` (relative to the importing file), loading it if necessary.
+pub fn load_module(path: &str) -> Result<Rc<Module>, String> {
+    let dir = loading_modules
+        .with(|lm| lm.borrow().last().and_then(|(p, _)| p.parent().map(|d| d.to_path_buf())))
+        .unwrap_or_else(|| import_base_dir.with(|d| d.borrow().clone()));
+    let canonical_path = std::fs::canonicalize(dir.join(path)).map_err(|e| e.to_string())?;
+
+    let module = match loaded_modules.with(|lm| lm.borrow().get(&canonical_path).cloned()) {
+        Some(module) => module,
+        None => {
+            let source = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+            let module = Rc::new(load_module_uncached(&canonical_path, source)?);
+            loaded_modules.with(|lm| lm.borrow_mut().insert(canonical_path, module.clone()));
+            module
+        }
+    };
+
+    // Whatever imported this depends on it:
+    loading_modules.with(|lm| {
+        if let Some((_, ref mut imports)) = lm.borrow_mut().last_mut() {
+            if !imports.iter().any(|(imported, _)| imported == path) {
+                imports.push((path.to_owned(), module.fingerprint));
+            }
+        }
+    });
+    Ok(module)
+}
+
+/// Load the standard library (which is built in, so it doesn't have a real path).
+pub fn load_std() -> Result<Rc<Module>, String> {
+    let path = PathBuf::from(format!("std-v{}.≉", STD_VERSION));
+    if let Some(module) = loaded_modules.with(|lm| lm.borrow().get(&path).cloned()) {
+        return Ok(module);
+    }
+    let module = Rc::new(load_module_uncached(&path, STD_SOURCE.to_owned())?);
+    loaded_modules.with(|lm| lm.borrow_mut().insert(path, module.clone()));
+    Ok(module)
+}
+
+fn load_module_uncached(path: &PathBuf, source: String) -> Result<Module, String> {
+    if loading_modules.with(|lm| lm.borrow().iter().any(|(p, _)| p == path)) {
+        return Err("it imports itself (perhaps indirectly)".to_owned());
+    }
+
+    loading_modules.with(|lm| lm.borrow_mut().push((path.clone(), vec![])));
+    let outer_exported_syntax = exported_syntax.with(|es| es.borrow_mut().take());
+    let module = match read_cache(path, &source) {
+        Some(module) => Ok((module, None)),
+        None => run_module(&source).map(|(module, program)| (module, Some(program))),
+    };
+    exported_syntax.with(|es| *es.borrow_mut() = outer_exported_syntax);
+    let (_, imports) = loading_modules.with(|lm| lm.borrow_mut().pop()).unwrap();
+
+    let (mut module, program) = module?;
+    module.fingerprint = hash_of(&(&source, &imports));
+    if let Some(program) = program {
+        write_cache(path, &source, &imports, &module, &program);
+    }
+    Ok(module)
+}
+
+/// FNV-1a. Unlike `DefaultHasher`, it's the same in every build, so its results can be saved.
+struct StableHasher(u64);
+
+impl std::hash::Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 { self.0 }
+}
+
+fn hash_of<T: std::hash::Hash>(x: &T) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = StableHasher(0xcbf2_9ce4_8422_2325);
+    x.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parses, typechecks, and expands a module, returning it and its expanded source.
+fn run_module(source: &str) -> Result<(Module, Ast), String> {
+    let core_forms = crate::core_forms::get_core_forms();
+    let ast = crate::grammar::parse(
+        &crate::core_forms::outermost_form(),
+        &core_forms,
+        crate::runtime::core_values::get_core_envs(),
+        source,
+    )
+    .map_err(|e| e.msg)?;
+
+    let grammar = exported_syntax
+        .with(|es| es.borrow_mut().take())
+        .ok_or_else(|| "it doesn't `export` anything".to_owned())?;
+
+    let ty = crate::ty::synth_type_all(&ast, crate::runtime::core_values::core_types())
+        .map_err(|errs| errs.iter().map(|e| format!("{}", e)).collect::<Vec<_>>().join("\n"))?;
+    let program = crate::expand::expand(&ast).map_err(|_| "it failed while running".to_owned())?;
+    let values = run_program(&program)?;
+
+    // `export` produces a struct (and its type is a struct type, which includes exported types):
+    let types = match ty.0 {
+        Ast::Node(_, ref parts, _) if parts.get_rep_leaf(n("component_name")).is_some() => {
+            let mut types = Assoc::new();
+            for component in parts.march_all(&[n("component_name")]) {
+                types = types.set(
+                    ast_to_name(component.get_leaf_or_panic(&n("component_name"))),
+                    Ty(component.get_leaf_or_panic(&n("component")).clone()),
+                );
+            }
+            types
+        }
+        _ => return Err("its last form isn't an `export`".to_owned()),
+    };
+
+    let mut macros = vec![];
+    for macro_name in exported_macros(&ast) {
+        let mut found = false;
+        for (nt, pat) in grammar.iter_pairs() {
+            for (name, macro_pat) in macros_in(pat) {
+                if name.unhygienic_orig() == macro_name
+                    && !macros.iter().any(|(_, _, p)| Rc::ptr_eq(p, &macro_pat))
+                {
+                    macros.push((macro_name, *nt, macro_pat));
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            return Err(format!("it exports `{}`, but there's no macro by that name", macro_name));
+        }
+    }
+    let nonterminals = grammar
+        .iter_pairs()
+        .filter(|(nt, pat)| match core_forms.find(nt) {
+            None => true,
+            // Replaced with `::=` (as opposed to extended with macros)?
+            Some(core_pat) => !Rc::ptr_eq(core_pat, pat) && macros_in(pat).is_empty(),
+        })
+        .map(|(nt, pat)| (*nt, pat.clone()))
+        .collect();
+
+    Ok((Module { macros, nonterminals, types, values, fingerprint: 0 }, program))
+}
+
+/// Runs an expanded module, producing its exported values.
+fn run_program(program: &Ast) -> Result<Assoc<Name, Value>, String> {
+    match crate::runtime::eval::eval(program, crate::runtime::core_values::core_values()) {
+        Ok(Value::Struct(values)) => Ok(values),
+        Ok(_) => Err("its last form isn't an `export`".to_owned()),
+        Err(_) => Err("it failed while running".to_owned()),
+    }
+}
+
+// A compiled module is cached as an S-expression (see `serialize.rs`):
+//  (unseemly-module ‹version› ‹source hash› ‹imports› ‹types› ‹nonterminals› ‹macros› ‹program›)
+// It's only used if the source is unchanged and each import still has the same fingerprint
+//  (which covers its source, and, recursively, its imports).
+// Values are recomputed by running the expanded program, which is usually quick.
+// Anything that can't be serialized (e.g. a macro whose implementation refers to a `for_syntax`
+//  value containing a built-in function) just means that the module doesn't get cached.
+
+/// Change this when the format (or the meaning of what's in it) changes.
+const CACHE_VERSION: &str = "3";
+
+/// Cached code refers to core forms by name, so it's only good for the same Unseemly
+///  (and the same core forms, in case they've changed without the version changing).
+fn cache_version() -> String {
+    format!(
+        "{}-{}-{:016x}",
+        CACHE_VERSION,
+        env!("CARGO_PKG_VERSION"),
+        hash_of(&crate::serialize::core_forms_description())
+    )
+}
+
+fn cache_file(path: &PathBuf) -> Option<PathBuf> {
+    let dir = cache_dir.with(|d| d.borrow().clone())?;
+    let file_name = path.file_name()?.to_string_lossy();
+    Some(dir.join(format!("{}-{:016x}.sexp", file_name, hash_of(path))))
+}
+
+fn read_cache(path: &PathBuf, source: &str) -> Option<Module> {
+    use crate::serialize::{untag, Deserializer, Serializable, Sexp};
+
+    let text = std::fs::read_to_string(cache_file(path)?).ok()?;
+    let sexp = Sexp::parse(&text).ok()?;
+    let d = &mut Deserializer::default();
+    match untag(&sexp).ok()? {
+        (
+            "unseemly-module",
+            [
+                Sexp::Atom(version),
+                Sexp::Atom(source_hash),
+                imports,
+                types,
+                nonterminals,
+                macros,
+                program,
+            ],
+        ) => {
+            if *version != cache_version() || *source_hash != format!("{:016x}", hash_of(&source)) {
+                return None;
+            }
+            for (import, fingerprint) in Imports::deserialize(imports, d).ok()? {
+                if load_module(&import).ok()?.fingerprint != fingerprint {
+                    return None;
+                }
+            }
+            Some(Module {
+                types: Serializable::deserialize(types, d).ok()?,
+                nonterminals: Serializable::deserialize(nonterminals, d).ok()?,
+                macros: Serializable::deserialize(macros, d).ok()?,
+                values: run_program(&Ast::deserialize(program, d).ok()?).ok()?,
+                fingerprint: 0,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn write_cache(path: &PathBuf, source: &str, imports: &Imports, module: &Module, program: &Ast) {
+    use crate::serialize::{tagged, Serializable, Serializer, Sexp};
+
+    let file = match cache_file(path) {
+        Some(file) => file,
+        None => return,
+    };
+    let s = &mut Serializer::default();
+    let serialized = (|| {
+        Ok::<_, String>(tagged("unseemly-module", vec![
+            Sexp::Atom(cache_version()),
+            Sexp::Atom(format!("{:016x}", hash_of(&source))),
+            imports.to_vec().serialize(s)?,
+            module.types.serialize(s)?,
+            module.nonterminals.serialize(s)?,
+            module.macros.serialize(s)?,
+            program.serialize(s)?,
+        ]))
+    })();
+    // Caching is just an optimization, so don't complain if it doesn't work out:
+    if let (Ok(serialized), Some(dir)) = (serialized, file.parent()) {
+        let _ = std::fs::create_dir_all(dir);
+        let _ = std::fs::write(&file, format!("{}", serialized));
+    }
+}
+
+/// `ast`, with every mention of the names in `renaming` replaced, even binders.
+/// (This is only safe because the names being replaced are unique.)
+fn rename_everywhere(ast: &Ast, renaming: &Assoc<Name, Name>) -> Ast {
+    let rename = |name: &Name| *renaming.find(name).unwrap_or(name);
+    match *ast {
+        Ast::VariableReference(name) => Ast::VariableReference(rename(&name)),
+        Ast::Atom(name) => Ast::Atom(rename(&name)),
+        Ast::Node(ref f, ref parts, ref export) => Ast::Node(
+            f.clone(),
+            parts.map(&mut |part: &Ast| rename_everywhere(part, renaming)),
+            export.clone(),
+        ),
+        Ast::ExtendEnv(ref body, ref beta) => {
+            Ast::ExtendEnv(Box::new(rename_everywhere(body, renaming)), beta.clone())
+        }
+        Ast::ExtendEnvPhaseless(ref body, ref beta) => {
+            Ast::ExtendEnvPhaseless(Box::new(rename_everywhere(body, renaming)), beta.clone())
+        }
+        Ast::QuoteMore(ref body, pos) => {
+            Ast::QuoteMore(Box::new(rename_everywhere(body, renaming)), pos)
+        }
+        Ast::QuoteLess(ref body, depth) => {
+            Ast::QuoteLess(Box::new(rename_everywhere(body, renaming)), depth)
+        }
+        _ => ast.clone(),
+    }
+}
+
+/// The names of the macros exported by the `export` in `ast`.
+fn exported_macros(ast: &Ast) -> Vec<Name> {
+    match *ast {
+        Ast::Node(ref f, ref parts, _) if f.name == n("export") => parts
+            .march_all(&[n("macro")])
+            .iter()
+            .flat_map(|section| {
+                section.get_rep_leaf_or_panic(n("macro")).into_iter().map(vr_to_name).collect::<Vec<_>>()
+            })
+            .collect(),
+        Ast::Node(_, ref parts, _) => parts.map_reduce(
+            &|sub: &Ast| exported_macros(sub),
+            &|lhs, rhs| lhs.iter().chain(rhs.iter()).cloned().collect(),
+            vec![],
+        ),
+        Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) => exported_macros(body),
+        _ => vec![],
+    }
+}
+
+/// The path in `import "path"` (the scanner keeps the quotes, so that it unparses correctly).
+fn path_of(path_ast: &Ast) -> String { ast_to_name(path_ast).orig_sp().trim_matches('"').to_owned() }
+
+/// `import "path" in body` parses, typechecks, and evaluates `body`
+///  with the values, types, and syntax `export`ed by the module at `path`.
+/// (The path is relative to the file containing the `import`.)
+pub fn import() -> Rc<Form> {
+    let perform_import = move |pc: ParseContext, import_info: Ast| -> ParseContext {
+        let import_parts = partial_parse_parts(&import_info);
+        match load_module(&path_of(import_parts.get_leaf_or_panic(&n("path")))) {
+            Ok(module) => ParseContext { grammar: module.extend_grammar(&pc.grammar), ..pc },
+            Err(_) => pc, // The typechecker will complain about it.
+        }
+    };
+
+    typed_form!("import",
+        (extend [(lit "import"), (named "path", (scan r#"\s*("[^"]*")"#)), (lit "in")],
+            (named "body", (call "Expr")),
+            perform_import),
+        cust_rc_box!(|parts| {
+            let path = parts.get_term(n("path"));
+            let module = match load_module(&path_of(&path)) {
+                Ok(module) => module,
+                Err(why) => ty_err!(ImportFailed(path_of(&path), why) at path),
+            };
+            crate::ast_walk::walk::<SynthTy>(
+                &parts.get_term(n("body")),
+                &parts.with_environment(parts.env.set_assoc(&module.types)),
+            )
+        }),
+        cust_rc_box!(|parts| {
+            let module = load_module(&path_of(&parts.get_term(n("path")))).map_err(|_| ())?;
+            crate::ast_walk::walk::<Eval>(
+                &parts.get_term(n("body")),
+                &parts.with_environment(parts.env.set_assoc(&module.values)),
+            )
+        }))
+}
+
+/// `export values ⋯ ; types ⋯ ; syntax ⋯ ;` ends a module.
+/// It evaluates to a struct of the values (and its type includes the types and macros, too).
+pub fn export() -> Rc<Form> {
+    let record_syntax = move |pc: ParseContext, _: Ast| -> ParseContext {
+        exported_syntax.with(|es| *es.borrow_mut() = Some(pc.grammar.clone()));
+        pc
+    };
+
+    typed_form!("export",
+        (extend [(lit "export"),
+                 (star [(lit "values"), (star (named "value", varref)), (lit ";")]),
+                 (star [(lit "types"), (star (named "type", varref)), (lit ";")]),
+                 (star [(lit "syntax"), (star (named "macro", varref)), (lit ";")])],
+            [],
+            record_syntax),
+        cust_rc_box!(|parts| {
+            let mut names = vec![];
+            let mut tys = vec![];
+            // Exported types might be known by different (fresh) names inside the module,
+            //  and other types (e.g. recursive ones) might refer to them that way:
+            let mut renaming = Assoc::new();
+            for section in parts.march_parts(&[n("type")]) {
+                for name in section.get_rep_term(n("type")) {
+                    let name = vr_to_name(&name);
+                    renaming = renaming.set(name, name.unhygienic_orig());
+                }
+            }
+            // (A macro's "type" is what its invocations are typechecked against.)
+            for part in &[n("value"), n("type"), n("macro")] {
+                for section in parts.march_parts(&[*part]) {
+                    let section_tys = section.get_rep_res(*part)?;
+                    for (name, ty) in section.get_rep_term(*part).iter().zip(section_tys) {
+                        names.push(Ast::Atom(vr_to_name(name).unhygienic_orig()));
+                        tys.push(rename_everywhere(&ty.concrete(), &renaming));
+                    }
+                }
+            }
+            Ok(ty!({ "Type" "struct" :
+                "component_name" => (@"c" ,seq names),
+                "component" => (@"c" ,seq tys)
+            }))
+        }),
+        cust_rc_box!(|parts| {
+            let mut res = Assoc::new();
+            for section in parts.march_parts(&[n("value")]) {
+                for (name, value) in
+                    section.get_rep_term(n("value")).iter().zip(section.get_rep_res(n("value"))?)
+                {
+                    res = res.set(vr_to_name(name).unhygienic_orig(), value);
+                }
+            }
+            Ok(Value::Struct(res))
+        }))
+}
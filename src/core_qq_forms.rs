@@ -11,7 +11,11 @@ use crate::{
     util::assoc::Assoc,
     walk_mode::{NegativeWalkMode, WalkMode},
 };
-use std::rc::Rc;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
 
 // == Types and syntax quotation: when are annotations needed? ==
 // Expressions are "positive", and are traversed leaf-to-root in an environment, producing a type.
@@ -55,9 +59,11 @@ use std::rc::Rc;
 // Note that it doesn't matter whether the boundary is a quotation or an unquotation!
 // The phase only matters inasmuch as variables don't leave their phase.
 
-// There's a nice-seeming syntax for determining what `unquote` does when quotation is nested.
-// However, it would require some weird additional power for the parser:
+// When quotation is nested, the number of commas determines how far `unquote` escapes:
 //   '[Expr | '[Expr | ,[…], ,,[…],, ]']'
+// The first interpolates into the inner quotation (when it's evaluated),
+//  the second interpolates into the inner quotation when the *outer* one is evaluated.
+// (This is what a macro that defines a macro needs in order to pass its arguments along.)
 // OTOH, if you are using `,,,,[],,,,`, something has gone terribly wrong.
 
 // == Opacity! ==
@@ -147,6 +153,9 @@ impl WalkMode for MuProtect {
     }
     fn automatically_extend_env() -> bool { true }
 
+    // Names bound inside the type (e.g. by `forall`) stand for themselves, and aren't protected:
+    fn underspecified(name: Name) -> Ty { Ty(VariableReference(name)) }
+
     fn walk_var(name: Name, parts: &crate::ast_walk::LazyWalkReses<MuProtect>) -> Result<Ty, ()> {
         if parts.extra_info <= 0 {
             return Ok(Ty(VariableReference(name)));
@@ -181,6 +190,31 @@ impl NegativeWalkMode for UnusedNegativeMuProtect {
 //  (It only makes sense inside a `quote`.)
 // However, this would leave us with one `unquote` form available per level of quotation
 
+/// The arguments that a form made by `unquote_form` or `dotdotdot_form` was made from.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QQForm {
+    Unquote(Name, bool, u8),
+    DotDotDot(Name),
+}
+
+thread_local! {
+    /// The forms that have been made by `unquote_form` and `dotdotdot_form`, by address.
+    static qq_forms: RefCell<HashMap<usize, (Weak<Form>, QQForm)>> = RefCell::new(HashMap::new());
+}
+
+fn remember_qq_form(f: &Rc<Form>, how: QQForm) {
+    qq_forms.with(|qqf| qqf.borrow_mut().insert(Rc::as_ptr(f) as usize, (Rc::downgrade(f), how)));
+}
+
+/// How `f` was made, if it's an unquotation or `dotdotdot` form.
+pub fn qq_form(f: &Rc<Form>) -> Option<QQForm> {
+    qq_forms.with(|qqf| match qqf.borrow().get(&(Rc::as_ptr(f) as usize)) {
+        // (The address might have been reused after the original was dropped.)
+        Some((orig, how)) if orig.upgrade().is_some_and(|orig| Rc::ptr_eq(&orig, f)) => Some(*how),
+        _ => None,
+    })
+}
+
 /// Generate a (depth-1) unquoting form.
 /// `pos_quot` is true iff the quotation itself (and thus the interpolation) is positive.
 pub fn unquote(nt: Name, pos_quot: bool) -> Rc<FormPat> {
@@ -197,7 +231,7 @@ pub fn unquote(nt: Name, pos_quot: bool) -> Rc<FormPat> {
 pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
     let form_delim_start = &format!("{}[", ",".repeat(depth as usize));
 
-    Rc::new(Form {
+    let form = Rc::new(Form {
         name: n("unquote"),
         grammar:
             // It's a pain to determine whether type annotation is needed at syntax time,
@@ -240,15 +274,15 @@ pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
                         let res = if pos_quot {
                             // TODO: check annotation if present
 
-                            let mut res = unquote_parts.get_res(n("body"))?; // `Expr<String>`
-                            for _ in 0..(depth-1) {
-                                res = less_quoted_ty(&res, None, &ast_for_errors)?;
-                            } // HACK: we only know the last `nt` to expect
+                            let res = unquote_parts.get_res(n("body"))?; // `Expr<String>`
+                            // However many levels this skips, the syntax is spliced in one step,
+                            //  so there's only one layer of `Expr<>` to remove:
                             less_quoted_ty(&res, Some(nt), &ast_for_errors)?
                         } else {
                             // need a type annotation
                             if !unquote_parts.has(n("ty_annot")) {
-                                ty_err!(AnnotationRequired (()) at unquote_parts.this_ast);
+                                ty_err!(AnnotationRequired (ast_for_errors)
+                                    at unquote_parts.this_ast);
                             }
                             let expected_type = unquote_parts.get_res(n("ty_annot"))?;
 
@@ -269,23 +303,47 @@ pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
             } else {
                 // For example: ` '[Pat | (x, ,[Pat<String> | body], ) ]' `
                 //                            ^^^^^^^^^^^^^^^^^^^^^^
-                Negative(
+                Both(
+                    // A binder in quoted `Type` syntax is walked positively.
+                    // For example: ` '[Type | forall ,[Atom<Int> | name], . Int ]' `
+                    if nt == n("Atom") {
+                        cust_rc_box!( move | unquote_parts | {
+                            let ast_for_errors = unquote_parts.get_term(n("body"));
+                            if !pos_quot {
+                                icp!("{} isn't negative, but is walked positively", ast_for_errors)
+                            }
+                            let res = unquote_parts.get_res(n("body"))?; // `Atom<Int>`
+                            less_quoted_ty(&res, Some(nt), &ast_for_errors)?;
+
+                            // We can't know the name yet, so it's a fresh one:
+                            Ok(Ty(Atom(Name::gensym(&format!("{}", ast_for_errors)))))
+                        })
+                    } else {
+                        NotWalked
+                    },
                     cust_rc_box!( move | unquote_parts | {
                         let ast_for_errors = unquote_parts.get_term(n("body"));
                         let ctxt_elt = remove_opacity(unquote_parts.context_elt(),
                                                       -(i32::from(depth)));
 
-                        let mut ctxt_elt = ctxt_elt;
                         for _ in 0..(depth-1) {
                             unimplemented!("We may need a stack of what NTs are quoted")
                         }
-                        ctxt_elt = more_quoted_ty(&ctxt_elt, nt);
+                        let ctxt_elt_unquoted = ctxt_elt;
+                        let ctxt_elt = more_quoted_ty(&ctxt_elt_unquoted, nt);
 
                         if pos_quot {
                             // `String`
                             let lq_parts = unquote_parts.switch_mode::<crate::ty::SynthTy>();
                             let res = lq_parts.get_res(n("body"))?;
 
+                            // An `Atom<T>` is a fine `Pat<T>`; it's just a binder
+                            //  (e.g. ` '[Expr | match x { ,[name], => ⋯ } ]' `):
+                            let atom_ty = more_quoted_ty(&ctxt_elt_unquoted, n("Atom"));
+                            if nt == n("Pat") && res == atom_ty {
+                                return Ok(Assoc::new());
+                            }
+
                             // Bonus typecheck
                             ty_exp!(&ctxt_elt, &res, ast_for_errors);
 
@@ -309,6 +367,11 @@ pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
         quasiquote: // TODO #26: this and "dotdotdot" are the only forms that *aren't* `LiteralLike`
             Both( // TODO: double-check that `pos` and `neg` don't matter here
                 cust_rc_box!( move | unquote_parts | {
+                    // Inside a nested quotation, this doesn't escape far enough to be evaluated,
+                    //  but there might be a deeper unquotation inside it that does:
+                    if usize::from(depth) < unquote_parts.less_quoted_env.len() {
+                        return walk_escaped_body(unquote_parts, depth);
+                    }
                     let lq_parts = unquote_parts.switch_mode::<Eval>();
                     crate::ast_walk::walk::<Eval>(lq_parts.get_term_ref(n("body")), &lq_parts)
                 }),
@@ -318,8 +381,32 @@ pub fn unquote_form(nt: Name, pos_quot: bool, depth: u8) -> Rc<Form> {
                     let lq_parts = unquote_parts.switch_mode::<Destructure>();
                     crate::ast_walk::walk::<Destructure>(lq_parts.get_term_ref(n("body")),
                         &lq_parts.with_context(context))
-                }))
-    })
+                })),
+        macro_definition: None,
+    });
+    remember_qq_form(&form, QQForm::Unquote(nt, pos_quot, depth));
+    form
+}
+
+/// Quasiquote an `unquote` without evaluating it (because it's inside a nested quotation).
+fn walk_escaped_body(
+    unquote_parts: crate::ast_walk::LazyWalkReses<QQuote>,
+    depth: u8,
+) -> Result<crate::runtime::eval::Value, ()> {
+    use crate::walk_mode::WalkElt;
+
+    match unquote_parts.this_ast {
+        Node(ref f, ref parts, ref export) => {
+            let body = crate::ast_walk::walk::<QQuote>(
+                parts.get_leaf_or_panic(&n("body")),
+                &unquote_parts,
+            )?;
+            let mut parts = parts.clone();
+            parts.add_leaf(n("body"), QuoteLess(Box::new(body.to_ast()), depth));
+            Ok(WalkElt::from_ast(&Node(f.clone(), parts, export.clone())))
+        }
+        _ => icp!(),
+    }
 }
 
 // Macro By Example transcription. TODO: currently positive-only
@@ -356,6 +443,8 @@ macro_rules! ddd_type__body {
             let (_, ddd_parts_uq) = $ddd_parts.quote_less();
 
             let mut walked_env = Assoc::new();
+            // If we're repeating over an abstract repetition (`:::[T >> ⋯]:::`), these are the `T`s:
+            let mut abstract_drivers: Vec<Ast> = vec![];
 
             let repeats = match ddd_parts_uq.env.find(&drivers[0]) {
                 Some(&Ty(Node(ref form, ref parts, _))) if form.name == n("tuple") => {
@@ -367,7 +456,9 @@ macro_rules! ddd_type__body {
                     ty_err!(UnableToDestructure(other_t.clone(), n("tuple"))
                                 at ddd_parts_uq.this_ast);
                 }
-                _ => ty_err!(UnboundName(drivers[0]) at ddd_parts_uq.this_ast),
+                _ => ty_err!(UnboundName(drivers[0],
+                        crate::ty::did_you_mean(drivers[0], ddd_parts_uq.env.iter_keys().cloned()))
+                    at ddd_parts_uq.this_ast),
             };
 
             // We should be invoking `get_res` once per repetition,
@@ -384,6 +475,11 @@ macro_rules! ddd_type__body {
                                     // HACK! If the tuple had a ddd, we should just unwrap it.
                                     // We should somehow eliminate this linkage between
                                     //  syntax repetition and tuples with type repetition.
+                                    for driver in ddd2_parts.get_rep_leaf_or_panic(n("driver")) {
+                                        if !abstract_drivers.contains(driver) {
+                                            abstract_drivers.push(driver.clone());
+                                        }
+                                    }
                                     Ty(ddd2_parts.get_leaf_or_panic(&n("body")).clone())
                                 } else {
                                     Ty(component)
@@ -402,7 +498,8 @@ macro_rules! ddd_type__body {
                     }
                 }
             }
-            ddd_parts_uq.with_environment(walked_env).quote_more(None).get_res(n("body"))
+            (ddd_parts_uq.with_environment(walked_env).quote_more(None).get_res(n("body"))?,
+             abstract_drivers)
         }
     };
 }
@@ -410,7 +507,7 @@ macro_rules! ddd_type__body {
 // TODO #38: This should take a grammar, not an NT, as an argument,
 //  and be located underneath each Plus or Star.
 pub fn dotdotdot_form(nt: Name) -> Rc<Form> {
-    Rc::new(Form {
+    let form = Rc::new(Form {
         name: n("dotdotdot"),
         grammar: Rc::new(form_pat!((delim "...[", "[",
             [(star [(call "DefaultSeparator"), (scan "(,)"),
@@ -419,8 +516,25 @@ pub fn dotdotdot_form(nt: Name) -> Rc<Form> {
              (named "body", (call_by_name nt))]))),
         type_compare: Positive(NotWalked), // this is not a type form
         synth_type: Both(
-            cust_rc_box!(|ddd_parts| { ddd_type__body!(ddd_parts) }),
-            cust_rc_box!(|ddd_parts| { ddd_type__body!(ddd_parts) }),
+            cust_rc_box!(|ddd_parts| {
+                let (res, abstract_drivers) = ddd_type__body!(ddd_parts);
+                if abstract_drivers.is_empty() {
+                    Ok(res)
+                } else {
+                    // The result is repeated, too (e.g. this is a function's parameter types):
+                    Ok(ty!({"Type" "dotdotdot_type" :
+                        "driver" => (,seq abstract_drivers), "body" => (, res.0)}))
+                }
+            }),
+            cust_rc_box!(|ddd_parts| {
+                // Each repetition is matched against one element of an abstract repetition:
+                let ddd_parts = match ddd_parts.context_elt().0 {
+                    Node(ref f, ref parts, _) if f.name == n("dotdotdot_type") => ddd_parts
+                        .with_context(Ty(parts.get_leaf_or_panic(&n("body")).clone())),
+                    _ => ddd_parts,
+                };
+                Ok(ddd_type__body!(ddd_parts).0)
+            }),
         ),
         // An evaluate-time version of this might be a good idea;
         //  it might be all that's needed to implement variable-number-of-argument functions.
@@ -432,6 +546,11 @@ pub fn dotdotdot_form(nt: Name) -> Rc<Form> {
                 walk_mode::WalkElt,
             };
 
+            // Inside a nested quotation, this belongs to the inner quotation:
+            if ddd_parts.less_quoted_env.len() > 1 {
+                return QQuote::walk_quasi_literally(ddd_parts.this_ast.clone(), &ddd_parts);
+            }
+
             let (_, ddd_parts_uq) = ddd_parts.quote_less();
 
             let drivers: Vec<Name> = ddd_parts_uq
@@ -478,7 +597,10 @@ pub fn dotdotdot_form(nt: Name) -> Rc<Form> {
             // HACK: this tells `walk_quasi_literally` to splice (TODO #40?)
             Ok(Value::from_ast(&Shape(reps)))
         })),
-    })
+        macro_definition: None,
+    });
+    remember_qq_form(&form, QQForm::DotDotDot(nt));
+    form
 }
 
 // How do we walk quasiquotations?
@@ -501,14 +623,24 @@ pub fn quote(pos: bool) -> Rc<Form> {
             IncompleteNode(ref parts) => vr_to_name(&parts.get_leaf_or_panic(&n("nt"))),
             _ => icp!("malformed quotation"),
         };
-        fn already_has_unquote(fp: &FormPat) -> bool {
+        // The unquotations that are already available (one per enclosing quotation),
+        //  and whether each is positive:
+        fn existing_unquotes(fp: &FormPat) -> Vec<bool> {
             match *fp {
-                Alt(ref parts) => parts.iter().any(|sub_fp| already_has_unquote(&*sub_fp)),
+                Alt(ref parts) => parts
+                    .iter()
+                    .map(|sub_fp| existing_unquotes(&*sub_fp))
+                    .max_by_key(Vec::len)
+                    .unwrap_or_default(),
                 Biased(ref plan_a, ref plan_b) => {
-                    already_has_unquote(&*plan_a) || already_has_unquote(&*plan_b)
+                    let mut res = existing_unquotes(&*plan_a);
+                    res.append(&mut existing_unquotes(&*plan_b));
+                    res
+                }
+                Scope(ref f, ref export) if f.name == n("unquote") => {
+                    vec![*export == crate::beta::ExportBeta::Nothing]
                 }
-                Scope(ref f, _) => f.name == n("unquote"),
-                _ => false,
+                _ => vec![],
             }
         }
 
@@ -517,13 +649,35 @@ pub fn quote(pos: bool) -> Rc<Form> {
         let new_grammar = pc
             .grammar
             .keyed_map_borrow_f(&mut |nt: &Name, nt_def: &Rc<FormPat>| {
-                if already_has_unquote(nt_def)
-               // HACK: this is to avoid hitting "starterer". TODO: find a better way
-               || (nt != &n("Expr") && nt != &n("Pat") && nt != &n("Type") && nt != &n("AtomNotInPat"))
+                // HACK: this is to avoid hitting "starterer". TODO: find a better way
+                if nt != &n("Expr") && nt != &n("Pat") && nt != &n("SimplePat") && nt != &n("Type")
+                    && nt != &n("AtomNotInPat")
                 {
-                    nt_def.clone()
+                    return nt_def.clone();
+                }
+                let nt_for_type = if nt == &n("AtomNotInPat") {
+                    n("Atom")
+                } else if nt == &n("SimplePat") {
+                    n("Pat")
+                } else {
+                    *nt
+                };
+                let existing = existing_unquotes(nt_def);
+                if !existing.is_empty() {
+                    // A nested quotation: `,,[ ⋯ ],,` (etc.) escapes all the way out.
+                    // TODO: this is currently positive-only
+                    if pos && nt_is_positive(nt_for_type) && existing.iter().all(|p| *p) {
+                        Rc::new(Biased(
+                            Rc::new(Scope(
+                                unquote_form(nt_for_type, pos, existing.len() as u8 + 1),
+                                crate::beta::ExportBeta::Nothing,
+                            )),
+                            nt_def.clone(),
+                        ))
+                    } else {
+                        nt_def.clone()
+                    }
                 } else {
-                    let nt_for_type = if nt == &n("AtomNotInPat") { n("Atom") } else { *nt };
                     // TODO #38: we should insert `dotdotdot` under Star and Plus,
                     //  not at the top level
                     Rc::new(Biased(
@@ -553,9 +707,11 @@ pub fn quote(pos: bool) -> Rc<Form> {
     // What happens when more NTs are added?
     Rc::new(Form {
         name: if pos { n("quote_expr") } else { n("quote_pat") },
-        grammar: Rc::new(form_pat!((delim "'[", "[",
-            // TODO: use `extend`, not `extend_nt`. Can it resolve the HACK above?
-            [(extend_nt (named "nt", varref), "QuotationBody", perform_quotation)]))),
+        grammar: Rc::new(form_pat!([
+            (named "source_line", (anyways (, Trivial))),
+            (delim "'[", "[",
+                // TODO: use `extend`, not `extend_nt`. Can it resolve the HACK above?
+                [(extend_nt (named "nt", varref), "QuotationBody", perform_quotation)])])),
         type_compare: Both(NotWalked, NotWalked), // Not a type
         synth_type: if pos {
             Positive(cust_rc_box!(|quote_parts| {
@@ -571,7 +727,11 @@ pub fn quote(pos: bool) -> Rc<Form> {
                     }))
                 } else {
                     if !quote_parts.has(n("ty_annot")) {
-                        ty_err!(AnnotationRequired (()) at quote_parts.this_ast);
+                        let quoted = match quote_parts.get_term(n("body")) {
+                            QuoteMore(body, _) => *body,
+                            other => other,
+                        };
+                        ty_err!(AnnotationRequired (quoted) at quote_parts.this_ast);
                     }
                     let expected_type = &quote_parts.get_res(n("ty_annot"))?;
 
@@ -613,11 +773,18 @@ pub fn quote(pos: bool) -> Rc<Form> {
         eval: if pos {
             Positive(cust_rc_box!(|quote_parts| {
                 let mq_parts = quote_parts.switch_mode::<QQuote>().quote_more(None);
-                match mq_parts.get_term_ref(n("body")) {
+                let res = match mq_parts.get_term_ref(n("body")) {
                     // Strip the `QuoteMore`:
-                    QuoteMore(ref a, _) => crate::ast_walk::walk::<QQuote>(&*a, &mq_parts),
+                    QuoteMore(ref a, _) => crate::ast_walk::walk::<QQuote>(&*a, &mq_parts)?,
                     _ => icp!(),
+                };
+                if vr_to_name(&quote_parts.get_term(n("nt"))) != n("Expr") {
+                    return Ok(res);
                 }
+                // Remember where macro-generated code came from:
+                let template_line = crate::core_macro_forms::source_line(&quote_parts.this_ast);
+                crate::core_macro_forms::note_template(&res, template_line);
+                Ok(res)
             }))
         } else {
             Negative(cust_rc_box!(|quote_parts| {
@@ -634,7 +801,29 @@ pub fn quote(pos: bool) -> Rc<Form> {
                 }
             }))
         },
-        quasiquote: Both(LiteralLike, LiteralLike),
+        // A quotation inside a quotation is literal, except for its deeper unquotations:
+        quasiquote: Both(
+            cust_rc_box!(|quote_parts| {
+                use crate::walk_mode::WalkElt;
+
+                match quote_parts.this_ast {
+                    Node(ref f, ref parts, ref export) => match parts.get_leaf_or_panic(&n("body")) {
+                        QuoteMore(ref body, true) => {
+                            let mq_parts = quote_parts.clone().quote_more(None);
+                            let body = crate::ast_walk::walk::<QQuote>(&*body, &mq_parts)?;
+                            let mut parts = parts.clone();
+                            parts.add_leaf(n("body"), QuoteMore(Box::new(body.to_ast()), true));
+                            Ok(WalkElt::from_ast(&Node(f.clone(), parts, export.clone())))
+                        }
+                        // TODO: deeper unquotation is positive-only
+                        _ => QQuote::walk_quasi_literally(quote_parts.this_ast.clone(), &quote_parts),
+                    },
+                    _ => icp!(),
+                }
+            }),
+            LiteralLike,
+        ),
+        macro_definition: None,
     })
 }
 
@@ -811,7 +1000,7 @@ fn quote_type_basic() {
             env.clone(),
             qenv.clone()
         ),
-        ty_err_p!(UnboundName(_))
+        ty_err_p!(UnboundName(_, _))
     );
 
     // '[Expr | { x: qn  y: qn }]'
@@ -1311,7 +1500,8 @@ fn use_dotdotdot() {
             ddd_abs_env.clone(),
             qenv.clone()
         ),
-        Ok(uty!({type_apply : (prim Expr) [{tuple : [{Int :}]}]}))
+        // We don't know how many `vals` there are, so we don't know how many `Int`s we get:
+        Ok(uty!({type_apply : (prim Expr) [{tuple : [{dotdotdot_type : [T] {Int :}}]}]}))
     );
 }
 
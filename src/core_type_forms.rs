@@ -48,7 +48,7 @@ use crate::{
         walk,
         WalkRule::{self, *},
     },
-    core_forms::{ast_to_name, vr_to_name},
+    core_forms::{ast_to_name, strip_ee, vr_to_name},
     form::{simple_form, BiDiWR, Both, Form, Positive},
     grammar::{
         FormPat::{self, *},
@@ -57,7 +57,7 @@ use crate::{
     name::*,
     ty::{synth_type, SynthTy, Ty, TyErr},
     ty_compare::{Canonicalize, Subtype},
-    util::assoc::Assoc,
+    util::{assoc::Assoc, mbe::EnvMBE},
     walk_mode::{NegativeWalkMode, WalkMode},
 };
 use std::rc::Rc;
@@ -71,6 +71,7 @@ pub fn type_defn(form_name: &str, p: FormPat) -> Rc<Form> {
         synth_type: Positive(LiteralLike),
         quasiquote: Both(LiteralLike, LiteralLike),
         eval: Positive(NotWalked),
+        macro_definition: None,
     })
 }
 
@@ -88,9 +89,28 @@ fn type_defn_complex(
         synth_type: Positive(sy),
         quasiquote: Both(LiteralLike, LiteralLike),
         eval: Positive(NotWalked),
+        macro_definition: None,
     })
 }
 
+/// The names of the arms of an `enum` type, and how many components each one has.
+/// `None` if there's a `:::[]:::` among the components, since it can stand for any number of them.
+fn enum_arm_shapes(parts: &EnvMBE<Ast>) -> Option<Vec<(Name, usize)>> {
+    let mut res = vec![];
+    for arm in parts.march_all(&[n("name")]) {
+        let components = arm.get_rep_leaf_or_panic(n("component"));
+        for component in &components {
+            if let Node(ref f, _, _) = **component {
+                if f.name == n("dotdotdot_type") {
+                    return None;
+                }
+            }
+        }
+        res.push((ast_to_name(arm.get_leaf_or_panic(&n("name"))), components.len()));
+    }
+    Some(res)
+}
+
 thread_local! {
     // Not needed by the user.
     // An internal type to keep the compiler from trying to dig into the `Expr` in `Expr<X>`.
@@ -100,8 +120,13 @@ thread_local! {
         type_compare: Both(LiteralLike, LiteralLike),
         synth_type: Positive(LiteralLike),
         quasiquote: Both(LiteralLike, LiteralLike),
-        eval: Positive(NotWalked)
-    })
+        eval: Positive(NotWalked),
+        macro_definition: None,
+    });
+
+    // Not needed by the user.
+    // A placeholder type, for betas that need a type but whose names won't be used as variables.
+    pub static unused_type : Rc<Form> = type_defn("unused", form_pat!((impossible)));
 }
 
 pub fn get__primitive_type(called: Name) -> Ty {
@@ -162,12 +187,37 @@ pub fn make_core_syn_env_types() -> SynEnv {
         ),
     );
 
-    let enum_type = type_defn(
+    let enum_type = type_defn_complex(
         "enum",
         form_pat!(
             (delim "{", "{", (star
                 (delim "+[", "[",
                     [(named "name", atom),(star (named "component", (call "Type")))])))),
+        LiteralLike, // synth is normal
+        Both(
+            LiteralLike,
+            cust_rc_box!(move |enum_parts| {
+                let actual_parts = Subtype::context_match(
+                    &enum_parts.this_ast,
+                    &enum_parts.context_elt().concrete(),
+                    enum_parts.env.clone(),
+                )?;
+                // Walking arms of different shapes against each other would go horribly wrong:
+                if let Node(_, ref expected_parts, _) = enum_parts.this_ast {
+                    if let (Some(expected), Some(actual)) =
+                        (enum_arm_shapes(expected_parts), enum_arm_shapes(&actual_parts))
+                    {
+                        if expected != actual {
+                            return Err(TyErr::Mismatch(
+                                enum_parts.context_elt().clone(),
+                                Ty::new(enum_parts.this_ast.clone()),
+                            ));
+                        }
+                    }
+                }
+                Subtype::walk_quasi_literally(enum_parts.this_ast.clone(), &enum_parts)
+            }),
+        ),
     );
 
     let struct_type = type_defn_complex(
@@ -208,7 +258,7 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     if !found {
                         return Err(TyErr::NonexistentStructField(
                             ast_to_name(&got_name),
-                            struct_parts.context_elt().clone(),
+                            Ty::new(struct_parts.this_ast.clone()),
                         ));
                     }
                 }
@@ -258,11 +308,83 @@ pub fn make_core_syn_env_types() -> SynEnv {
         ),
     );
 
+    // [Type theory alert!]
+    // An existential type hides some types (its `param`s) behind an abstraction boundary.
+    // Values of this type are produced by `pack` and consumed by `unpack`.
+    // Anything can be packed as `∃ X. ⋯` if some choice of `X` makes it fit,
+    //  which is exactly the unification that `import [forall "param"]` already does for `∀`.
+    // But nothing other than another `∃` is a supertype of `∃ X. ⋯`;
+    //  the only way to use the inside is to `unpack` it.
+    let exists_type = type_defn_complex(
+        "exists_type",
+        form_pat!([(lit "exists"), (star (named "param", atom)), (lit "."),
+                       (named "body", (import [* [forall "param"]], (call "Type")))]),
+        LiteralLike, // synth is normal
+        Both(
+            LiteralLike,
+            cust_rc_box!(move |exists_parts| {
+                match Subtype::context_match(
+                    &exists_parts.this_ast,
+                    &exists_parts.context_elt().concrete(),
+                    exists_parts.env.clone(),
+                ) {
+                    // ∃ X. ⋯ <: ∃ Y. ⋯ ? (so force X=Y)
+                    Ok(actual_exists_parts) => {
+                        let actl_params = actual_exists_parts.get_rep_leaf_or_panic(n("param"));
+                        let expd_params = exists_parts.get_rep_term(n("param"));
+                        if actl_params.len() != expd_params.len() {
+                            return Err(TyErr::LengthMismatch(
+                                actl_params.iter().map(|&a| Ty(a.clone())).collect(),
+                                expd_params.len(),
+                            ));
+                        }
+                        // Neither side's params can be unified with anything,
+                        //  so rename both sides' to shared fresh names and protect those:
+                        let mut actl_renaming = Assoc::new();
+                        let mut expd_renaming = Assoc::new();
+                        let mut body_env = exists_parts.env.clone();
+                        for (actl_param, expd_param) in actl_params.iter().zip(expd_params.iter()) {
+                            let (actl_param, expd_param) =
+                                (ast_to_name(actl_param), ast_to_name(expd_param));
+                            let fresh = expd_param.freshen();
+                            actl_renaming = actl_renaming.set(actl_param, VariableReference(fresh));
+                            expd_renaming = expd_renaming.set(expd_param, VariableReference(fresh));
+                            body_env = body_env.set(fresh, Ty(VariableReference(fresh)));
+                        }
+                        let actl_inner_body = crate::alpha::substitute(
+                            strip_ee(actual_exists_parts.get_leaf_or_panic(&n("body"))),
+                            &actl_renaming,
+                        );
+                        let expd_inner_body = crate::alpha::substitute(
+                            strip_ee(exists_parts.get_term_ref(n("body"))),
+                            &expd_renaming,
+                        );
+
+                        walk::<Subtype>(
+                            &expd_inner_body,
+                            &exists_parts
+                                .with_environment(body_env)
+                                .with_context(Ty::new(actl_inner_body)),
+                        )
+                    }
+                    // ⋯ <: ∃ X. ⋯ ?  (so try to find a witness for X)
+                    Err(_) => {
+                        // `import [forall "param"]` handles the unification,
+                        //  and we leave the context element alone
+                        walk::<Subtype>(&exists_parts.get_term(n("body")), &exists_parts)
+                    }
+                }
+            }),
+        ),
+    );
+
     // This behaves slightly differently than the `mu` from Pierce's book,
     //  because we need to support mutual recursion.
     // In particular, it relies on having a binding for `param` in the environment!
     // The only thing that `mu` actually does is suppress substitution,
     //  to prevent the attempted generation of an infinite type.
+    // For a group of mutually recursive types, each member lists its own name first,
+    //  then the rest of the group in a consistent order (`let_type rec` does this).
     let mu_type = type_defn_complex(
         "mu_type",
         form_pat!([(lit "mu_type"), (star (named "param", (import [prot "param"], varref))),
@@ -287,16 +409,22 @@ pub fn make_core_syn_env_types() -> SynEnv {
                         l_params.len(),
                     ));
                 }
+                let param_vr = |ee: &Ast| match ee {
+                    ExtendEnv(p, _) => (**p).clone(),
+                    _ => icp!("ill-formed mu_type"),
+                };
+                let r_names: Vec<Ast> = r_params.iter().map(|ee| param_vr(ee)).collect();
+                let l_names: Vec<Ast> = l_params.iter().map(param_vr).collect();
+                // Members of the same mutually-recursive group list the same names
+                //  (in different orders), and the names already mean the same thing:
+                let same_group = r_names.iter().all(|r| l_names.contains(r));
+
                 // Apply the Amber rule; assume the `mu`ed names are subtypes to subtype the bodies
                 let mut amber_environment = mu_parts.env.clone();
-                for (&ee_r, ee_l) in r_params.iter().zip(l_params.iter()) {
-                    let (p_r, p_l) = if let (ExtendEnv(r, _), ExtendEnv(l, _)) = (ee_r, ee_l) {
-                        (&**r, &**l)
-                    } else {
-                        icp!("ill-formed mu_type")
-                    };
-                    if p_r == p_l // short-circuit if the names are the same...
-                        || mu_parts.env.find(&vr_to_name(&*p_r)) // ...or Amber assumed so already
+                for (p_r, p_l) in r_names.iter().zip(l_names.iter()) {
+                    if same_group
+                        || p_r == p_l // short-circuit if the names are the same...
+                        || mu_parts.env.find(&vr_to_name(p_r)) // ...or Amber assumed so already
                              == Some(&Ty(p_l.clone()))
                     {
                         continue;
@@ -318,10 +446,92 @@ pub fn make_core_syn_env_types() -> SynEnv {
     // TODO: add named repeats. Add type-level numbers!
     // TODO: We probably need kinds, to say that `T` is a tuple
     // TODO: we'll need dotdotdot inside betas, also, huh?
-    let dotdotdot_type = type_defn(
+    let dotdotdot_type = type_defn_complex(
         "dotdotdot_type",
         form_pat!((delim ":::[", "[", [(star (named "driver", varref)), (lit ">>"),
                                         (named "body", (call "Type"))])),
+        // If the drivers are all known tuples (e.g. from repeated syntax in a macro invocation),
+        //  this expands to one type per component, to be spliced into the surrounding repetition.
+        // Otherwise, it's an abstract repetition, and stays as it is
+        //  (the drivers might not even be bound at this quotation level, so don't walk them).
+        cust_rc_box!(move |ddd_parts| {
+            let drivers: Vec<Name> =
+                ddd_parts.get_rep_term(n("driver")).iter().map(vr_to_name).collect();
+            let abstract_repetition = || -> Result<Ty, crate::ty::TypeError> {
+                Ok(ty!({"Type" "dotdotdot_type" :
+                    "driver" => (,seq ddd_parts.get_rep_term(n("driver"))),
+                    "body" => (, ddd_parts.get_res(n("body"))?.0)}))
+            };
+            let mut driver_components = vec![];
+            for driver in &drivers {
+                match ddd_parts.env.find(driver).map(|t| t.0.clone()) {
+                    Some(Node(ref f, ref parts, _)) if f.name == n("tuple") => {
+                        let components = parts.get_rep_leaf_or_panic(n("component"));
+                        if components
+                            .iter()
+                            .any(|c| matches!(c, Node(ref f, _, _) if f.name == n("dotdotdot_type")))
+                        {
+                            return abstract_repetition();
+                        }
+                        driver_components
+                            .push(components.into_iter().cloned().collect::<Vec<Ast>>());
+                    }
+                    _ => return abstract_repetition(),
+                }
+            }
+            let len = driver_components.first().map(|c| c.len()).unwrap_or(0);
+            if let Some(wrong) = driver_components.iter().find(|c| c.len() != len) {
+                ty_err!(LengthMismatch(wrong.iter().map(|c| Ty(c.clone())).collect(), len)
+                    at ddd_parts.this_ast);
+            }
+
+            let mut expanded = vec![];
+            for i in 0..len {
+                let mut env = ddd_parts.env.clone();
+                for (driver, components) in drivers.iter().zip(driver_components.iter()) {
+                    env = env.set(*driver, Ty(components[i].clone()));
+                }
+                ddd_parts.clear_memo();
+                expanded.push(ddd_parts.with_environment(env).get_res(n("body"))?.0);
+            }
+            // HACK: this tells `walk_quasi_literally` to splice (like `dotdotdot` does for syntax)
+            Ok(Ty(Shape(expanded)))
+        }),
+        Both(
+            LiteralLike,
+            // Like `LiteralLike`, except that drivers from a different phase
+            //  (e.g. a macro's type parameters, seen from inside a quotation) can't be looked up,
+            //  so they have to match by name.
+            cust_rc_box!(|ddd_parts| {
+                let ddd_form = crate::core_forms::find("Type", "dotdotdot_type");
+                let actual = ddd_parts.context_elt().clone();
+                let actual_parts = match actual.0.destructure(ddd_form) {
+                    Some(actual_parts) => actual_parts,
+                    None => return Err(TyErr::Mismatch(actual, Ty(ddd_parts.this_ast.clone()))),
+                };
+                let expd_drivers = ddd_parts.get_rep_term(n("driver"));
+                let actl_drivers = actual_parts.get_rep_leaf_or_panic(n("driver"));
+                if expd_drivers.len() != actl_drivers.len() {
+                    return Err(TyErr::Mismatch(actual, Ty(ddd_parts.this_ast.clone())));
+                }
+                let mut res = Assoc::new();
+                for (expd, actl) in expd_drivers.iter().zip(actl_drivers) {
+                    if ddd_parts.env.find(&vr_to_name(expd)).is_some() {
+                        res = res.set_assoc(&walk::<Subtype>(
+                            expd,
+                            &ddd_parts.with_context(Ty(actl.clone())),
+                        )?);
+                    } else if expd != actl {
+                        return Err(TyErr::Mismatch(actual, Ty(ddd_parts.this_ast.clone())));
+                    }
+                }
+                Ok(res.set_assoc(&walk::<Subtype>(
+                    &ddd_parts.get_term(n("body")),
+                    &ddd_parts
+                        .with_context(Ty(actual_parts.get_leaf_or_panic(&n("body")).clone())),
+                )?))
+            }),
+        ),
     );
 
     let forall_type_0 = forall_type.clone();
@@ -394,7 +604,8 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     // This might ought to be done by a specialized `beta`...
                     let params = forall_type__parts.get_rep_leaf_or_panic(n("param"));
                     if params.len() != arg_res.len() {
-                        panic!("[kind error] wrong number of arguments");
+                        ty_err!(WrongNumberOfTypeArgs(rator_res.clone(), arg_res.len(), params.len())
+                            at tapp_parts.this_ast);
                     }
                     let mut new__ty_env = tapp_parts.env;
                     for (name, actual_type) in params.iter().zip(arg_res) {
@@ -410,9 +621,7 @@ pub fn make_core_syn_env_types() -> SynEnv {
                     )
                 }
 
-                _ => {
-                    panic!("[kind error] {} is not a forall.", rator_res);
-                }
+                _ => ty_err!(NotATypeFunction(rator_res.clone()) at tapp_parts.this_ast),
             }
         }),
         Both(LiteralLike, LiteralLike),
@@ -432,6 +641,7 @@ pub fn make_core_syn_env_types() -> SynEnv {
         struct_type,
         tuple_type,
         forall_type,
+        exists_type,
         dotdotdot_type,
         mu_type,
         type_apply
@@ -441,7 +651,7 @@ pub fn make_core_syn_env_types() -> SynEnv {
 // TODO #4: this should be extensible for when the syntax environment is extended...
 //  or just automatically have one type per NT. Probably the latter.
 pub fn nt_to_type(nt: Name) -> Ty {
-    if nt == n("Type") || nt == n("Pat") || nt == n("Expr") {
+    if nt == n("Type") || nt == n("Pat") || nt == n("Expr") || nt == n("Atom") {
         get__primitive_type(nt)
     } else {
         icp!("unknown NT {}", nt)
@@ -555,10 +765,30 @@ fn parametric_types() {
             &ast!( { "Type" "type_apply" :
             "type_rator" => (vr "unary"),
             "arg" => [ (, ident_ty.concrete()) ]}),
-            para_ty_env
+            para_ty_env.clone()
         ),
         Ok(ty!({ "Type" "fn" :
             "param" => [(, nat_ty.concrete() )],
             "ret" => (, ident_ty.concrete())}))
     );
+
+    // Kind errors:
+    assert_m!(
+        synth_type(
+            &ast!( { "Type" "type_apply" :
+            "type_rator" => (vr "binary"),
+            "arg" => [ (, ident_ty.concrete()) ]}),
+            para_ty_env.clone()
+        ),
+        ty_err_p!(WrongNumberOfTypeArgs(_, 1, 2))
+    );
+    assert_m!(
+        synth_type(
+            &ast!( { "Type" "type_apply" :
+            "type_rator" => (, nat_ty.concrete()),
+            "arg" => [ (, ident_ty.concrete()) ]}),
+            para_ty_env
+        ),
+        ty_err_p!(NotATypeFunction(_))
+    );
 }
@@ -42,6 +42,19 @@ thread_local! {
     // For parse error reporting: how far have we gotten?
     static best_token: RefCell<(usize, Rc<FormPat>, usize)>
         = RefCell::new((0, Rc::new(Impossible), 0));
+
+    // The (trimmed) text being parsed, so that we can say where things came from.
+    static source_text: RefCell<Rc<str>> = RefCell::new(Rc::from(""));
+}
+
+/// The line number of the first non-whitespace character at or after `idx` in the text being parsed.
+fn line_at(idx: usize) -> usize {
+    source_text.with(|text| {
+        let text = text.borrow();
+        let rest = &text[idx..];
+        let idx = idx + (rest.len() - rest.trim_start().len());
+        text[0..idx].matches('\n').count() + 1
+    })
 }
 
 fn get_next_id() -> UniqueId {
@@ -628,15 +641,22 @@ impl Item {
                     }
                 };
 
-                let new_ctxt = all_parse_contexts.with(|grammars| {
-                    let mut mut_grammars = grammars.borrow_mut();
-                    mut_grammars
-                        .entry(self.id.get_ref()) // memoize
-                        .or_insert_with(||
-                            f.0(ParseContext::new(
-                                self.grammar.clone(), (*self.envs).clone()), partial_parse))
-                        .clone()
-                });
+                // (Not borrowed while `f` runs; it might parse something, e.g. an imported module.)
+                let memoized = all_parse_contexts
+                    .with(|grammars| grammars.borrow().get(&self.id.get_ref()).cloned());
+                let new_ctxt = match memoized {
+                    Some(ctxt) => ctxt,
+                    None => {
+                        let ctxt = f.0(
+                            ParseContext::new(self.grammar.clone(), (*self.envs).clone()),
+                            partial_parse,
+                        );
+                        all_parse_contexts.with(|grammars| {
+                            grammars.borrow_mut().insert(self.id.get_ref(), ctxt.clone())
+                        });
+                        ctxt
+                    }
+                };
 
                 vec![(
                     Item {
@@ -786,7 +806,13 @@ impl Item {
             Scope(ref form, ref export) => {
                 let sub_parsed = self.find_wanted(chart, done_tok).c_parse(chart, done_tok)?;
                 // TODO #14: We should add zero-length repeats of missing `Named`s,
-                Ok(Ast::Node(form.clone(), sub_parsed.flatten(), export.clone()))
+                let mut parts = sub_parsed.flatten();
+                // Forms that want to know where they were written say so with an (empty) part:
+                if parts.get_leaf(n("source_line")) == Some(&Ast::Trivial) {
+                    let line = line_at(self.start_idx);
+                    parts.add_leaf(n("source_line"), Ast::Atom(n(&line.to_string())));
+                }
+                Ok(Ast::Node(form.clone(), parts, export.clone()))
             }
             Pick(_, name) => {
                 let sub_parsed = self.find_wanted(chart, done_tok).c_parse(chart, done_tok)?;
@@ -829,7 +855,8 @@ pub struct ParseError {
 }
 
 pub fn parse(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs, toks: &str) -> ParseResult {
-    best_token.with(|bt| *bt.borrow_mut() = (0, Rc::new(rule.clone()), 0));
+    let outer_best_token = best_token.with(|bt| bt.replace((0, Rc::new(rule.clone()), 0)));
+    let outer_source = source_text.with(|text| text.replace(Rc::from(toks.trim())));
 
     let (start_but_startier, chart) =
         create_chart(Rc::new(rule.clone()), grammar.clone(), envs, toks);
@@ -838,7 +865,7 @@ pub fn parse(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs, toks: &str) -> Pa
             && *item.done.borrow()
     });
     log!("-------\n");
-    match final_item {
+    let res = match final_item {
         Some(i) => i.c_parse(&chart, chart.len() - 1),
         None => best_token.with(|bt| {
             let (idx, ref grammar, pos) = *bt.borrow();
@@ -858,7 +885,10 @@ pub fn parse(rule: &FormPat, grammar: &SynEnv, envs: CodeEnvs, toks: &str) -> Pa
                 ),
             })
         }),
-    }
+    };
+    source_text.with(|text| *text.borrow_mut() = outer_source);
+    best_token.with(|bt| *bt.borrow_mut() = outer_best_token);
+    res
 }
 
 fn parse_top(rule: &FormPat, toks: &str) -> ParseResult {
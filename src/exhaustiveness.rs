@@ -0,0 +1,585 @@
+// Exhaustiveness and redundancy checking for `match`.
+//
+// This is the "usefulness" algorithm from
+//  "Warnings for pattern matching" by Luc Maranget (JFP 2007).
+// A pattern vector `q` is *useful* with respect to a matrix of pattern rows `P`
+//  if some value matches `q` but none of the rows of `P`.
+// An arm is redundant if its pattern isn't useful with respect to the arms above it,
+//  and a `match` is exhaustive if `_` isn't useful with respect to all of its arms.
+//
+// Patterns are analyzed before macro expansion, so we can't see inside macro invocations
+//  (or `,[pat],` in a macro's template). Those are "opaque": we assume that they match anything,
+//  and if they don't, the `match` fails at runtime.
+// Syntax quotation patterns, on the other hand, might not match, so they don't cover anything.
+
+use crate::{
+    ast::{Ast, Ast::*},
+    ast_walk::Clo,
+    core_forms::{ast_to_name, strip_ee},
+    name::*,
+    ty::Ty,
+    util::assoc::Assoc,
+};
+use num::BigInt;
+use std::fmt;
+
+/// A simplified pattern, as far as coverage is concerned.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pat {
+    Wild,
+    /// Something we can't analyze until after macro expansion.
+    Opaque,
+    /// A syntax quotation pattern.
+    Quote,
+    Enum(Name, Vec<Pat>),
+    Tuple(Vec<Pat>),
+    Struct(Vec<(Name, Pat)>),
+    Int(BigInt),
+    Or(Vec<Pat>),
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pat::Wild | Pat::Opaque | Pat::Quote => write!(f, "_"),
+            Pat::Enum(name, ref components) => {
+                write!(f, "+[{}", name)?;
+                for c in components {
+                    write!(f, " {}", c)?;
+                }
+                write!(f, "]+")
+            }
+            Pat::Tuple(ref components) => {
+                write!(f, "**[")?;
+                for (i, c) in components.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { " " }, c)?;
+                }
+                write!(f, "]**")
+            }
+            Pat::Struct(ref components) => {
+                write!(f, "*[")?;
+                for (i, (name, c)) in components.iter().enumerate() {
+                    write!(f, "{}{} : {}", if i == 0 { "" } else { " " }, name, c)?;
+                }
+                write!(f, "]*")
+            }
+            Pat::Int(ref i) => write!(f, "{}", i),
+            Pat::Or(ref alts) => {
+                for (i, alt) in alts.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { " | " }, alt)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Pat {
+    pub fn from_ast(a: &Ast) -> Pat {
+        match *a {
+            Atom(_) => Pat::Wild,
+            Node(ref f, _, _) if f.name == n("wildcard_pat") => Pat::Wild,
+            Node(ref f, ref parts, _) if f.name == n("quote_pat") => {
+                let body = match *parts.get_leaf_or_panic(&n("body")) {
+                    ExtendEnv(ref body, _) | ExtendEnvPhaseless(ref body, _) => &**body,
+                    ref body => body,
+                };
+                match *body {
+                    // Really a `,[pat],` (in a template), so we can't tell what it'll be yet:
+                    QuoteLess(_, _) => Pat::Opaque,
+                    _ => Pat::Quote,
+                }
+            }
+            Node(ref f, ref parts, _) if f.name == n("as_pat") => {
+                Pat::from_ast(parts.get_leaf_or_panic(&n("body")))
+            }
+            Node(ref f, ref parts, _) if f.name == n("int_pat") => {
+                match ast_to_name(parts.get_leaf_or_panic(&n("value"))).orig_sp().parse() {
+                    Ok(i) => Pat::Int(i),
+                    Err(_) => Pat::Opaque,
+                }
+            }
+            Node(ref f, ref parts, _) if f.name == n("or_pat") => Pat::Or(vec![
+                Pat::from_ast(parts.get_leaf_or_panic(&n("first"))),
+                Pat::from_ast(parts.get_leaf_or_panic(&n("rest"))),
+            ]),
+            Node(ref f, ref parts, _) if f.name == n("enum_pat") => Pat::Enum(
+                ast_to_name(parts.get_leaf_or_panic(&n("name"))),
+                parts.get_rep_leaf_or_panic(n("component")).into_iter().map(Pat::from_ast).collect(),
+            ),
+            Node(ref f, ref parts, _) if f.name == n("tuple_pat") => Pat::Tuple(
+                parts.get_rep_leaf_or_panic(n("component")).into_iter().map(Pat::from_ast).collect(),
+            ),
+            Node(ref f, ref parts, _) if f.name == n("struct_pat") => Pat::Struct(
+                parts
+                    .march_all(&[n("component")])
+                    .iter()
+                    .map(|c_parts| {
+                        (
+                            ast_to_name(c_parts.get_leaf_or_panic(&n("component_name"))),
+                            Pat::from_ast(c_parts.get_leaf_or_panic(&n("component"))),
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => Pat::Opaque,
+        }
+    }
+
+    /// Does `self` contain anything that `pred` is true of?
+    fn contains(&self, pred: &dyn Fn(&Pat) -> bool) -> bool {
+        pred(self)
+            || match *self {
+                Pat::Enum(_, ref cs) | Pat::Tuple(ref cs) | Pat::Or(ref cs) => {
+                    cs.iter().any(|c| c.contains(pred))
+                }
+                Pat::Struct(ref cs) => cs.iter().any(|(_, c)| c.contains(pred)),
+                _ => false,
+            }
+    }
+
+    fn is_opaque(&self) -> bool { self.contains(&|p| *p == Pat::Opaque || *p == Pat::Quote) }
+
+    /// Assume that opaque patterns match anything.
+    fn assume_opaque_matches(&self) -> Pat {
+        match *self {
+            Pat::Opaque => Pat::Wild,
+            Pat::Enum(name, ref cs) => {
+                Pat::Enum(name, cs.iter().map(Pat::assume_opaque_matches).collect())
+            }
+            Pat::Tuple(ref cs) => Pat::Tuple(cs.iter().map(Pat::assume_opaque_matches).collect()),
+            Pat::Or(ref cs) => Pat::Or(cs.iter().map(Pat::assume_opaque_matches).collect()),
+            Pat::Struct(ref cs) => {
+                Pat::Struct(cs.iter().map(|(n, c)| (*n, c.assume_opaque_matches())).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+/// A constructor for values of some type, with the types of its arguments (if known).
+#[derive(Clone, Debug)]
+enum Ctor {
+    Enum(Name, Vec<Option<Ty>>),
+    Tuple(Vec<Option<Ty>>),
+    Struct(Vec<(Name, Option<Ty>)>),
+    /// One of infinitely many; never part of a complete set of constructors
+    Int(BigInt),
+}
+
+impl Ctor {
+    fn arg_tys(&self) -> Vec<Option<Ty>> {
+        match *self {
+            Ctor::Enum(_, ref tys) | Ctor::Tuple(ref tys) => tys.clone(),
+            Ctor::Struct(ref fields) => fields.iter().map(|(_, t)| t.clone()).collect(),
+            Ctor::Int(_) => vec![],
+        }
+    }
+
+    fn arity(&self) -> usize { self.arg_tys().len() }
+
+    fn is_head_of(&self, p: &Pat) -> bool {
+        match (self, p) {
+            (Ctor::Enum(name, _), Pat::Enum(p_name, _)) => name == p_name,
+            (Ctor::Tuple(_), Pat::Tuple(_)) | (Ctor::Struct(_), Pat::Struct(_)) => true,
+            (Ctor::Int(i), Pat::Int(p_i)) => i == p_i,
+            _ => false,
+        }
+    }
+
+    /// The arguments of `p`, if `p` is built from this constructor.
+    fn args_of(&self, p: &Pat) -> Option<Vec<Pat>> {
+        match (self, p) {
+            (_, Pat::Wild) => Some(vec![Pat::Wild; self.arity()]),
+            (Ctor::Enum(name, tys), Pat::Enum(p_name, args)) if name == p_name => {
+                // (Arity errors are the typechecker's job; just don't crash.)
+                let mut args = args.clone();
+                args.resize(tys.len(), Pat::Wild);
+                Some(args)
+            }
+            (Ctor::Tuple(tys), Pat::Tuple(args)) => {
+                let mut args = args.clone();
+                args.resize(tys.len(), Pat::Wild);
+                Some(args)
+            }
+            (Ctor::Struct(fields), Pat::Struct(p_fields)) => Some(
+                fields
+                    .iter()
+                    .map(|(name, _)| {
+                        p_fields
+                            .iter()
+                            .find(|(p_name, _)| p_name == name)
+                            .map(|(_, p)| p.clone())
+                            .unwrap_or(Pat::Wild)
+                    })
+                    .collect(),
+            ),
+            (Ctor::Int(i), Pat::Int(p_i)) if i == p_i => Some(vec![]),
+            _ => None,
+        }
+    }
+
+    fn build(&self, args: Vec<Pat>) -> Pat {
+        match *self {
+            Ctor::Enum(name, _) => Pat::Enum(name, args),
+            Ctor::Tuple(_) => Pat::Tuple(args),
+            Ctor::Struct(ref fields) => {
+                Pat::Struct(fields.iter().map(|(name, _)| *name).zip(args).collect())
+            }
+            Ctor::Int(ref i) => Pat::Int(i.clone()),
+        }
+    }
+}
+
+/// Look through variable references, type applications, and `mu`s to find the shape of `t`.
+fn resolve_shape(t: &Ty, env: &Assoc<Name, Ty>) -> Ty {
+    let resolved = crate::ty_compare::unification.with(|unif| {
+        crate::ty_compare::resolve(Clo { it: t.clone(), env: env.clone() }, &unif.borrow()).it
+    });
+    match resolved.0 {
+        // Matching doesn't care about recursion, just the shape of the outermost layer:
+        Node(ref f, ref parts, _) if f.name == n("mu_type") => {
+            resolve_shape(&Ty(strip_ee(parts.get_leaf_or_panic(&n("body"))).clone()), env)
+        }
+        _ => resolved,
+    }
+}
+
+/// All the constructors for the type of a column, if we know them all.
+/// (The patterns in the column are consulted in case the type isn't helpful.)
+fn constructors(t: &Option<Ty>, column: &[&Pat], env: &Assoc<Name, Ty>) -> Option<Vec<Ctor>> {
+    let tys_of = |asts: Vec<&Ast>| -> Vec<Option<Ty>> {
+        asts.into_iter().map(|a| Some(Ty(a.clone()))).collect()
+    };
+    if let Some(t) = t {
+        if let Node(ref f, ref parts, _) = resolve_shape(t, env).0 {
+            if f.name == n("enum") {
+                return Some(
+                    parts
+                        .march_all(&[n("name")])
+                        .iter()
+                        .map(|arm| {
+                            Ctor::Enum(
+                                ast_to_name(arm.get_leaf_or_panic(&n("name"))),
+                                tys_of(arm.get_rep_leaf_or_panic(n("component"))),
+                            )
+                        })
+                        .collect(),
+                );
+            } else if f.name == n("tuple") {
+                return Some(vec![Ctor::Tuple(tys_of(parts.get_rep_leaf_or_panic(n("component"))))]);
+            } else if f.name == n("struct") {
+                return Some(vec![Ctor::Struct(
+                    parts
+                        .march_all(&[n("component")])
+                        .iter()
+                        .map(|c| {
+                            (
+                                ast_to_name(c.get_leaf_or_panic(&n("component_name"))),
+                                Some(Ty(c.get_leaf_or_panic(&n("component")).clone())),
+                            )
+                        })
+                        .collect(),
+                )]);
+            }
+        }
+    }
+    // The type is unknown (or not built from constructors), but single-constructor patterns
+    //  still tell us everything we need to know:
+    for p in column {
+        match *p {
+            Pat::Tuple(ref cs) => return Some(vec![Ctor::Tuple(vec![None; cs.len()])]),
+            Pat::Struct(_) => {
+                let mut fields = vec![];
+                for p in column {
+                    if let Pat::Struct(ref cs) = **p {
+                        for (name, _) in cs {
+                            if !fields.iter().any(|(f_name, _)| f_name == name) {
+                                fields.push((*name, None));
+                            }
+                        }
+                    }
+                }
+                return Some(vec![Ctor::Struct(fields)]);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace each row starting with an or-pattern by one row per alternative.
+fn expand_or(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    let mut res = vec![];
+    for row in rows {
+        match row[0] {
+            Pat::Or(ref alts) => {
+                for alt in alts {
+                    let mut alt_row = vec![alt.clone()];
+                    alt_row.extend_from_slice(&row[1..]);
+                    res.append(&mut expand_or(&[alt_row]));
+                }
+            }
+            _ => res.push(row.clone()),
+        }
+    }
+    res
+}
+
+/// The rows of `rows` that match `ctor`, with `ctor`'s arguments replacing the first column.
+fn specialize(rows: &[Vec<Pat>], ctor: &Ctor) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            ctor.args_of(&row[0]).map(|mut args| {
+                args.extend_from_slice(&row[1..]);
+                args
+            })
+        })
+        .collect()
+}
+
+/// The rows of `rows` that match anything at all in the first column, without that column.
+fn default(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter().filter(|row| row[0] == Pat::Wild).map(|row| row[1..].to_vec()).collect()
+}
+
+fn with_arg_tys(ctor: &Ctor, tys: &[Option<Ty>]) -> Vec<Option<Ty>> {
+    let mut res = ctor.arg_tys();
+    res.extend_from_slice(&tys[1..]);
+    res
+}
+
+/// Does some value match `q`, but no row of `rows`?
+fn is_useful(rows: &[Vec<Pat>], q: &[Pat], tys: &[Option<Ty>], env: &Assoc<Name, Ty>) -> bool {
+    if q.is_empty() {
+        return rows.is_empty();
+    }
+    if let Pat::Or(ref alts) = q[0] {
+        return alts.iter().any(|alt| {
+            let mut alt_q = vec![alt.clone()];
+            alt_q.extend_from_slice(&q[1..]);
+            is_useful(rows, &alt_q, tys, env)
+        });
+    }
+    let rows = &expand_or(rows);
+    let column: Vec<&Pat> = rows.iter().map(|row| &row[0]).chain(Some(&q[0])).collect();
+    let ctors = constructors(&tys[0], &column, env);
+
+    let head_ctor = match q[0] {
+        Pat::Int(ref i) => Some(Ctor::Int(i.clone())),
+        _ => ctors.iter().flatten().find(|c| c.is_head_of(&q[0])).cloned(),
+    };
+    if let Some(ctor) = head_ctor {
+        let mut sub_q = ctor.args_of(&q[0]).unwrap();
+        sub_q.extend_from_slice(&q[1..]);
+        return is_useful(&specialize(rows, &ctor), &sub_q, &with_arg_tys(&ctor, tys), env);
+    }
+
+    match ctors {
+        Some(ref ctors) if complete(ctors, rows) => ctors.iter().any(|ctor| {
+            let mut sub_q = vec![Pat::Wild; ctor.arity()];
+            sub_q.extend_from_slice(&q[1..]);
+            is_useful(&specialize(rows, ctor), &sub_q, &with_arg_tys(ctor, tys), env)
+        }),
+        _ => is_useful(&default(rows), &q[1..], &tys[1..], env),
+    }
+}
+
+/// Does every constructor appear at the head of some row?
+fn complete(ctors: &[Ctor], rows: &[Vec<Pat>]) -> bool {
+    ctors.iter().all(|ctor| rows.iter().any(|row| ctor.is_head_of(&row[0])))
+}
+
+/// Find a vector of values (as patterns) that no row of `rows` matches.
+fn missing(rows: &[Vec<Pat>], tys: &[Option<Ty>], env: &Assoc<Name, Ty>) -> Option<Vec<Pat>> {
+    if tys.is_empty() {
+        return if rows.is_empty() { Some(vec![]) } else { None };
+    }
+    let rows = &expand_or(rows);
+    let column: Vec<&Pat> = rows.iter().map(|row| &row[0]).collect();
+    let ctors = constructors(&tys[0], &column, env);
+
+    match ctors {
+        Some(ref ctors) if complete(ctors, rows) => {
+            for ctor in ctors {
+                if let Some(mut witness) =
+                    missing(&specialize(rows, ctor), &with_arg_tys(ctor, tys), env)
+                {
+                    let rest = witness.split_off(ctor.arity());
+                    let mut res = vec![ctor.build(witness)];
+                    res.extend(rest);
+                    return Some(res);
+                }
+            }
+            None
+        }
+        _ => {
+            let mut witness = missing(&default(rows), &tys[1..], env)?;
+            // Be specific, if we can:
+            let head = match ctors {
+                Some(ref ctors) => {
+                    match ctors.iter().find(|c| !rows.iter().any(|row| c.is_head_of(&row[0]))) {
+                        Some(ctor) => ctor.build(vec![Pat::Wild; ctor.arity()]),
+                        None => Pat::Wild,
+                    }
+                }
+                None => Pat::Wild,
+            };
+            witness.insert(0, head);
+            Some(witness)
+        }
+    }
+}
+
+/// The result of checking the arms of a `match`.
+pub struct Coverage {
+    /// A value that no arm matches (`None` if the match is exhaustive)
+    pub missing: Option<Pat>,
+    /// The indices of arms that can never be reached
+    pub redundant: Vec<usize>,
+}
+
+/// Check the patterns of `match` arms against the type of the scrutinee.
+/// Arms with guards (as indicated by `guarded`) might not match, so they don't cover anything.
+/// Neither do opaque patterns, so a `match` that relies on them needs a catch-all arm.
+pub fn check_match(
+    scrutinee_ty: &Ty,
+    pats: &[Ast],
+    guarded: &[bool],
+    env: &Assoc<Name, Ty>,
+) -> Coverage
+{
+    let tys = vec![Some(scrutinee_ty.clone())];
+    let rows: Vec<Vec<Pat>> = pats.iter().map(|p| vec![Pat::from_ast(p)]).collect();
+    let is_guarded = |i: usize| guarded.get(i).cloned().unwrap_or(false);
+
+    let mut redundant = vec![];
+    let mut covering_rows = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        if !row[0].is_opaque() && !is_useful(&covering_rows, row, &tys, env) {
+            redundant.push(i);
+        }
+        if !is_guarded(i) {
+            covering_rows.push(row.clone());
+        }
+    }
+
+    // (For redundancy, opaque patterns match nothing, so they don't hide anything.)
+    let covering_rows: Vec<Vec<Pat>> = covering_rows
+        .iter()
+        .filter(|row| !row[0].contains(&|p| *p == Pat::Quote))
+        .map(|row| vec![row[0].assume_opaque_matches()])
+        .collect();
+    let missing = missing(&covering_rows, &tys, env).map(|mut witness| witness.remove(0));
+
+    Coverage { missing, redundant }
+}
+
+#[test]
+fn basic_coverage() {
+    let env = assoc_n!("Bool" => uty!({enum : [True [] ; False []]}));
+    let bool_ty = uty!(Bool);
+
+    let c = check_match(&bool_ty, &[u!({Pat enum_pat : True []})], &[], &env);
+    assert_eq!(c.missing, Some(Pat::Enum(n("False"), vec![])));
+    assert_eq!(c.redundant, Vec::<usize>::new());
+
+    let c = check_match(&bool_ty, &[ast!("b"), u!({Pat enum_pat : False []})], &[], &env);
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, vec![1]);
+
+    // Quotation patterns might not match, so they can't make anything redundant or exhaustive:
+    let quoted = ast!({"Pat" "quote_pat" : "nt" => (vr "Expr"), "body" => "a"});
+    let c = check_match(&bool_ty, &[quoted.clone(), u!({Pat enum_pat : True []})], &[], &env);
+    assert_eq!(c.missing, Some(Pat::Enum(n("False"), vec![])));
+    assert_eq!(c.redundant, Vec::<usize>::new());
+    let c = check_match(&bool_ty, &[quoted, ast!("b")], &[], &env);
+    assert_eq!(c.missing, None);
+
+    // But we can't see inside opaque ones until later, so they're assumed to match:
+    let unquoted = ast!({"Pat" "quote_pat" : "nt" => (vr "Pat"), "body" => (-- 1 (vr "a"))});
+    let c = check_match(&bool_ty, &[unquoted, u!({Pat enum_pat : True []})], &[], &env);
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, Vec::<usize>::new());
+
+    // Guarded arms might not match either:
+    let true_pat = u!({Pat enum_pat : True []});
+    let false_pat = u!({Pat enum_pat : False []});
+    let c = check_match(&bool_ty, &[true_pat.clone(), false_pat], &[true, false], &env);
+    assert_eq!(c.missing, Some(Pat::Enum(n("True"), vec![])));
+    let c = check_match(&bool_ty, &[true_pat, ast!("b")], &[true, false], &env);
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, Vec::<usize>::new());
+}
+
+#[test]
+fn nested_coverage() {
+    let env = assoc_n!("Option" => uty!({enum : [Some [{Int :}] ; None []]}));
+    let pair_ty = uty!({tuple : [Option ; Option]});
+
+    let some_x = u!({Pat enum_pat : Some [(at x)]});
+    let none = u!({Pat enum_pat : None []});
+
+    let c = check_match(
+        &pair_ty,
+        &[
+            u!({Pat tuple_pat : [(, some_x.clone()) ; (at y)]}),
+            u!({Pat tuple_pat : [(, none.clone()) ; (, none.clone())]}),
+        ],
+        &[],
+        &env,
+    );
+    assert_eq!(format!("{}", c.missing.unwrap()), "**[+[None]+ +[Some _]+]**");
+
+    let c = check_match(
+        &pair_ty,
+        &[
+            u!({Pat tuple_pat : [(, some_x.clone()) ; (at y)]}),
+            u!({Pat tuple_pat : [(at x) ; (, none.clone())]}),
+            u!({Pat tuple_pat : [(, none.clone()) ; (, some_x.clone())]}),
+            u!({Pat tuple_pat : [(, some_x.clone()) ; (, none.clone())]}),
+        ],
+        &[],
+        &env,
+    );
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, vec![3]);
+
+    // Struct fields that aren't mentioned match anything:
+    let point_ty = uty!({struct : [x {Int :} ; y Option]});
+    let c = check_match(
+        &point_ty,
+        &[u!({Pat struct_pat : [y (, some_x.clone())]}), u!({Pat struct_pat : [x (at x)]})],
+        &[],
+        &env,
+    );
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, Vec::<usize>::new());
+    let c = check_match(&point_ty, &[u!({Pat struct_pat : [y (, some_x.clone())]})], &[], &env);
+    assert_eq!(format!("{}", c.missing.unwrap()), "*[x : _ y : +[None]+]*");
+}
+
+#[test]
+fn or_and_literal_coverage() {
+    let env = assoc_n!("Option" => uty!({enum : [Some [{Int :}] ; None []]}));
+    let option_ty = uty!(Option);
+
+    let some_0 = u!({Pat enum_pat : Some [{Pat int_pat : (, Atom(n("0")))}]});
+    let none = u!({Pat enum_pat : None []});
+
+    let some_0_or_none = u!({Pat or_pat : (, some_0.clone()) (, none.clone())});
+    let c = check_match(&option_ty, &[some_0_or_none], &[], &env);
+    assert_eq!(format!("{}", c.missing.unwrap()), "+[Some _]+");
+
+    let c = check_match(
+        &option_ty,
+        &[some_0.clone(), u!({Pat or_pat : (, none.clone()) (, some_0.clone())}), ast!("x")],
+        &[],
+        &env,
+    );
+    assert_eq!(c.missing, None);
+    assert_eq!(c.redundant, Vec::<usize>::new());
+
+    let c = check_match(&option_ty, &[ast!("x"), u!({Pat wildcard_pat :}), some_0], &[], &env);
+    assert_eq!(c.redundant, vec![1, 2]);
+}
@@ -2,11 +2,13 @@ use crate::{
     ast::Ast,
     ast_walk::{LazyWalkReses, WalkRule, WalkRule::LiteralLike},
     form::Form,
+    grammar::SynEnv,
     name::{n, Name},
     runtime::{eval, eval::Value},
     util::assoc::Assoc,
     walk_mode::{NegativeWalkMode, WalkElt, WalkMode},
 };
+use std::{cell::RefCell, fmt};
 
 custom_derive! {
     #[derive(Copy, Clone, Debug, Reifiable)]
@@ -28,7 +30,7 @@ impl WalkMode for ExpandMacros {
     type ExtraInfo = ();
 
     fn get_walk_rule(f: &Form) -> WalkRule<ExpandMacros> {
-        if f.name == n("macro_invocation") {
+        if crate::core_macro_forms::is_macro_invocation(f) {
             let rule = f.eval.pos().clone();
             cust_rc_box!(move |parts| {
                 match rule {
@@ -77,6 +79,73 @@ pub fn expand(ast: &Ast) -> Result<Ast, ()> {
     Ok(Ast::reflect(&crate::ast_walk::walk::<ExpandMacros>(ast, &LazyWalkReses::new_empty())?))
 }
 
+/// One macro invocation, and what it expanded to.
+#[derive(Clone)]
+pub struct ExpansionStep {
+    pub macro_name: Name,
+    pub invocation: Ast,
+    /// The syntax bound to each `Named` part (flattened, if it's under a repetition).
+    pub arguments: Vec<(Name, Vec<Ast>)>,
+    /// The syntax the macro produced (before expanding any macros in it).
+    pub result: Ast,
+    /// The grammar where the macro was defined, which is needed to unparse its syntax.
+    pub grammar: SynEnv,
+}
+
+thread_local! {
+    /// When `Some`, each macro invocation is recorded here as it's expanded.
+    static expansion_trace: RefCell<Option<Vec<ExpansionStep>>> = RefCell::new(None);
+}
+
+/// Are we recording `ExpansionStep`s? (They're expensive to make, so check first.)
+pub fn tracing() -> bool { expansion_trace.with(|trace| trace.borrow().is_some()) }
+
+pub fn record_step(step: ExpansionStep) {
+    expansion_trace.with(|trace| {
+        if let Some(ref mut steps) = *trace.borrow_mut() {
+            steps.push(step)
+        }
+    })
+}
+
+/// Like `expand`, but also returns every macro invocation, in the order they were expanded.
+/// (Outer invocations come before the ones that they produce.)
+/// The steps are returned even if expansion fails, since that's when they're most useful.
+pub fn expand_with_trace(ast: &Ast) -> (Result<Ast, ()>, Vec<ExpansionStep>) {
+    let outer = expansion_trace.with(|trace| trace.replace(Some(vec![])));
+    let res = expand(ast);
+    let steps = expansion_trace.with(|trace| trace.replace(outer)).unwrap_or_default();
+    (res, steps)
+}
+
+/// Like `format!("{}", a)`, but with the right grammar for non-core syntax.
+fn unparse_in(a: &Ast, grammar: &SynEnv) -> String {
+    match *a {
+        Ast::Node(ref form, ref body, _) => {
+            crate::unparse::unparse_mbe(&form.grammar, a, body, grammar)
+        }
+        Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) => {
+            unparse_in(body, grammar)
+        }
+        _ => format!("{}", a),
+    }
+}
+
+impl fmt::Display for ExpansionStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.macro_name)?;
+        if let Some(line) = crate::core_macro_forms::source_line(&self.invocation) {
+            write!(f, " at line {}", line)?;
+        }
+        writeln!(f, ": {}", unparse_in(&self.invocation, &self.grammar))?;
+        for (part, args) in &self.arguments {
+            let args: Vec<String> = args.iter().map(|a| unparse_in(a, &self.grammar)).collect();
+            writeln!(f, "    {} := {}", part, args.join("  "))?;
+        }
+        write!(f, "  ⇒ {}", unparse_in(&self.result, &self.grammar))
+    }
+}
+
 #[test]
 fn expand_basic_macros() {
     use crate::core_macro_forms::macro_invocation;
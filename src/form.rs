@@ -31,6 +31,8 @@ custom_derive! {
         pub eval: BiDiWR<crate::runtime::eval::Eval, crate::runtime::eval::Destructure>,
         /// At runtime, pick up code to use it as a value
         pub quasiquote: BiDiWR<crate::runtime::eval::QQuote, crate::runtime::eval::QQuoteDestr>,
+        /// (Meaningful for macro invocations only) What the macro was made from.
+        pub macro_definition: Option<Rc<crate::core_macro_forms::MacroDefinition>>,
     }
 }
 
@@ -93,5 +95,6 @@ pub fn simple_form(form_name: &str, p: FormPat) -> Rc<Form> {
         synth_type: Positive(NotWalked),
         eval: Positive(NotWalked),
         quasiquote: Both(LiteralLike, LiteralLike),
+        macro_definition: None,
     })
 }
@@ -123,6 +123,8 @@ impl FormPat {
                 }
                 match **sub {
                     Call(nt) => Some(nt),
+                    // An atom supplied by the grammar itself (see `capture`) acts like one parsed:
+                    Anyways(Ast::Atom(_)) => Some(Name::global("Atom")),
                     _ => None,
                 }
             }
@@ -205,6 +205,13 @@ where I: Iterator<Item = &'a Ast> {
             }
             result
         }
+        // Optional parts (e.g. `match` guards) can be omitted entirely, instead of with `(~)`:
+        Alt(ref subs)
+            if matches!(*subs[0], Seq(ref empty) if empty.is_empty())
+                && flimsy_seq.peek() != Some(&&Shape(vec![Atom(n("SEQ"))])) =>
+        {
+            EnvMBE::new()
+        }
         _ => {
             let flimsy = *match flimsy_seq.peek() {
                 None => return EnvMBE::new(), // Or is this an error?
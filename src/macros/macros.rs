@@ -217,12 +217,18 @@ macro_rules! ty {
 macro_rules! ty_err_val {
     ( $name:tt ( $($arg:expr),* ) at $loc:expr) => {
         crate::util::err::sp(crate::ty::TyErr::$name( $($arg),* ), $loc.clone())
+    };
+    ( $name:tt at $loc:expr) => {
+        crate::util::err::sp(crate::ty::TyErr::$name, $loc.clone())
     }
 }
 
 macro_rules! ty_err {
     ( $name:tt ( $($arg:expr),* ) at $loc:expr) => {
-        return Err(ty_err_val!( $name ( $($arg),* ) at $loc));
+        return Err(ty_err_val!( $name ( $($arg),* ) at $loc))
+    };
+    ( $name:tt at $loc:expr) => {
+        return Err(ty_err_val!( $name at $loc))
     }
 }
 
@@ -237,6 +243,9 @@ macro_rules! ty_exp { // type expectation
 macro_rules! ty_err_p { // type error pattern
     ( $name:tt ( $($arg:pat),* ) ) => {
         Err( crate::util::err::Spanned { body: crate::ty::TyErr::$name( $($arg),* ), loc: _ } )
+    };
+    ( $name:tt ) => {
+        Err( crate::util::err::Spanned { body: crate::ty::TyErr::$name, loc: _ } )
     }
 }
 
@@ -465,6 +474,7 @@ macro_rules! basic_typed_form {
                 crate::ast_walk::WalkRule::LiteralLike,
             ),
             eval: crate::form::Positive($eval),
+            macro_definition: None,
         })
     };
 }
@@ -481,6 +491,7 @@ macro_rules! typed_form {
                 crate::ast_walk::WalkRule::LiteralLike,
             ),
             eval: crate::form::Positive($eval),
+            macro_definition: None,
         })
     };
 }
@@ -497,6 +508,7 @@ macro_rules! negative_typed_form {
                 crate::ast_walk::WalkRule::LiteralLike,
             ),
             eval: crate::form::Negative($eval),
+            macro_definition: None,
         })
     };
 }
@@ -528,7 +540,7 @@ macro_rules! val {
         crate::runtime::eval::Value::Enum(crate::name::n($nm), vec![ $( val! $v ),* ])
     };
     (seq $($v:tt)*) => {
-        crate::runtime::eval::Value::Sequence(vec![ $( Rc::new(val! $v) ),* ])
+        crate::runtime::eval::Value::Sequence(vec![ $( std::rc::Rc::new(val! $v) ),* ].into())
     };
     (, $interpolate:expr) => { $interpolate }
 }
@@ -577,6 +589,7 @@ macro_rules! bind_patterns {
                 bind_patterns!($iter; ($( $p_cdr, )*) => $body)
             }
             None => { icp!("too few arguments"); }
+            #[allow(unreachable_patterns)] // `$p_car` might match anything
             Some(ref other) => { icp!("[type error] in argument: {:#?}", other); }
         }
     }
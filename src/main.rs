@@ -40,15 +40,18 @@ mod unparse;
 mod form;
 
 mod ast_walk;
+mod exhaustiveness;
 mod expand;
 mod ty;
 mod ty_compare;
 mod walk_mode;
 
 mod runtime;
+mod serialize;
 
 mod core_forms;
 mod core_macro_forms;
+mod core_module_forms;
 mod core_qq_forms;
 mod core_type_forms;
 
@@ -65,8 +68,13 @@ use crate::{
 use std::{borrow::Cow, cell::RefCell, io::BufRead};
 
 thread_local! {
+    pub static syn_env : RefCell<grammar::SynEnv> = RefCell::new(core_forms::get_core_forms());
     pub static ty_env : RefCell<Assoc<Name, Ty>> = RefCell::new(core_values::core_types());
     pub static val_env : RefCell<Assoc<Name, Value>> = RefCell::new(core_values::core_values());
+    /// What macros (and `for_syntax`) can use when they run, during parsing:
+    ///  the core values and, if it's loaded, the standard library.
+    pub static syntax_phase_envs : RefCell<earley::CodeEnvs> =
+        RefCell::new(core_values::get_core_envs());
 }
 
 struct LineHelper {
@@ -134,9 +142,20 @@ impl rustyline::Helper for LineHelper {}
 
 #[cfg_attr(tarpaulin, skip)]
 fn main() {
-    let arguments: Vec<String> = std::env::args().collect();
+    let mut arguments: Vec<String> = std::env::args().collect();
+    // `--no-std` (anywhere) means that the standard library isn't loaded:
+    let no_std = arguments.iter().any(|arg| arg == "--no-std");
+    arguments.retain(|arg| arg != "--no-std");
     let prelude_filename = format!("{}/.unseemly_prelude", dirs::home_dir().unwrap().display());
     let history_filename = format!("{}/.unseemly_history", dirs::home_dir().unwrap().display());
+    // Compiled modules are saved for next time here:
+    core_module_forms::cache_dir
+        .with(|d| *d.borrow_mut() = dirs::cache_dir().map(|cache| cache.join("unseemly")));
+    if !no_std {
+        if let Err(e) = load_std() {
+            println!("\x1b[1;31m✘\x1b[0m Error in the standard library: {}", e);
+        }
+    }
 
     if arguments.len() == 1 {
         let mut rl = rustyline::Editor::<LineHelper>::new();
@@ -148,6 +167,7 @@ fn main() {
         let just_type = regex::Regex::new("^:t (.*)$").unwrap();
         let just_eval = regex::Regex::new("^:e (.*)$").unwrap();
         let type_and_expand = regex::Regex::new("^:x (.*)$").unwrap();
+        let type_and_trace_expand = regex::Regex::new("^:xs (.*)$").unwrap();
         let canon_type = regex::Regex::new("^:tt (.*)$").unwrap();
         let assign_value = regex::Regex::new("^(\\w+)\\s*:=(.*)$").unwrap();
         let save_value = regex::Regex::new("^:s +((\\w+)\\s*:=(.*))$").unwrap();
@@ -159,6 +179,7 @@ fn main() {
         println!("                  \x1b[1;38mUnseemly\x1b[0m");
         println!("    `<expr>` to (typecheck and expand and) evaluate `<expr>`.");
         println!("    `:x <expr>` to (typecheck and) expand `<expr>`.");
+        println!("    `:xs <expr>` to (typecheck and) expand `<expr>`, showing each macro expansion step.");
         println!("    `:e <expr>` to (expand and) evaluate `<expr>` without typechecking.");
         println!("    `<name> := <expr>` to bind a name for this session.");
         println!("    `:t <expr>` to synthesize the type of <expr>.");
@@ -172,6 +193,9 @@ fn main() {
         println!("    Tab-completion works on variables, and lots of Bash-isms work.");
         println!();
 
+        if !no_std {
+            println!("    [standard library v{} loaded]", core_module_forms::STD_VERSION);
+        }
         if let Ok(prelude_file) = File::open(&Path::new(&prelude_filename)) {
             let prelude = std::io::BufReader::new(prelude_file);
             for line in prelude.lines() {
@@ -188,6 +212,7 @@ fn main() {
                     }
                 }
             }
+            report_warnings();
             println!("    [prelude loaded from {}]", prelude_filename);
         }
 
@@ -206,6 +231,8 @@ fn main() {
                 eval_unseemly_program_without_typechecking(&caps[1]).map(|x| format!("{}", x))
             } else if let Some(caps) = type_and_expand.captures(&line) {
                 type_and_expand_unseemly_program(&caps[1]).map(|x| format!("{}", x))
+            } else if let Some(caps) = type_and_trace_expand.captures(&line) {
+                type_and_trace_expand_unseemly_program(&caps[1])
             } else if let Some(caps) = canon_type.captures(&line) {
                 canonicalize_type(&caps[1]).map(|x| format!("{}", x))
             } else if let Some(caps) = assign_value.captures(&line) {
@@ -244,23 +271,90 @@ fn main() {
                 eval_unseemly_program(&line).map(|x| format!("{}", x))
             };
 
+            report_warnings();
             match result_display {
                 Ok(v) => println!("\x1b[1;32m≉\x1b[0m {}", v),
                 Err(s) => println!("\x1b[1;31m✘\x1b[0m {}", s),
             }
         }
         rl.save_history(&history_filename).unwrap();
+    } else if arguments[1] == "expand" {
+        // `unseemly expand [--trace] <file>`
+        let trace = arguments.get(2).map(String::as_str) == Some("--trace");
+        let filename = match arguments.get(if trace { 3 } else { 2 }) {
+            Some(filename) => filename,
+            None => {
+                println!("Usage: unseemly expand [--trace] <file>");
+                return;
+            }
+        };
+
+        let mut raw_input = String::new();
+        File::open(&Path::new(filename))
+            .expect("Error opening file")
+            .read_to_string(&mut raw_input)
+            .expect("Error reading file");
+        set_import_base_dir(filename);
+
+        let result = if trace {
+            type_and_trace_expand_unseemly_program(&raw_input)
+        } else {
+            type_and_expand_unseemly_program(&raw_input).map(|x| format!("{}", x))
+        };
+
+        report_warnings();
+        match result {
+            Ok(v) => println!("{}", v),
+            Err(e) => println!("\x1b[1;31m✘\x1b[0m {}", e),
+        }
+    } else if arguments[1] == "dump" {
+        // `unseemly dump [--json] (ast|expanded|type|value) <file>`
+        let json = arguments.get(2).map(String::as_str) == Some("--json");
+        let (what, filename) = match (
+            arguments.get(if json { 3 } else { 2 }),
+            arguments.get(if json { 4 } else { 3 }),
+        ) {
+            (Some(what), Some(filename)) => (what, filename),
+            _ => {
+                println!("Usage: unseemly dump [--json] (ast|expanded|type|value) <file>");
+                return;
+            }
+        };
+
+        let mut raw_input = String::new();
+        File::open(&Path::new(filename))
+            .expect("Error opening file")
+            .read_to_string(&mut raw_input)
+            .expect("Error reading file");
+        set_import_base_dir(filename);
+
+        let result = dump_unseemly_program(&raw_input, what, json);
+
+        report_warnings();
+        match result {
+            Ok(v) => println!("{}", v),
+            Err(e) => println!("\x1b[1;31m✘\x1b[0m {}", e),
+        }
     } else {
-        let filename = &arguments[1];
+        // `unseemly [run] <file>`
+        let filename = match arguments.get(if arguments[1] == "run" { 2 } else { 1 }) {
+            Some(filename) => filename,
+            None => {
+                println!("Usage: unseemly [run] <file>");
+                return;
+            }
+        };
 
         let mut raw_input = String::new();
         File::open(&Path::new(filename))
             .expect("Error opening file")
             .read_to_string(&mut raw_input)
             .expect("Error reading file");
+        set_import_base_dir(filename);
 
         let result = eval_unseemly_program(&raw_input);
 
+        report_warnings();
         match result {
             Ok(v) => println!("{}", v),
             Err(e) => println!("\x1b[1;31m✘\x1b[0m {}", e),
@@ -268,6 +362,51 @@ fn main() {
     }
 }
 
+/// Make the standard library's values, types, and syntax available to everything after this.
+fn load_std() -> Result<(), String> {
+    let std = core_module_forms::load_std()?;
+    syn_env.with(|grammar| {
+        let new_grammar = std.extend_grammar(&grammar.borrow());
+        *grammar.borrow_mut() = new_grammar;
+    });
+    ty_env.with(|tys| {
+        let new_tys = tys.borrow().set_assoc(std.types());
+        *tys.borrow_mut() = new_tys;
+    });
+    val_env.with(|vals| {
+        let new_vals = vals.borrow().set_assoc(std.values());
+        *vals.borrow_mut() = new_vals;
+    });
+    // Macros can use it, too:
+    let (syn_tys, syn_vals) = syntax_phase_envs.with(|envs| envs.borrow().clone());
+    let syn_tys = syn_tys.env.set_assoc(std.types());
+    core_macro_forms::set_syntax_phase_types(syn_tys.clone());
+    syntax_phase_envs.with(|envs| {
+        *envs.borrow_mut() = (
+            ast_walk::LazyWalkReses::new_wrapper(syn_tys),
+            ast_walk::LazyWalkReses::new_wrapper(syn_vals.env.set_assoc(std.values())),
+        )
+    });
+    Ok(())
+}
+
+/// `import`s in `filename` are relative to the directory it's in.
+fn set_import_base_dir(filename: &str) {
+    if let Some(dir) = Path::new(filename).parent() {
+        core_module_forms::import_base_dir.with(|d| *d.borrow_mut() = dir.to_path_buf());
+    }
+}
+
+fn report_warnings() {
+    for warning in ty::take_warnings() {
+        println!("\x1b[1;33m⚠\x1b[0m {}", warning);
+    }
+}
+
+fn type_errors_to_string(errs: Vec<ty::TypeError>) -> String {
+    errs.iter().map(|e| format!("{}", e)).collect::<Vec<_>>().join("\n")
+}
+
 fn assign_variable(name: &str, expr: &str) -> Result<Value, String> {
     let res = eval_unseemly_program(expr);
 
@@ -288,14 +427,14 @@ fn assign_variable(name: &str, expr: &str) -> Result<Value, String> {
 fn assign_t_var(name: &str, t: &str) -> Result<ty::Ty, String> {
     let ast = grammar::parse(
         &grammar::FormPat::Call(n("Type")),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         t,
     )
     .map_err(|e| e.msg)?;
 
     let res = ty_env
-        .with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)));
+        .with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string));
 
     if let Ok(ref t) = res {
         ty_env.with(|tys| {
@@ -310,20 +449,20 @@ fn assign_t_var(name: &str, t: &str) -> Result<ty::Ty, String> {
 fn canonicalize_type(t: &str) -> Result<ty::Ty, String> {
     let ast = grammar::parse(
         &grammar::FormPat::Call(n("Type")),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         t,
     )
     .map_err(|e| e.msg)?;
 
-    ty_env.with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)))
+    ty_env.with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string))
 }
 
 fn parse_unseemly_program(program: &str, pretty: bool) -> Result<String, String> {
     let ast = grammar::parse(
         &core_forms::outermost_form(),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         program,
     )
     .map_err(|e| e.msg)?;
@@ -338,61 +477,121 @@ fn parse_unseemly_program(program: &str, pretty: bool) -> Result<String, String>
 fn type_unseemly_program(program: &str) -> Result<ty::Ty, String> {
     let ast = grammar::parse(
         &core_forms::outermost_form(),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         program,
     )
     .map_err(|e| e.msg)?;
 
-    ty_env.with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)))
+    ty_env.with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string))
+}
+
+/// Evaluate an expanded program, saying which macros produced the code that failed (if any).
+fn run(core_ast: &Ast) -> Result<Value, String> {
+    // Forget about old errors:
+    runtime::eval::take_error_origin_chain();
+    runtime::eval::take_runtime_error();
+    val_env.with(|vals| {
+        eval(core_ast, vals.borrow().clone()).map_err(|_| {
+            format!(
+                "{}{}",
+                runtime::eval::take_runtime_error().unwrap_or_else(|| "???".to_owned()),
+                runtime::eval::take_error_origin_chain()
+            )
+        })
+    })
 }
 
 fn eval_unseemly_program_without_typechecking(program: &str) -> Result<Value, String> {
     let ast: Ast = grammar::parse(
         &core_forms::outermost_form(),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         program,
     )
     .map_err(|e| e.msg)?;
 
     let core_ast = crate::expand::expand(&ast).map_err(|_| "error".to_owned())?;
 
-    val_env.with(|vals| eval(&core_ast, vals.borrow().clone()).map_err(|_| "???".to_string()))
+    run(&core_ast)
 }
 
 fn eval_unseemly_program(program: &str) -> Result<Value, String> {
     let ast: Ast = grammar::parse(
         &core_forms::outermost_form(),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         program,
     )
     .map_err(|e| e.msg)?;
 
     let _type = ty_env
-        .with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)))?;
+        .with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string))?;
 
     let core_ast = crate::expand::expand(&ast).map_err(|_| "error".to_owned())?;
 
-    val_env.with(|vals| eval(&core_ast, vals.borrow().clone()).map_err(|_| "???".to_string()))
+    run(&core_ast)
 }
 
 fn type_and_expand_unseemly_program(program: &str) -> Result<ast::Ast, String> {
     let ast: Ast = grammar::parse(
         &core_forms::outermost_form(),
-        &core_forms::get_core_forms(),
-        runtime::core_values::get_core_envs(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
         program,
     )
     .map_err(|e| e.msg)?;
 
     let _type = ty_env
-        .with(|tys| ty::synth_type(&ast, tys.borrow().clone()).map_err(|e| format!("{}", e)))?;
+        .with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string))?;
 
     crate::expand::expand(&ast).map_err(|_| "error".to_owned())
 }
 
+/// Like `type_and_expand_unseemly_program`, but shows each macro expansion step before the result.
+fn type_and_trace_expand_unseemly_program(program: &str) -> Result<String, String> {
+    let ast: Ast = grammar::parse(
+        &core_forms::outermost_form(),
+        &syn_env.with(|grammar| grammar.borrow().clone()),
+        syntax_phase_envs.with(|envs| envs.borrow().clone()),
+        program,
+    )
+    .map_err(|e| e.msg)?;
+
+    let _type = ty_env
+        .with(|tys| ty::synth_type_all(&ast, tys.borrow().clone()).map_err(type_errors_to_string))?;
+
+    let (result, steps) = crate::expand::expand_with_trace(&ast);
+    let result = result.map_err(|_| "error".to_owned())?;
+
+    let mut res = String::new();
+    for (i, step) in steps.iter().enumerate() {
+        res.push_str(&format!("[{}] {}\n", i + 1, step));
+    }
+    res.push_str(&format!("{}", result));
+    Ok(res)
+}
+
+/// Write out the parsed or expanded program, its type, or its value as an S-expression or JSON
+///  (see `serialize.rs`), for other tools to read.
+fn dump_unseemly_program(program: &str, what: &str, json: bool) -> Result<String, String> {
+    use crate::serialize::to_sexp;
+    let sexp = match what {
+        "ast" => to_sexp(&grammar::parse(
+            &core_forms::outermost_form(),
+            &syn_env.with(|grammar| grammar.borrow().clone()),
+            syntax_phase_envs.with(|envs| envs.borrow().clone()),
+            program,
+        )
+        .map_err(|e| e.msg)?),
+        "expanded" => to_sexp(&type_and_expand_unseemly_program(program)?),
+        "type" => to_sexp(&type_unseemly_program(program)?),
+        "value" => to_sexp(&eval_unseemly_program(program)?),
+        _ => return Err(format!("can't dump `{}`; try `ast`, `expanded`, `type`, or `value`", what)),
+    }?;
+    Ok(if json { sexp.to_json() } else { format!("{}", sexp) })
+}
+
 #[test]
 fn simple_end_to_end_eval() {
     assert_eq!(eval_unseemly_program("(zero? zero)"), Ok(val!(b true)));
@@ -534,117 +733,570 @@ fn end_to_end_list_tools() {
 }
 
 #[test]
-fn end_to_end_quotation_basic() {
-    assert_m!(eval_unseemly_program("'[Expr | .[ x : Int . x ]. ]'"), Ok(_));
+fn end_to_end_implicit_fold_unfold() {
+    assert_m!(
+        assign_t_var("Chain", "forall T . mu_type Chain . { +[End]+ +[Link T Chain<T> ]+ }"),
+        Ok(_)
+    );
 
-    assert_m!(eval_unseemly_program("'[Expr | (plus five five) ]'"), Ok(_));
+    // No `fold`s needed to construct...
+    assert_m!(
+        assign_variable(
+            "chain_12",
+            "+[Link one +[Link two +[End]+ : Chain<Int> ]+ : Chain<Int> ]+ : Chain<Int>"
+        ),
+        Ok(_)
+    );
+    // ...and no `unfold`s needed to take apart:
+    assert_m!(
+        assign_variable(
+            "chain_sum",
+            "(fix .[again : [-> [Chain<Int> -> Int]] .
+             .[ c : Chain<Int> .
+                 match c { +[End]+ => zero  +[Link hd tl]+ => (plus hd ((again) tl))} ]. ].)"
+        ),
+        Ok(_)
+    );
+    assert_eq!(eval_unseemly_program("(chain_sum chain_12)"), Ok(val!(i 3)));
 
-    assert_m!(eval_unseemly_program("'[Expr | '[Expr | (plus five five) ]' ]'"), Ok(_));
+    assert_m!(
+        assign_variable(
+            "chain_map",
+            "forall T S . (fix .[again : [-> [Chain<T>  [T -> S] -> Chain<S> ]] .
+            .[ c : Chain<T>   f : [T -> S] .
+                match c {
+                    +[End]+ => +[End]+ : Chain<S>
+                    +[Link hd tl]+ => +[Link (f hd) ((again) tl f)]+ : Chain<S> } ]. ].)"
+        ),
+        Ok(_)
+    );
+    assert_eq!(
+        eval_unseemly_program("(chain_sum (chain_map chain_12 .[x : Int . (plus x one)].))"),
+        Ok(val!(i 5))
+    );
 
-    //≫ .[s : Expr<Int> . '[Expr | ( ,[Expr | s], '[Expr | ,[Expr | s], ]')]' ].
+    // Recursive types are nominal; the unfolded type isn't folded just because it's expected:
+    assert_m!(
+        type_unseemly_program(
+            "(chain_sum +[Link five chain_12]+ : { +[End]+ +[Link Int Chain<Int> ]+ })"
+        ),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+    // Explicit `fold` and `unfold` still work:
+    assert_eq!(
+        eval_unseemly_program(
+            "(chain_sum fold +[Link five chain_12]+ : { +[End]+ +[Link Int Chain<Int> ]+ }
+                 : Chain<Int>)"
+        ),
+        Ok(val!(i 8))
+    );
+    assert_m!(
+        type_unseemly_program("match unfold chain_12 { +[End]+ => zero  +[Link hd tl]+ => hd }"),
+        Ok(_)
+    );
+
+    assert_m!(
+        type_unseemly_program("(chain_sum +[Link chain_12 chain_12]+ : Chain<Int>)"),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
 }
-#[test]
-fn subtyping_direction() {
-    // Let's check to make sure that "supertype" and "subtype" never got mixed up:
 
-    assert_m!(assign_variable("ident", "forall T . .[ a : T . a ]."), Ok(_));
+#[test]
+fn end_to_end_match_coverage() {
+    assert_m!(assign_t_var("IntOption", "{ +[Some Int]+ +[None]+ }"), Ok(_));
+    assert_m!(assign_variable("some_five", "+[Some five]+ : IntOption"), Ok(_));
 
-    assert_eq!(eval_unseemly_program("(ident five)"), Ok(val!(i 5)));
+    assert_m!(
+        type_unseemly_program(
+            "match **[some_five some_five]** {
+                **[+[Some x]+ +[Some y]+]** => (plus x y)
+                **[+[None]+ whatever]** => zero }"
+        ),
+        Err(ref msg),
+        msg.contains("`**[+[Some _]+ +[None]+]**` not covered")
+    );
 
-    assert_m!(eval_unseemly_program("( .[ a : [Int -> Int] . a]. ident)"), Ok(_));
+    ty::take_warnings();
+    assert_eq!(
+        eval_unseemly_program("match some_five { +[None]+ => zero  x => one  +[Some y]+ => y }"),
+        Ok(val!(i 1))
+    );
+    assert_eq!(ty::take_warnings().len(), 1);
+}
 
-    assert_m!(eval_unseemly_program("( .[ a : forall T . [T -> T] . a]. .[a : Int . a].)"), Err(_));
+#[test]
+fn end_to_end_extended_patterns() {
+    assert_m!(assign_t_var("Shape", "{ +[Circle Int]+ +[Square Int]+ +[Dot]+ }"), Ok(_));
+    assert_m!(assign_variable("sq", "+[Square ten]+ : Shape"), Ok(_));
 
-    assert_m!(eval_unseemly_program(".[ a : *[]* . a]."), Ok(_));
+    assert_eq!(
+        eval_unseemly_program(
+            "match sq { +[Circle r]+ | +[Square r]+ => r  +[Dot]+ => zero }"),
+        Ok(val!(i 10))
+    );
+    assert_eq!(
+        eval_unseemly_program("match sq { +[Square 10]+ => one  _ => zero }"),
+        Ok(val!(i 1))
+    );
+    assert_eq!(
+        eval_unseemly_program("match sq { +[Square 3]+ => one  +[Square -3]+ => two  _ => zero }"),
+        Ok(val!(i 0))
+    );
+    assert_eq!(
+        eval_unseemly_program(
+            "match sq { s @ +[Square n]+ => match s { +[Square m]+ => (plus n m)  _ => zero }
+                        _ => zero }"
+        ),
+        Ok(val!(i 20))
+    );
 
+    // Alternatives must agree on their bindings:
     assert_m!(
-        eval_unseemly_program("( .[ a : *[normal : Int extra : Int]* . a]. *[normal : one]*)"),
-        Err(_)
+        type_unseemly_program("match sq { +[Circle r]+ | +[Dot]+ => r  _ => zero }"),
+        Err(ref msg),
+        msg.contains("InconsistentBinding")
+    );
+    assert_m!(
+        type_unseemly_program("match sq { +[Circle r]+ | s => zero  _ => zero }"),
+        Err(ref msg),
+        msg.contains("InconsistentBinding")
+    );
+    // Literal patterns only match integers:
+    assert_m!(
+        type_unseemly_program("match sq { 5 => zero  _ => zero }"),
+        Err(ref msg),
+        msg.starts_with("[UnableToDestructure] expected a `Int` type")
     );
 
+    // Or-patterns count towards coverage, but literals can't cover `Int`:
+    ty::take_warnings();
     assert_m!(
-        eval_unseemly_program("( .[ a : *[normal : Int]* . a]. *[normal : one extra : five]*)"),
+        type_unseemly_program("match sq { +[Circle _]+ | +[Dot]+ => zero  +[Square _]+ => one }"),
+        Ok(_)
+    );
+    assert_m!(
+        type_unseemly_program("match sq { +[Circle 0]+ | +[Dot]+ => zero  +[Square _]+ => one }"),
+        Err(ref msg),
+        msg.contains("`+[Circle _]+` not covered")
+    );
+    assert_m!(
+        type_unseemly_program("match sq { +[Circle 0]+ => zero  +[Circle 0]+ | _ => one }"),
+        Ok(_)
+    );
+    assert_eq!(ty::take_warnings().len(), 0);
+    assert_m!(
+        type_unseemly_program("match sq { _ => zero  +[Dot]+ | +[Circle _]+ => one }"),
         Ok(_)
     );
+    assert_eq!(ty::take_warnings().len(), 1);
 }
 
 #[test]
-fn end_to_end_quotation_advanced() {
+fn end_to_end_match_guards() {
+    assert_m!(assign_t_var("IntOption", "{ +[Some Int]+ +[None]+ }"), Ok(_));
+    assert_m!(assign_variable("some_zero", "+[Some zero]+ : IntOption"), Ok(_));
+    assert_m!(assign_variable("some_five", "+[Some five]+ : IntOption"), Ok(_));
+
+    let classify = "match opt {
+            +[Some x]+ if (zero? x) => one
+            +[Some x]+ => two
+            +[None]+ => zero }";
     assert_eq!(
-        eval_unseemly_program(
-            "(.[five_e : Expr < Int >.
-                '[Expr | (plus five ,[five_e],) ]' ].
-                '[Expr | five]')"
+        eval_unseemly_program(&format!("(.[opt : IntOption . {}]. some_zero)", classify)),
+        Ok(val!(i 1))
+    );
+    // A failing guard falls through to the next arm:
+    assert_eq!(
+        eval_unseemly_program(&format!("(.[opt : IntOption . {}]. some_five)", classify)),
+        Ok(val!(i 2))
+    );
+
+    // Guards must be `Bool`s:
+    assert_m!(
+        type_unseemly_program("match some_five { +[Some x]+ if x => one  _ => two }"),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+    // ...and don't count towards exhaustiveness:
+    assert_m!(
+        type_unseemly_program(
+            "match some_five { +[Some x]+ if (zero? x) => one  +[None]+ => two }"
         ),
-        eval_unseemly_program("'[Expr | (plus five five) ]'")
+        Err(ref msg),
+        msg.contains("`+[Some _]+` not covered")
     );
+}
 
-    // Pass the wrong type (not really a test of quotation)
+#[test]
+fn end_to_end_type_error_messages() {
+    assert_m!(
+        type_unseemly_program("(plus fiv five)"),
+        Err(ref msg),
+        msg.contains("`fiv` is not defined; did you mean `five`?")
+    );
+    // Internal names aren't suggested (`match` arms bind one):
+    assert_m!(
+        type_unseemly_program("match five { num => x }"),
+        Err(ref msg),
+        msg.contains("`x` is not defined at")
+    );
+
+    assert_m!(assign_t_var("Shape", "{ +[Circle Int]+ +[Square Int]+ }"), Ok(_));
+    assert_m!(
+        type_unseemly_program("+[Sqare five]+ : Shape"),
+        Err(ref msg),
+        msg.contains("did you mean `Square`?")
+    );
     assert_m!(
         type_unseemly_program(
-            "(.[five_e : Expr<Int> .
-                '[Expr | (plus five ,[five_e],) ]' ].
-                '[Expr | true]')"
+            ".[s : *[width: Int  height: Int]* . match s { *[width: w  heigth: h]* => w } ]."
         ),
-        Err(_)
+        Err(ref msg),
+        msg.contains("did you mean `height`?")
     );
 
-    // Interpolate the wrong type
+    // Only the part of the type that differs is shown:
     assert_m!(
         type_unseemly_program(
-            "(.[five_e : Expr<Bool> .
-                '[Expr | (plus five ,[five_e],) ]' ].
-                '[Expr | true]')"
+            "+[Some .[x : Int . (equal? x zero)].]+
+                : { +[Some [Int -> Int]]+ +[None]+ }"
         ),
-        Err(_)
+        Err(ref msg),
+        msg.contains("expected:\n  `Int`") && msg.contains("(at fn.ret of the expected type)")
     );
 
-    // Interpolate the wrong type (no application needed to find the error)
     assert_m!(
-        type_unseemly_program(".[five_e : Expr<Bool> . '[Expr | (plus five ,[five_e],) ]' ]."),
-        Err(_)
+        type_unseemly_program("'[Pat | +[Some x]+ ]'"),
+        Err(ref msg),
+        msg.contains("`+[ Some x ]+` is negative syntax")
     );
 
+    // Healing splices in a type with no matching repeat on the other side is just a mismatch:
     assert_m!(
-        eval_unseemly_program(
-            "forall T . .[type : Type<T>   rhs : Expr<T>
-                . '[Expr | (.[x : ,[Type<T> | type], . eight].  ,[rhs], )]' ]."
+        type_unseemly_program("forall T . .[ g : [T -> Bool] . (g true) ]."),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+}
+
+#[test]
+fn end_to_end_multiple_type_errors() {
+    // `match` typechecks its arms before its scrutinee, but errors come out in source order:
+    assert_m!(
+        type_unseemly_program("match fiv { _ => (plus true (zero? heigth)) }"),
+        Err(ref msg),
+        msg.lines().filter(|l| l.starts_with('[')).collect::<Vec<_>>()
+            == vec![
+                "[UnboundName] `fiv` is not defined; did you mean `five`? at match fiv \
+                 { _ => ( plus true ( zero? heigth ) ) }",
+                "[Mismatch] got:",
+                "[UnboundName] `heigth` is not defined at ( zero? heigth )"
+            ]
+    );
+
+    // An ill-typed term doesn't cause more errors where it's used:
+    assert_m!(
+        type_unseemly_program("(plus (times fiv true) (plus one fiv))"),
+        Err(ref msg),
+        msg.matches("[UnboundName]").count() == 2
+            && msg.matches("[Mismatch]").count() == 1
+            && !msg.contains("<error>")
+    );
+    // ...even when the ill-typed term is a function's return value:
+    assert_m!(
+        type_unseemly_program("(.[ g : [Int -> Bool] . (g true) ]. .[x : Int . true].)"),
+        Err(ref msg),
+        msg.lines().filter(|l| l.starts_with('[')).count() == 1
+            && msg.starts_with("[Mismatch]")
+    );
+
+    // A quotation pattern that can't be typechecked doesn't quietly bind nothing:
+    let first_arg = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'first_arg'
+                e := ( ,{ Expr<Int> }, )
+            ] }' first_arg ->
+            .{ match e { '[Expr | (plus ,[a], ,[b], ) ]' => a  _ => e } }. ;
+        in first_arg (plus one two)";
+    assert_m!(
+        type_unseemly_program(first_arg),
+        Err(ref msg),
+        msg.starts_with("[AnnotationRequired] `,,(1)∘a∘,,` is negative syntax")
+            && msg.lines().filter(|l| l.starts_with('[')).count() == 1
+    );
+}
+
+#[test]
+fn end_to_end_typed_holes() {
+    assert_m!(
+        type_unseemly_program(
+            ".[ a : Int  b : Bool .
+                (plus ?left match b { +[True]+ => ?right  +[False]+ => a }) ]."
         ),
-        Ok(_)
+        Err(ref msg),
+        msg.contains("[Hole] `?left` needs to have the type `Int`\n   in scope:\n     a : Int\n     b :")
+            && msg.contains("[Hole] `?right` needs to have the type `Int`")
+            && msg.find("?left") < msg.find("?right")
     );
 
-    assert_m!(eval_unseemly_program("'[Pat<Nat> | x]'"), Ok(_));
+    assert_m!(
+        type_unseemly_program("?anything"),
+        Err(ref msg),
+        msg.starts_with("[Hole] `?anything` can have any type")
+    );
 
-    // Actually import a pattern of quoted syntax:
+    // Even when we're not collecting every error, holes know what they need to be:
+    let hole_ast = grammar::parse(
+        &core_forms::outermost_form(),
+        &core_forms::get_core_forms(),
+        runtime::core_values::get_core_envs(),
+        "(plus ?x one)",
+    )
+    .unwrap();
+    assert_m!(
+        ty::synth_type(&hole_ast, runtime::core_values::core_types()),
+        Err(ref err),
+        format!("{}", err).starts_with("[Hole] `?x` needs to have the type `Int`")
+    );
+
+    // Evaluating a hole is an error, not a crash:
     assert_eq!(
-        eval_unseemly_program(
-            "match '[Expr | (plus one two) ]' {
-                 '[Expr<Int> | (plus ,[Expr<Int> | e], two) ]' => e }"
-        ),
-        Ok(val!(ast (vr "one")))
+        eval_unseemly_program_without_typechecking("(plus ?x one)"),
+        Err("evaluated the hole `?x`, which needs to be filled in".to_owned())
     );
+}
 
-    // In order to have "traditional", non-type-annotated `let`, we want to ... reify T, I guess?
-    // But the whole language has parametricity kinda baked in, and that seems to make it hard?
-    // I think the solution is to build `let` into the language;
-    //  if a macro wants to have non-annotated binding, it's probably expandable to `let` anyways.
+#[test]
+fn end_to_end_mutually_recursive_types() {
+    let defs = "let_type rec
+          Ex = { +[Lit Int]+ +[Block St]+ }
+          St = { +[Eval Ex]+ +[Seq St St]+ }
+        in let_type
+          ExUF = { +[Lit Int]+ +[Block St]+ }
+          StUF = { +[Eval Ex]+ +[Seq St St]+ }
+        in";
+    let nested = "fold +[Block fold +[Eval fold +[Lit five]+ : ExUF : Ex]+ : StUF : St]+ : ExUF : Ex";
+    let lit_of = ".[e : Ex . match unfold e {
+            +[Lit n]+ => n
+            +[Block s]+ => match unfold s { +[Eval inner]+ => one  +[Seq a b]+ => two } } ].";
+
+    assert_eq!(
+        eval_unseemly_program(&format!("{} ({} {})", defs, lit_of, nested)),
+        Ok(val!(i 1))
+    );
+    // `Ex` and `St` are different types:
     assert_m!(
-        assign_variable(
-            "let",
-            "forall T S . .[binder : Pat<T>
-                        type : Type<T>
-                        rhs : Expr<T>
-                        body : Expr<S> .
-             '[ Expr | (.[x : ,[type],
-                     . match x { ,[Pat<T> | binder], => ,[body], } ].
-                 ,[rhs],)]' ]."
-        ),
+        type_unseemly_program(&format!("{} ({} fold +[Eval {}]+ : StUF : St)", defs, lit_of, nested)),
+        Err(_)
+    );
+    // A structurally identical group defined elsewhere is interchangeable:
+    assert_m!(
+        type_unseemly_program(&format!(
+            "{} let_type rec
+                St2 = {{ +[Eval Ex2]+ +[Seq St2 St2]+ }}
+                Ex2 = {{ +[Lit Int]+ +[Block St2]+ }}
+             in (.[e : Ex2 . e]. {})",
+            defs, nested
+        )),
         Ok(_)
     );
+}
 
-    without_freshening! {
-        assert_eq!(
-            eval_unseemly_program(
+#[test]
+fn end_to_end_existential_types() {
+    assert_m!(
+        assign_t_var("Counter", "exists T . *[init : T  next : [T -> T]  read : [T -> Int]]*"),
+        Ok(_)
+    );
+
+    assert_m!(
+        assign_variable(
+            "int_counter",
+            "pack *[init : zero  next : .[x : Int . (plus x one)].  read : .[x : Int . x]. ]*
+                : Counter"
+        ),
+        Ok(_)
+    );
+
+    assert_eq!(
+        eval_unseemly_program(
+            "unpack int_counter as C . c in
+                match c { *[init : i  next : nx  read : r]* => (r (nx (nx i))) }"
+        ),
+        Ok(val!(i 2))
+    );
+
+    // The representation type is hidden:
+    assert_m!(
+        type_unseemly_program(
+            "unpack int_counter as C . c in
+                match c { *[init : i  next : nx  read : r]* => (plus i one) }"
+        ),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]") && msg.contains("expected:\n  `C`")
+    );
+
+    // ...and can't escape:
+    assert_m!(
+        type_unseemly_program(
+            "unpack int_counter as C . c in match c { *[init : i  next : nx  read : r]* => i }"
+        ),
+        Err(ref msg),
+        msg.starts_with("[TypeEscapes]")
+    );
+
+    // Packing checks that some representation type works:
+    assert_m!(
+        type_unseemly_program(
+            "pack *[init : zero  next : .[x : Int . (zero? x)].  read : .[x : Int . x]. ]*
+                : Counter"
+        ),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+
+    // One existential type is a subtype of another only if the bodies match:
+    assert_m!(
+        type_unseemly_program(
+            "(.[ c : exists S . *[init : S  next : [S -> S]  read : [S -> Int]]* . c ]. int_counter)"
+        ),
+        Ok(_)
+    );
+    assert_m!(
+        type_unseemly_program(
+            "(.[ p : exists T . *[a : Int]* .
+                   unpack p as C . c in match c { *[a : x]* => (plus x one) } ].
+                pack *[a : true]* : exists T . *[a : T]*)"
+        ),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+}
+
+#[test]
+fn end_to_end_quotation_basic() {
+    assert_m!(eval_unseemly_program("'[Expr | .[ x : Int . x ]. ]'"), Ok(_));
+
+    assert_m!(eval_unseemly_program("'[Expr | (plus five five) ]'"), Ok(_));
+
+    assert_m!(eval_unseemly_program("'[Expr | '[Expr | (plus five five) ]' ]'"), Ok(_));
+
+    //≫ .[s : Expr<Int> . '[Expr | ( ,[Expr | s], '[Expr | ,[Expr | s], ]')]' ].
+}
+#[test]
+fn subtyping_direction() {
+    // Let's check to make sure that "supertype" and "subtype" never got mixed up:
+
+    assert_m!(assign_variable("ident", "forall T . .[ a : T . a ]."), Ok(_));
+
+    assert_eq!(eval_unseemly_program("(ident five)"), Ok(val!(i 5)));
+
+    assert_m!(eval_unseemly_program("( .[ a : [Int -> Int] . a]. ident)"), Ok(_));
+
+    assert_m!(eval_unseemly_program("( .[ a : forall T . [T -> T] . a]. .[a : Int . a].)"), Err(_));
+
+    assert_m!(eval_unseemly_program(".[ a : *[]* . a]."), Ok(_));
+
+    assert_m!(
+        eval_unseemly_program("( .[ a : *[normal : Int extra : Int]* . a]. *[normal : one]*)"),
+        Err(_)
+    );
+
+    assert_m!(
+        eval_unseemly_program("( .[ a : *[normal : Int]* . a]. *[normal : one extra : five]*)"),
+        Ok(_)
+    );
+}
+
+#[test]
+fn end_to_end_quotation_advanced() {
+    assert_eq!(
+        eval_unseemly_program(
+            "(.[five_e : Expr < Int >.
+                '[Expr | (plus five ,[five_e],) ]' ].
+                '[Expr | five]')"
+        ),
+        eval_unseemly_program("'[Expr | (plus five five) ]'")
+    );
+
+    // Pass the wrong type (not really a test of quotation)
+    assert_m!(
+        type_unseemly_program(
+            "(.[five_e : Expr<Int> .
+                '[Expr | (plus five ,[five_e],) ]' ].
+                '[Expr | true]')"
+        ),
+        Err(_)
+    );
+
+    // Interpolate the wrong type
+    assert_m!(
+        type_unseemly_program(
+            "(.[five_e : Expr<Bool> .
+                '[Expr | (plus five ,[five_e],) ]' ].
+                '[Expr | true]')"
+        ),
+        Err(_)
+    );
+
+    // Interpolate the wrong type (no application needed to find the error)
+    assert_m!(
+        type_unseemly_program(".[five_e : Expr<Bool> . '[Expr | (plus five ,[five_e],) ]' ]."),
+        Err(_)
+    );
+
+    assert_m!(
+        eval_unseemly_program(
+            "forall T . .[type : Type<T>   rhs : Expr<T>
+                . '[Expr | (.[x : ,[Type<T> | type], . eight].  ,[rhs], )]' ]."
+        ),
+        Ok(_)
+    );
+
+    assert_m!(eval_unseemly_program("'[Pat<Nat> | x]'"), Ok(_));
+
+    // Actually import a pattern of quoted syntax:
+    assert_eq!(
+        eval_unseemly_program(
+            "match '[Expr | (plus one two) ]' {
+                 '[Expr<Int> | (plus ,[Expr<Int> | e], two) ]' => e
+                 _ => '[Expr | zero]' }"
+        ),
+        Ok(val!(ast (vr "one")))
+    );
+    // ...which might not match:
+    assert_m!(
+        type_unseemly_program(
+            "match '[Expr | (plus one two) ]' {
+                 '[Expr<Int> | (plus ,[Expr<Int> | e], two) ]' => e }"
+        ),
+        Err(ref msg),
+        msg.starts_with("[NonExhaustiveMatch]") && msg.contains("`_` not covered")
+    );
+
+    // In order to have "traditional", non-type-annotated `let`, we want to ... reify T, I guess?
+    // But the whole language has parametricity kinda baked in, and that seems to make it hard?
+    // I think the solution is to build `let` into the language;
+    //  if a macro wants to have non-annotated binding, it's probably expandable to `let` anyways.
+    assert_m!(
+        assign_variable(
+            "let",
+            "forall T S . .[binder : Pat<T>
+                        type : Type<T>
+                        rhs : Expr<T>
+                        body : Expr<S> .
+             '[ Expr | (.[x : ,[type],
+                     . match x { ,[Pat<T> | binder], => ,[body], } ].
+                 ,[rhs],)]' ]."
+        ),
+        Ok(_)
+    );
+
+    without_freshening! {
+        assert_eq!(
+            eval_unseemly_program(
                 "(let  '[Pat<Int> | y]'
                        '[Type<Int> | Int]'
                        '[Expr<Int> | eight]'
@@ -676,6 +1328,933 @@ fn end_to_end_quotation_advanced() {
     //  }
 }
 
+#[test]
+fn end_to_end_atom_interpolation() {
+    // An `Atom<T>` can be interpolated where a binder goes:
+    assert_m!(
+        type_unseemly_program(
+            ".[name : Atom<Int>  body : Expr<Bool> .
+                '[Expr | .[ ,[name], : Int . ,[body], ]. ]' ]."
+        ),
+        Ok(_)
+    );
+    // ...but it has to be an atom for the right type:
+    assert_m!(
+        type_unseemly_program(
+            ".[name : Atom<Bool>  body : Expr<Bool> .
+                '[Expr | .[ ,[name], : Int . ,[body], ]. ]' ]."
+        ),
+        Err(ref msg),
+        msg.starts_with("[Mismatch]")
+    );
+    // Binders in quoted `Pat` and `Type` syntax work the same way:
+    assert_m!(
+        type_unseemly_program(
+            ".[name : Atom<Int>  body : Expr<Bool> .
+                '[Expr | match five { ,[name], => ,[body], } ]' ]."
+        ),
+        Ok(_)
+    );
+    assert_m!(
+        type_unseemly_program(
+            ".[name : Atom<Int>  body : Type<Bool> .
+                '[Type | forall ,[name], . ,[body], ]' ]."
+        ),
+        Ok(_)
+    );
+
+    let apply_fn_prog = "extend_syntax
+            Expr ::=also forall A T . '{
+                [
+                    lit ,{ DefaultToken }, = 'with'
+                    arg := ( ,{ Atom<A> }, )
+                    lit ,{ DefaultToken }, = ':'
+                    arg_type := ( ,{ Type<A> }, )
+                    lit ,{ DefaultToken }, = '='
+                    value := ( ,{ Expr<A> }, )
+                    lit ,{ DefaultToken }, = 'do'
+                    body := ( ,{ Expr<T> }, <-- arg : arg_type )
+                ]
+            }' with_macro -> .{
+                '[Expr | (.[ ,[arg], : ,[arg_type],  tmp : Int . ,[body], ]. ,[value], one) ]'
+            }. ;
+        in
+        with tmp : Int = eight do (plus tmp tmp)";
+    // The user's `tmp` is `eight`, not the macro's `tmp` (which is `one`):
+    assert_eq!(eval_unseemly_program(apply_fn_prog), Ok(val!(i 16)));
+
+    let bind_prog = "extend_syntax
+            Expr ::=also forall A T . '{
+                [
+                    lit ,{ DefaultToken }, = 'bind'
+                    arg := ( ,{ Atom<A> }, )
+                    lit ,{ DefaultToken }, = '='
+                    value := ( ,{ Expr<A> }, )
+                    lit ,{ DefaultToken }, = 'in'
+                    body := ( ,{ Expr<T> }, <-- arg : value )
+                ]
+            }' bind_macro -> .{
+                '[Expr | match ,[value], { ,[arg], => ,[body], } ]'
+            }. ;
+        in
+        bind x = eight in (plus x one)";
+    assert_eq!(eval_unseemly_program(bind_prog), Ok(val!(i 9)));
+}
+
+#[test]
+fn end_to_end_variadic_macro() {
+    // One macro handles any number of arguments, each with its own type:
+    let letfn_macro = "extend_syntax
+            Expr ::=also forall I O T . '{ [
+                lit ,{ DefaultToken }, = 'letfn'
+                lit ,{ DefaultToken }, = '('
+                fn_name := ( ,{ Pat< [ :::[ I >> I ]::: -> O ] > }, )
+                [
+                    arg_name := ( ,{ Atom<I> }, )
+                    lit ,{ DefaultToken }, = ':'
+                    arg_type := ( ,{ Type<I> }, )
+                ] *
+                lit ,{ DefaultToken }, = ')'
+                lit ,{ DefaultToken }, = '->'
+                ret_type := ( ,{ Type<O> }, )
+                lit ,{ DefaultToken }, = '='
+                fn_body := ( ,{ Expr<O> }, <-- ...[ arg_name : arg_type ]... )
+                lit ,{ DefaultToken }, = ';'
+                lit ,{ DefaultToken }, = 'in'
+                body := ( ,{ Expr<T> }, <-- fn_name = [ :::[ arg_type >> arg_type ]::: -> ret_type ] )
+            ] }' letfn_macro -> .{
+                '[Expr | match (fix
+                    .[ again: [ -> [ ...[,arg_type, >> ,[arg_type], ]... -> ,[ret_type], ] ] .
+                        .[ ...[,arg_name, >> ,[arg_name], ]... : ...[,arg_type, >> ,[arg_type], ]... .
+                            ,[fn_body], ].
+                ].) { ,[fn_name], => ,[body], } ]'
+            }. ;
+        in ";
+
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} letfn (add3 a: Int  b: Int  c: Int) -> Int = (plus a (plus b c)) ;
+                in (add3 one two three)",
+            letfn_macro
+        )),
+        Ok(val!(i 6))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} letfn (first x: Int  y: Bool) -> Int = x ; in (first seven true)",
+            letfn_macro
+        )),
+        Ok(val!(i 7))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} letfn (double x: Int) -> Int = (plus x x) ; in (double four)",
+            letfn_macro
+        )),
+        Ok(val!(i 8))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} letfn (nullary) -> Int = five ; in (nullary)",
+            letfn_macro
+        )),
+        Ok(val!(i 5))
+    );
+
+    // The argument types are checked, in the body and at the call:
+    assert_m!(
+        type_unseemly_program(&format!(
+            "{} letfn (first x: Int  y: Bool) -> Int = y ; in (first seven true)",
+            letfn_macro
+        )),
+        Err(_)
+    );
+    assert_m!(
+        type_unseemly_program(&format!(
+            "{} letfn (first x: Int  y: Bool) -> Int = x ; in (first seven eight)",
+            letfn_macro
+        )),
+        Err(_)
+    );
+}
+
+#[test]
+fn end_to_end_macro_names() {
+    let dbl_macro = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'twice'
+                e := ( ,{ Expr<Int> }, )
+            ] }' dbl -> .{ '[Expr | (plus ,[e], ,[e],) ]' }. ;
+        in ";
+
+    assert_eq!(eval_unseemly_program(&format!("{} twice four", dbl_macro)), Ok(val!(i 8)));
+
+    // A macro with the same name shadows the old one (syntax and all):
+    let shadowing = format!(
+        "{} extend_syntax
+            Expr ::=also forall T . '{{ [
+                lit ,{{ DefaultToken }}, = 'thrice'
+                e := ( ,{{ Expr<Bool> }}, )
+            ] }}' dbl -> .{{ '[Expr | ,[e], ]' }}. ;
+        in ",
+        dbl_macro
+    );
+    assert_eq!(eval_unseemly_program(&format!("{} thrice true", shadowing)), Ok(val!(b true)));
+    assert_m!(eval_unseemly_program(&format!("{} twice four", shadowing)), Err(_));
+
+    // ...but two macros with the same name in the same place is an error:
+    assert_m!(
+        type_unseemly_program(
+            "extend_syntax
+                Expr ::=also forall T . '{ [
+                    lit ,{ DefaultToken }, = 'one_m'  e := ( ,{ Expr<Int> }, ) ] }'
+                    m -> .{ '[Expr | ,[e], ]' }. ;
+                Expr ::=also forall T . '{ [
+                    lit ,{ DefaultToken }, = 'two_m'  e := ( ,{ Expr<Int> }, ) ] }'
+                    m -> .{ '[Expr | ,[e], ]' }. ;
+            in one_m five"
+        ),
+        Err(ref msg),
+        msg.contains("MacroNameCollision")
+    );
+
+    // Macros can be removed...
+    // (so `twice` is just a variable name, and `four` is left over)
+    assert_m!(
+        eval_unseemly_program(&format!(
+            "{} extend_syntax without dbl ; in twice four",
+            dbl_macro
+        )),
+        Err(ref msg),
+        msg.starts_with("Could not parse past") && msg.contains("twice •four")
+    );
+    // ...and re-used by name:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} extend_syntax Expr ::= alt[ macro dbl  ,{{ DefaultReference }}, ]alt ;
+             in twice four",
+            dbl_macro
+        )),
+        Ok(val!(i 8))
+    );
+    assert_m!(
+        type_unseemly_program(&format!("{} extend_syntax without dbll ; in four", dbl_macro)),
+        Err(ref msg),
+        msg.contains("did you mean `dbl`?")
+    );
+
+    // Errors in an invocation say which macro it was:
+    assert_m!(
+        type_unseemly_program(&format!("{} twice true", dbl_macro)),
+        Err(ref msg),
+        msg.contains("[InMacro] in this invocation of `dbl`")
+    );
+}
+
+#[test]
+fn end_to_end_capture() {
+    // An anaphoric macro: `it` is bound in `body`, even though the macro user never wrote a binder.
+    let with_it = "extend_syntax
+            Expr ::=also forall T S . '{ [
+                lit ,{ DefaultToken }, = 'with_it'
+                value := ( ,{ Expr<S> }, )
+                it := ( capture ,{ Pat<S> }, = 'it' )
+                lit ,{ DefaultToken }, = 'do'
+                body := ( ,{ Expr<T> }, <-- it = value )
+            ] }' with_it -> .{ '[Expr | match ,[value], { ,[it], => ,[body], } ]' }. ;
+        in ";
+    assert_eq!(
+        eval_unseemly_program(&format!("{} with_it (plus one two) do (times it it)", with_it)),
+        Ok(val!(i 9))
+    );
+    // It's still typechecked:
+    assert_m!(
+        type_unseemly_program(&format!("{} with_it (plus one two) do (times it true)", with_it)),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    // ...and it only exists inside `body`:
+    assert_m!(
+        type_unseemly_program(&format!("{} *[ a : with_it one do it  b : it ]*", with_it)),
+        Err(ref msg),
+        msg.contains("[UnboundName] `it") // (`it` might be freshened)
+    );
+    // It shadows any `it` outside:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "(.[it : Bool . {} with_it five do (plus it one) ]. true)",
+            with_it
+        )),
+        Ok(val!(i 6))
+    );
+
+    // A captured `Atom` can be used as a function parameter:
+    let let_it_be = "extend_syntax
+            Expr ::=also forall T S . '{ [
+                lit ,{ DefaultToken }, = 'let_it_be'
+                value := ( ,{ Expr<S> }, )
+                lit ,{ DefaultToken }, = ':'
+                ty := ( ,{ Type<S> }, )
+                it := ( capture ,{ Atom<S> }, = 'it' )
+                lit ,{ DefaultToken }, = 'in'
+                body := ( ,{ Expr<T> }, <-- it : ty )
+            ] }' let_it_be -> .{ '[Expr | (.[ ,[it], : ,[ty], . ,[body], ]. ,[value],) ]' }. ;
+        in ";
+    assert_eq!(
+        eval_unseemly_program(&format!("{} let_it_be seven : Int in (plus it it)", let_it_be)),
+        Ok(val!(i 14))
+    );
+
+    // Only binders can be captured:
+    assert_m!(
+        type_unseemly_program(
+            "extend_syntax
+                Expr ::=also forall T S . '{ [
+                    lit ,{ DefaultToken }, = 'bad_capture'
+                    it := ( capture ,{ Expr<S> }, = 'it' )
+                ] }' bad_capture -> .{ '[Expr | ,[it], ]' }. ;
+            in five"
+        ),
+        Err(ref msg),
+        msg.contains("[NonBinderCapture]")
+    );
+}
+
+#[test]
+fn end_to_end_expansion_trace() {
+    let trace = type_and_trace_expand_unseemly_program(
+        "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'twice'
+                e := ( ,{ Expr<Int> }, )
+            ] }' dbl -> .{ '[Expr | (plus ,[e], ,[e],) ]' }. ;
+        in twice twice four",
+    )
+    .unwrap();
+
+    // The outer invocation goes first; its argument is still unexpanded:
+    assert!(trace.contains("[1] dbl at line 6: twice twice four\n    e := twice four\n"));
+    assert!(trace.contains("  ⇒ ( plus twice four twice four )\n"));
+    // ...then each copy of the inner one:
+    assert!(trace.contains("[2] dbl at line 6: twice four\n    e := four\n  ⇒ ( plus four four )\n"));
+    assert!(trace.contains("[3] dbl at line 6: twice four"));
+    assert!(!trace.contains("[4]"));
+
+    // A macro's syntax is shown with the grammar it was defined in:
+    let trace = type_and_trace_expand_unseemly_program(
+        "extend_syntax
+            Twice ::= lit ,{ DefaultToken }, = 'twice' ;
+            Expr ::=also forall T . '{ [
+                ,{ Twice },
+                e := ( ,{ Expr<Int> }, )
+            ] }' dbl -> .{ '[Expr | (plus ,[e], ,[e],) ]' }. ;
+        in twice four",
+    )
+    .unwrap();
+    assert!(trace.contains("[1] dbl at line 7: twice four\n"));
+
+    // No macros, no steps:
+    assert_eq!(
+        type_and_trace_expand_unseemly_program("(plus one one)"),
+        Ok("( plus one one )".to_owned())
+    );
+}
+
+#[test]
+fn end_to_end_dump() {
+    use crate::serialize::{from_sexp, Sexp};
+
+    assert_eq!(dump_unseemly_program("(plus one two)", "value", false), Ok("(int 3)".to_owned()));
+    assert_eq!(
+        dump_unseemly_program("(plus one two)", "type", true),
+        Ok(r#"["node", ["form", {"str": "Type"}, {"str": "Int"}], ["mbe", [], []], "nothing"]"#
+            .to_owned())
+    );
+
+    // What's dumped can be read back:
+    let program =
+        "match +[Some eight]+ : { +[Some Int]+ +[None]+ } { +[Some x]+ => x  +[None]+ => one }";
+    let ast: Ast =
+        from_sexp(&Sexp::parse(&dump_unseemly_program(program, "ast", false).unwrap()).unwrap())
+            .unwrap();
+    assert_eq!(runtime::eval::eval(&ast, runtime::core_values::core_values()), Ok(val!(i 8)));
+    let expanded: Ast = from_sexp(
+        &Sexp::parse_json(&dump_unseemly_program(program, "expanded", true).unwrap()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(runtime::eval::eval(&expanded, runtime::core_values::core_values()), Ok(val!(i 8)));
+
+    assert_m!(
+        dump_unseemly_program("five", "tokens", false),
+        Err(ref msg),
+        msg.contains("try `ast`")
+    );
+    assert_m!(
+        dump_unseemly_program("plus", "value", false),
+        Err(ref msg),
+        msg.contains("built-in")
+    );
+}
+
+#[test]
+fn end_to_end_expansion_origins() {
+    // `:e` doesn't typecheck, so this non-exhaustive `match` gets to fail at runtime:
+    let program = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'assert_zero'
+                e := ( ,{ Expr<Int> }, )
+            ] }' assert_zero ->
+            .{ '[Expr |
+                match (zero? ,[e],) { +[True]+ => ,[e], } ]' }. ;
+        in
+        assert_zero five";
+    assert_eq!(
+        eval_unseemly_program_without_typechecking(program),
+        Err("no arm of `match` matched `+[False]+`
+    in expansion of `assert_zero` at line 9 (template at line 6)"
+            .to_owned())
+    );
+
+    // Hand-written code has no such chain:
+    assert_eq!(
+        eval_unseemly_program_without_typechecking("match (zero? five) { +[True]+ => one }"),
+        Err("no arm of `match` matched `+[False]+`".to_owned())
+    );
+
+    // Syntax quoted inside a macro is just syntax, which quotation patterns can take apart:
+    let program = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'second_summand'
+                e := ( ,{ Expr<Int> }, )
+            ] }' second_summand ->
+            .{ match '[Expr | (plus one ,[e],) ]' {
+                   '[Expr | (plus ,[a], ,[b],) ]' => b } }. ;
+        in
+        second_summand five";
+    assert_eq!(eval_unseemly_program_without_typechecking(program), Ok(val!(i 5)));
+}
+
+#[test]
+fn end_to_end_macro_defining_macros() {
+    let define_operator = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'define_operator'
+                f := ( ,{ Expr< [ Int Int -> Int ] > }, )
+                lit ,{ DefaultToken }, = 'to'
+                a := ( ,{ Expr<Int> }, )
+                b := ( ,{ Expr<Int> }, )
+            ] }' define_operator -> .{
+                '[Expr |
+                    extend_syntax
+                        Expr ::=also forall S . '{ [
+                            lhs := ( ,{ Expr<Int> }, )
+                            lit ,{ DefaultToken }, = '<>'
+                            rhs := ( ,{ Expr<Int> }, )
+                        ] }' infix_op -> .{ '[Expr | (,,[f],, ,[lhs], ,[rhs],) ]' }. ;
+                    in ,[a], <> ,[b], ]'
+            }. ;
+        in ";
+    assert_eq!(
+        eval_unseemly_program(&format!("{} define_operator times to three four", define_operator)),
+        Ok(val!(i 12))
+    );
+    // `,,[f],,` is typechecked against the outer macro's arguments:
+    assert_m!(
+        type_unseemly_program(&format!("{} define_operator zero? to three four", define_operator)),
+        Err(ref msg),
+        msg.contains("[InMacro] in this invocation of `define_operator`")
+    );
+    // Two-comma unquotation only makes sense two quotations deep:
+    assert_m!(eval_unseemly_program("'[Expr | (plus ,,[one],, one) ]'"), Err(_));
+
+    // The generated macro binds names hygienically;
+    //  the outer `x` that `k` refers to is not the `x` bound by `let`:
+    let scaled = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'scaled'
+                k := ( ,{ Expr<Int> }, )
+                lit ,{ DefaultToken }, = 'do'
+                a := ( ,{ Expr<Int> }, )
+            ] }' scaled -> .{
+                '[Expr |
+                    extend_syntax
+                        Expr ::=also forall S . '{ [
+                            lit ,{ DefaultToken }, = 'let'
+                            pat := ( ,{ Pat<S> }, )
+                            lit ,{ DefaultToken }, = '='
+                            value := ( ,{ Expr<S> }, )
+                            lit ,{ DefaultToken }, = 'in'
+                            body := ( ,{ Expr<Int> }, <-- pat = value )
+                        ] }' let_scaled -> .{
+                            '[Expr | match ,[value], { ,[pat], => (times ,,[k],, ,[body],) } ]'
+                        }. ;
+                    in let x = ,[a], in (plus x one) ]'
+            }. ;
+        in ";
+    assert_eq!(
+        eval_unseemly_program(&format!("(.[x : Int . {} scaled x do five ]. three)", scaled)),
+        Ok(val!(i 18))
+    );
+}
+
+#[test]
+fn end_to_end_type_and_pattern_macros() {
+    let maybe = "extend_syntax
+            Type ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'Maybe'
+                t := ( ,{ Type<T> }, )
+            ] }' maybe -> .{ '[Type | { +[Some ,[t], ]+ +[None]+ } ]' }. ;
+            Pat ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'just'
+                p := ( ,{ Pat<T> }, )
+            ] }' just -> .{ '[Pat<{ +[Some T]+ +[None]+ } > | +[Some ,[p], ]+ ]' }. => p ;
+        in ";
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} match +[Some eight]+ : Maybe Int {{ just x => (plus x one)  _ => zero }}",
+            maybe
+        )),
+        Ok(val!(i 9))
+    );
+    // A `Type` macro is just the type it expands to, wherever types go:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} (.[x : Maybe Maybe Int . match x {{ just just y => y  _ => zero }} ].
+                 +[Some +[Some eight]+ : Maybe Int]+ : Maybe Maybe Int)",
+            maybe
+        )),
+        Ok(val!(i 8))
+    );
+    assert_m!(
+        type_unseemly_program(&format!("{} +[Some true]+ : Maybe Int", maybe)),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    // Names bound by a `Pat` macro have the right types:
+    assert_m!(
+        type_unseemly_program(&format!(
+            "{} match +[Some eight]+ : Maybe Int {{ just x => (zero? x)  _ => true }}",
+            maybe
+        )),
+        Ok(_)
+    );
+    assert_m!(
+        type_unseemly_program(&format!(
+            "{} match +[Some eight]+ : Maybe Int {{ just x => x  _ => true }}",
+            maybe
+        )),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    // ...and the pattern has to match the scrutinee:
+    assert_m!(
+        type_unseemly_program(&format!("{} match five {{ just x => x  _ => zero }}", maybe)),
+        Err(ref msg),
+        msg.contains("[InMacro] in this invocation of `just`")
+    );
+
+    // A list pattern:
+    assert_eq!(
+        eval_unseemly_program(
+            "let_type List = forall T . mu_type List . { +[Nil]+ +[Cons T List<T>]+ }
+            in extend_syntax
+                Pat ::=also forall T . '{ [
+                    lit ,{ DefaultToken }, = '['
+                    hd := ( ,{ Pat<T> }, )
+                    lit ,{ DefaultToken }, = '::'
+                    tl := ( ,{ Pat< List<T> > }, )
+                    lit ,{ DefaultToken }, = ']'
+                ] }' cons_pat -> .{
+                    '[Pat< List<T> > | +[Cons ,[hd], ,[tl], ]+ ]' }. => hd tl ;
+            in match +[Cons one +[Cons two +[Nil]+ : List<Int>]+ : List<Int>]+ : List<Int> {
+                [a :: [b :: rest]] => (plus a b)
+                _ => zero }"
+        ),
+        Ok(val!(i 3))
+    );
+}
+
+#[test]
+fn end_to_end_for_syntax() {
+    let dbl_and_quad = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'dbl'
+                e := ( ,{ Expr<Int> }, )
+            ] }' dbl -> .{ (twice e) }. ;
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'quad'
+                e := ( ,{ Expr<Int> }, )
+            ] }' quad -> .{ (twice (twice e)) }. ;
+        in dbl quad three";
+    // Two macros share a helper:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "for_syntax twice = .[ e : Expr<Int> . '[Expr | (plus ,[e], ,[e],) ]' ]. in {}",
+            dbl_and_quad
+        )),
+        Ok(val!(i 24))
+    );
+    // Helpers can use earlier helpers:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "for_syntax add = .[ a : Expr<Int>  b : Expr<Int> . '[Expr | (plus ,[a], ,[b],) ]' ]. in
+             for_syntax twice = .[ e : Expr<Int> . (add e e) ]. in {}",
+            dbl_and_quad
+        )),
+        Ok(val!(i 24))
+    );
+    // Macros are typechecked against the helper's type:
+    assert_m!(
+        type_unseemly_program(&format!(
+            "for_syntax twice = .[ e : Expr<Bool> . e ]. in {}",
+            dbl_and_quad
+        )),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    // The helper exists before the program runs, so it can't use the program's variables:
+    assert_m!(
+        type_unseemly_program(&format!(
+            "(.[x : Int . for_syntax twice = .[ e : Expr<Int> . '[Expr | (plus ,[e], ,[x],) ]' ].
+                 in {} ]. five)",
+            dbl_and_quad
+        )),
+        Err(ref msg),
+        msg.contains("[UnboundName] `x")
+    );
+    // An ill-typed helper is reported (not evaluated while parsing):
+    assert_m!(
+        type_unseemly_program("for_syntax h = (plus one true) in five"),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    // It's an ordinary variable in the body, too:
+    assert_eq!(eval_unseemly_program("for_syntax h = five in (plus h h)"), Ok(val!(i 10)));
+}
+
+#[test]
+fn end_to_end_syntax_introspection() {
+    // Only introduce a temporary if the argument isn't already a variable:
+    let dbl = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'dbl'
+                e := ( ,{ Expr<Int> }, )
+            ] }' dbl -> .{
+                match (var_name e) {
+                    +[Some name]+ => '[Expr | (plus ,[e], ,[e],) ]'
+                    +[None]+ =>
+                        (.[ tmp : Atom<Int> .
+                            '[Expr | (.[ ,[tmp], : Int . (plus ,[(var_ref tmp)], ,[(var_ref tmp)],) ].
+                                      ,[e],) ]' ].
+                         (gensym '[Atom<Int> | tmp]'))
+                } }. ;
+        in";
+    assert_eq!(eval_unseemly_program(&format!("{} dbl dbl (plus one two)", dbl)), Ok(val!(i 12)));
+    assert_eq!(eval_unseemly_program(&format!("{} (.[x : Int . dbl x]. five)", dbl)), Ok(val!(i 10)));
+    // The gensym'd name can't capture the user's `tmp`:
+    assert_eq!(
+        eval_unseemly_program(&format!("{} (.[tmp : Int . dbl (plus tmp one)]. four)", dbl)),
+        Ok(val!(i 10))
+    );
+
+    let compare = "extend_syntax
+            Expr ::=also forall T . '{ [
+                lit ,{ DefaultToken }, = 'same_code?'
+                a := ( ,{ Expr<T> }, )
+                lit ,{ DefaultToken }, = 'and'
+                b := ( ,{ Expr<T> }, )
+            ] }' same_code -> .{
+                match (alpha_equal? a b) {
+                    +[True]+ => '[Expr | true]' +[False]+ => '[Expr | false]' } }. ;
+        in";
+    assert_eq!(
+        eval_unseemly_program(&format!("{} same_code? .[x : Int . x]. and .[y : Int . y].", compare)),
+        Ok(val!(b true))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} same_code? .[x : Int . x]. and .[y : Int . five].",
+            compare
+        )),
+        Ok(val!(b false))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "{} (.[z : Int  w : Int . same_code? z and w]. one one)",
+            compare
+        )),
+        Ok(val!(b false))
+    );
+
+    assert_eq!(
+        eval_unseemly_program("(alpha_equal? '[Atom<Int> | x]' '[Atom<Int> | x]')"),
+        Ok(val!(b true))
+    );
+    assert_eq!(
+        eval_unseemly_program("(same_form? '[Expr | (plus one two)]' '[Expr | (zero? one)]')"),
+        Ok(val!(b true))
+    );
+    assert_eq!(
+        eval_unseemly_program("(same_form? '[Type | Int]' '[Type | [Int -> Int]]')"),
+        Ok(val!(b false))
+    );
+    // Only syntax can be compared:
+    assert_m!(type_unseemly_program("(alpha_equal? one one)"), Err(ref msg), msg.contains("[Mismatch]"));
+
+    // Syntax that a macro quotes itself can be inspected, too:
+    let check_quoted = |check: &str| {
+        eval_unseemly_program(&format!(
+            "extend_syntax
+                Expr ::=also forall T . '{{ [
+                    lit ,{{ DefaultToken }}, = 'check'
+                    e := ( ,{{ Expr<Int> }}, )
+                ] }}' check -> .{{
+                    match {} {{ +[True]+ => '[Expr | true]' +[False]+ => '[Expr | false]' }} }}. ;
+            in check five",
+            check
+        ))
+    };
+    assert_eq!(
+        check_quoted("match (var_name '[Expr | ,[e], ]') { +[Some n]+ => true +[None]+ => false }"),
+        Ok(val!(b true))
+    );
+    assert_eq!(
+        check_quoted("(same_form? '[Expr | (plus ,[e], one) ]' '[Expr | (plus one two) ]')"),
+        Ok(val!(b true))
+    );
+    assert_eq!(
+        check_quoted(
+            "(alpha_equal? '[Expr | .[x : Int . ,[e], ]. ]' '[Expr | .[y : Int . ,[e], ]. ]')"
+        ),
+        Ok(val!(b true))
+    );
+}
+
+#[test]
+fn end_to_end_modules() {
+    set_import_base_dir("src/examples/use_maybe_lib.≉");
+    let mut use_maybe_lib = String::new();
+    File::open(&Path::new("src/examples/use_maybe_lib.≉"))
+        .unwrap()
+        .read_to_string(&mut use_maybe_lib)
+        .unwrap();
+    assert_eq!(eval_unseemly_program(&use_maybe_lib), Ok(val!(i 9)));
+
+    // Values, types, and syntax (in `Expr`, `Type`, and `Pat`) are all imported:
+    assert_eq!(
+        eval_unseemly_program("import \"maybe_lib.≉\" in try (nonzero zero) else seven"),
+        Ok(val!(i 7))
+    );
+    assert_eq!(
+        eval_unseemly_program(
+            "import \"maybe_lib.≉\" in
+             (.[ p : Point . match +[Some (manhattan p)]+ : Maybe Int { just x => x _ => zero } ].
+              *[x : one  y : two]*)"
+        ),
+        Ok(val!(i 3))
+    );
+    // Importing the same module again is fine:
+    assert_eq!(
+        eval_unseemly_program(
+            "import \"maybe_lib.≉\" in import \"maybe_lib.≉\" in try (nonzero four) else seven"
+        ),
+        Ok(val!(i 4))
+    );
+    assert_m!(
+        type_unseemly_program("import \"maybe_lib.≉\" in (manhattan five)"),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+
+    assert_m!(
+        type_unseemly_program("import \"no_such_lib.≉\" in five"),
+        Err(ref msg),
+        msg.contains("[ImportFailed] couldn't import `no_such_lib.≉`")
+    );
+    assert_m!(
+        type_unseemly_program("import \"fact.≉\" in five"),
+        Err(ref msg),
+        msg.contains("it doesn't `export` anything")
+    );
+    assert_m!(
+        type_unseemly_program("export values five ; syntax nope ;"),
+        Err(ref msg),
+        msg.contains("[UnboundName] `nope`")
+    );
+}
+
+#[test]
+fn end_to_end_module_cache() {
+    let dir = std::env::temp_dir().join(format!("unseemly_cache_test_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy("src/examples/maybe_lib.≉", dir.join("maybe_lib.≉")).unwrap();
+    let program = "import \"maybe_lib.≉\" in
+        (.[ digit : Maybe Int . (plus (manhattan *[x : three  y : four]*)
+                                      match digit { just d => d  _ => zero }) ].
+         (nonzero two))";
+
+    // Each run gets a fresh thread, so nothing is remembered except through the cache:
+    let run = |dir: &Path| {
+        let dir = dir.to_path_buf();
+        std::thread::spawn(move || {
+            core_module_forms::import_base_dir.with(|d| *d.borrow_mut() = dir.clone());
+            core_module_forms::cache_dir.with(|d| *d.borrow_mut() = Some(dir.join("cache")));
+            // (`Value`s can't leave the thread)
+            eval_unseemly_program(program).map(|v| v.to_string())
+        })
+        .join()
+        .unwrap()
+    };
+    let cached_files = |dir: &Path| -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(dir.join("cache")).unwrap().map(|f| f.unwrap().path()).collect()
+    };
+
+    assert_eq!(run(&dir), Ok("9".to_string()));
+    let cached = cached_files(&dir);
+    assert_eq!(cached.len(), 1);
+    assert_eq!(run(&dir), Ok("9".to_string()));
+
+    // Prove that the cache is used, by tampering with it:
+    let artifact = std::fs::read_to_string(&cached[0]).unwrap();
+    assert!(artifact.contains("(vr \"plus\")"));
+    std::fs::write(&cached[0], artifact.replace("(vr \"plus\")", "(vr \"times\")")).unwrap();
+    assert_eq!(run(&dir), Ok("14".to_string()));
+
+    // ...unless it was made by a different version of Unseemly:
+    let tampered = std::fs::read_to_string(&cached[0]).unwrap();
+    let version = format!("-{}-", env!("CARGO_PKG_VERSION"));
+    assert!(tampered.contains(&version));
+    std::fs::write(&cached[0], tampered.replacen(&version, "-9.9.9-", 1)).unwrap();
+    assert_eq!(run(&dir), Ok("9".to_string()));
+
+    // Changing the source invalidates it:
+    let mut source = std::fs::read_to_string(dir.join("maybe_lib.≉")).unwrap();
+    source.push('\n');
+    std::fs::write(dir.join("maybe_lib.≉"), source).unwrap();
+    assert_eq!(run(&dir), Ok("9".to_string()));
+    assert_eq!(cached_files(&dir), cached);
+    assert_eq!(run(&dir), Ok("9".to_string()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn end_to_end_std() {
+    // Tests don't load the standard library unless they ask for it:
+    assert_m!(type_unseemly_program("map"), Err(ref msg), msg.contains("[UnboundName] `map`"));
+    load_std().unwrap();
+
+    let one_two_three = "+[Cons one +[Cons two +[Cons three +[Nil]+ : List<Int>]+ : List<Int>]+
+                           : List<Int>]+ : List<Int>";
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "# `let`, `letfn`, and comments come from the standard library
+             let nums = {} ; #| so do `map`, `filter`, `foldl`, and `append` |# in
+             letfn (double x : Int) -> Int = (plus x x) ; in
+             (foldl .[ acc : Int  x : Int . (plus acc x) ]. zero
+                (append (map double nums) (filter .[ x : Int . (zero? (minus x two)) ]. nums)))",
+            one_two_three
+        )),
+        Ok(val!(i 14))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "if (equal? (length {}) three) then one else zero",
+            one_two_three
+        )),
+        Ok(val!(i 1))
+    );
+    assert_eq!(
+        eval_unseemly_program(
+            "(.[ p : Pair<Int Option<Int>>  res : Result<Int Bool> .
+                 match p {
+                     *[fst : f  snd : +[Some s]+]* => s
+                     *[fst : f  snd : +[None]+]* => match res { +[Ok r]+ => (plus f r)
+                                                                +[Err e]+ => zero } } ].
+              *[fst : four  snd : +[None]+ : Option<Int>]*  +[Ok three]+ : Result<Int Bool>)"
+        ),
+        Ok(val!(i 7))
+    );
+    assert_m!(
+        type_unseemly_program("(map .[ x : Int . x ]. +[Nil]+ : List<Bool>)"),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+
+    // Macros can use it, too:
+    assert_eq!(
+        eval_unseemly_program("for_syntax f = (length +[Nil]+ : List<Int>) in f"),
+        Ok(val!(i 0))
+    );
+    assert_eq!(
+        eval_unseemly_program(
+            "extend_syntax
+                Expr ::=also forall T . '{ [
+                    lit ,{ DefaultToken }, = 'if_nil_is_empty'
+                    e := ( ,{ Expr<Int> }, )
+                ] }' if_nil_is_empty -> .{
+                    match (equal? (length +[Nil]+ : List<Int>) zero) {
+                        +[True]+ => e  +[False]+ => '[Expr | zero]' } }. ;
+            in if_nil_is_empty five"
+        ),
+        Ok(val!(i 5))
+    );
+}
+
+#[test]
+fn end_to_end_seq() {
+    let one_two_three = "(push (push (push empty one) two) three)";
+    assert_eq!(eval_unseemly_program(&format!("(len {})", one_two_three)), Ok(val!(i 3)));
+    assert_eq!(eval_unseemly_program("(len empty)"), Ok(val!(i 0)));
+    assert_eq!(
+        eval_unseemly_program(&format!("(get {} one)", one_two_three)),
+        Ok(val!(enum "Some", (i 2)))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!("(get {} three)", one_two_three)),
+        Ok(val!(enum "None",))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!("(concat {0} (slice {0} one ten))", one_two_three)),
+        Ok(val!(seq (i 1) (i 2) (i 3) (i 2) (i 3)))
+    );
+    assert_eq!(
+        eval_unseemly_program(&format!("(slice {} two one)", one_two_three)),
+        Ok(val!(seq))
+    );
+    // `push` doesn't change the original:
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "(.[ s : Sequence<Int> . (plus (len (push s four)) (len s)) ]. {})",
+            one_two_three
+        )),
+        Ok(val!(i 7))
+    );
+    assert_m!(
+        type_unseemly_program("(push (push empty one) true)"),
+        Err(ref msg),
+        msg.contains("[Mismatch]")
+    );
+    assert_m!(type_unseemly_program("(len one)"), Err(ref msg), msg.contains("[Mismatch]"));
+
+    // Converting to and from `List` is in the standard library:
+    load_std().unwrap();
+    assert_eq!(
+        eval_unseemly_program(&format!(
+            "(foldl .[ acc : Int  x : Int . (plus (times acc ten) x) ]. zero
+                (seq_to_list (concat {} (list_to_seq
+                    +[Cons four +[Cons five +[Nil]+ : List<Int>]+ : List<Int>]+ : List<Int>))))",
+            one_two_three
+        )),
+        Ok(val!(i 12345))
+    );
+    assert_eq!(
+        eval_unseemly_program("(.[ s : Sequence<Int> . (length (seq_to_list s)) ]. empty)"),
+        Ok(val!(i 0))
+    );
+}
+
 #[test]
 fn language_building() {
     assert_eq!(
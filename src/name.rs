@@ -151,6 +151,11 @@ impl Name {
             Name { id: id }
         })
     }
+    /// Is this the same as `Name::global(&self.sp())`? (Names from `gensym` and `freshen` aren't.)
+    pub fn is_global(self) -> bool {
+        id_map.with(|id_map_| id_map_.borrow().get(&self.sp()) == Some(&self.id))
+    }
+
     pub fn is(self, s: &str) -> bool { self.sp() == s }
 
     pub fn is_name(self, n: Name) -> bool { self.sp() == n.sp() }
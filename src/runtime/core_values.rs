@@ -11,7 +11,7 @@ use crate::{
 };
 use std::rc::Rc;
 
-use num::BigInt;
+use num::{BigInt, ToPrimitive};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypedValue {
@@ -74,11 +74,160 @@ pub fn core_typed_values() -> Assoc<Name, TypedValue> {
         "eight" => tf!( "Int", val!(i 8) ),
         "nine" => tf!( "Int", val!(i 9) ),
         "ten" => tf!( "Int", val!(i 10) ),
+        // Sequences (persistent vectors, so none of these copy the whole thing):
+        "empty" => TypedValue {
+            ty: ast!({ "Type" "forall_type" :
+                "param" => ["T"],
+                "body" => (import [* [forall "param"]]
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]})}),
+            val: Sequence(im_rc::Vector::new())
+        },
+        "push" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]},
+                    (vr "T")],
+                "ret" =>
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]}})},
+            ( Sequence(mut seq), elt ) => { seq.push_back(Rc::new(elt)); Sequence(seq) }
+        ),
+        // `+[None]+` if the index is out of bounds:
+        "get" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]},
+                    {"Type" "Int" :}],
+                "ret" => {"Type" "enum" :
+                    "name" => [@"c" "Some", "None"],
+                    "component" => [@"c" [(vr "T")], []]}})},
+            ( Sequence(seq), Int(idx) ) => match idx.to_usize().and_then(|idx| seq.get(idx)) {
+                Some(elt) => Enum(n("Some"), vec![(**elt).clone()]),
+                None => Enum(n("None"), vec![])
+            }
+        ),
+        "len" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]}],
+                "ret" => {"Type" "Int" :}})},
+            ( Sequence(seq) ) => Int(BigInt::from(seq.len()))
+        ),
+        "concat" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]},
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]}],
+                "ret" =>
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]}})},
+            ( Sequence(mut lhs), Sequence(rhs) ) => { lhs.append(rhs); Sequence(lhs) }
+        ),
+        // `(slice seq from to)` is the elements from index `from` up to (not including) `to`.
+        // Indices past either end are treated as being at that end.
+        "slice" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]},
+                    {"Type" "Int" :}, {"Type" "Int" :}],
+                "ret" =>
+                    {"Type" "type_apply" : "type_rator" => (vr "Sequence"), "arg" => [(vr "T")]}})},
+            ( Sequence(seq), Int(from), Int(to) ) => {
+                let clamp = |idx: BigInt| {
+                    num::clamp(idx, BigInt::from(0), BigInt::from(seq.len())).to_usize().unwrap()
+                };
+                let (from, to) = (clamp(from), clamp(to));
+                Sequence(seq.skip(from).take(to.saturating_sub(from)))
+            }
+        ),
+        // Taking apart and building syntax (e.g. in macro implementations):
+        "var_name" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [ {"Type" "type_apply" : "type_rator" => (vr "Expr"), "arg" => [(vr "T")]} ],
+                "ret" => {"Type" "enum" :
+                    "name" => [@"c" "Some", "None"],
+                    "component" => [@"c"
+                        [{"Type" "type_apply" : "type_rator" => (vr "Atom"), "arg" => [(vr "T")]}],
+                        []]}})},
+            ( AbstractSyntax(e) ) => match *without_binding(&e) {
+                Ast::VariableReference(name) =>
+                    Enum(n("Some"), vec![AbstractSyntax(Ast::Atom(name))]),
+                _ => Enum(n("None"), vec![])
+            }
+        ),
+        "var_ref" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [ {"Type" "type_apply" : "type_rator" => (vr "Atom"), "arg" => [(vr "T")]} ],
+                "ret" => {"Type" "type_apply" : "type_rator" => (vr "Expr"), "arg" => [(vr "T")]}})},
+            ( AbstractSyntax(Ast::Atom(name)) ) => AbstractSyntax(Ast::VariableReference(name))
+        ),
+        // A name that's spelled like its argument, but is different from every other name:
+        "gensym" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [ {"Type" "type_apply" : "type_rator" => (vr "Atom"), "arg" => [(vr "T")]} ],
+                "ret" => {"Type" "type_apply" : "type_rator" => (vr "Atom"), "arg" => [(vr "T")]}})},
+            ( AbstractSyntax(Ast::Atom(name)) ) => AbstractSyntax(Ast::Atom(name.freshen()))
+        ),
+        // Is it the same syntax, up to the names of binders? (Works on `Atom`s, too.)
+        "alpha_equal?" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["N", "T"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "N"), "arg" => [(vr "T")]},
+                    {"Type" "type_apply" : "type_rator" => (vr "N"), "arg" => [(vr "T")]}],
+                "ret" => (vr "Bool")})},
+            ( lhs, rhs ) => val!(b match (lhs, rhs) {
+                (AbstractSyntax(lhs), AbstractSyntax(rhs)) => crate::alpha::alpha_equiv(
+                    without_binding(&lhs), without_binding(&rhs)),
+                _ => false // Not syntax (`N` is something like `List`)
+            })
+        ),
+        // Was the first piece of syntax made by the same form (e.g. `apply`) as the second?
+        "same_form?" =>
+        tyf!( { "Type" "forall_type" :
+            "param" => ["N", "T", "S"],
+            "body" => (import [* [forall "param"]] { "Type" "fn" :
+                "param" => [
+                    {"Type" "type_apply" : "type_rator" => (vr "N"), "arg" => [(vr "T")]},
+                    {"Type" "type_apply" : "type_rator" => (vr "N"), "arg" => [(vr "S")]}],
+                "ret" => (vr "Bool")})},
+            ( lhs, rhs ) => val!(b match (lhs, rhs) {
+                (AbstractSyntax(lhs), AbstractSyntax(rhs)) =>
+                    match (without_binding(&lhs), without_binding(&rhs)) {
+                        (Ast::Node(f, _, _), Ast::Node(f_rhs, _, _)) => f == f_rhs,
+                        (lhs, rhs) => std::mem::discriminant(lhs) == std::mem::discriminant(rhs)
+                    },
+                _ => false // Not syntax (`N` is something like `List`)
+            })
+        ),
         "false" => TypedValue { ty: ast!((vr "Bool")), val: val!(b false)},
         "true" => TypedValue { ty: ast!((vr "Bool")), val: val!(b true)}
     )
 }
 
+/// Syntax values keep the binding information from where they were parsed; look past it.
+fn without_binding(mut a: &Ast) -> &Ast {
+    while let Ast::ExtendEnv(ref body, _) | Ast::ExtendEnvPhaseless(ref body, _) = *a {
+        a = body;
+    }
+    a
+}
+
 pub fn core_values() -> Assoc<Name, Value> { core_typed_values().map(&erase_type) }
 
 // Helper for building an environment by reifying a bunch of Rust types
@@ -106,6 +255,7 @@ pub fn core_types() -> Assoc<Name, Ty> {
         .set(n("Pat"), get__primitive_type(n("Pat")))
         .set(n("Type"), get__primitive_type(n("Type")))
         .set(n("Expr"), get__primitive_type(n("Expr")))
+        .set(n("Atom"), get__primitive_type(n("Atom")))
         .set(n("Sequence"), get__primitive_type(n("Sequence")))
         .set_assoc(&reified_ty_env!(
             Option<Irr>, u8, usize,
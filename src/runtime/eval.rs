@@ -9,15 +9,15 @@ use crate::{
     walk_mode::{NegativeWalkMode, WalkMode},
 };
 use num::bigint::BigInt;
-use std::{self, rc::Rc};
+use std::{self, cell::RefCell, fmt, rc::Rc};
 
 /// Values in Unseemly.
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Int(BigInt),
-    Sequence(Vec<Rc<Value>>), // TODO: switch to a different core sequence type
-    Function(Rc<Closure>),    // TODO: unsure if this Rc is needed
+    Sequence(im_rc::Vector<Rc<Value>>), // Persistent, so `push`, `concat`, etc. are cheap
+    Function(Rc<Closure>),              // TODO: unsure if this Rc is needed
     BuiltInFunction(BIF),
     AbstractSyntax(Ast),
     Struct(Assoc<Name, Value>),
@@ -26,11 +26,13 @@ pub enum Value {
 
 pub use self::Value::*;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Closure {
-    pub body: Ast,
-    pub params: Vec<Name>,
-    pub env: Assoc<Name, Value>,
+custom_derive! {
+    #[derive(Debug, Clone, PartialEq, Reifiable)]
+    pub struct Closure {
+        pub body: Ast,
+        pub params: Vec<Name>,
+        pub env: Assoc<Name, Value>,
+    }
 }
 
 // Built-in function
@@ -113,7 +115,7 @@ impl WalkMode for Eval {
 
     fn get_walk_rule(f: &Form) -> WalkRule<Eval> {
         // Macro invocations use `eval`, to avoid having a whole extra field in `Form`:
-        if f.name == n("macro_invocation") {
+        if crate::core_macro_forms::is_macro_invocation(f) {
             icp!("unexpanded macro!")
         }
         f.eval.pos().clone()
@@ -123,7 +125,7 @@ impl WalkMode for Eval {
     fn walk_var(n: Name, cnc: &LazyWalkReses<Eval>) -> Result<Value, ()> {
         match cnc.env.find(&n) {
             Some(v) => Ok(v.clone()),
-            None => panic!("Undefined var `{}` in {}", n, cnc.env),
+            None => panic!("Undefined var `{}` in {}{}", n, cnc.env, origin_chain()),
         }
     }
 
@@ -168,6 +170,96 @@ pub fn neg_eval(pat: &Ast, env: Assoc<Name, Value>) -> Result<Assoc<Name, Value>
     walk::<Destructure>(pat, &LazyWalkReses::new_wrapper(env))
 }
 
+/// Where some macro-generated code came from: which macro, invoked where, from which template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub macro_name: Name,
+    pub invocation_line: Option<usize>,
+    pub template_line: Option<usize>,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "in expansion of `{}`", self.macro_name)?;
+        if let Some(line) = self.invocation_line {
+            write!(f, " at line {}", line)?;
+        }
+        if let Some(line) = self.template_line {
+            write!(f, " (template at line {})", line)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The macro expansions that produced the code being evaluated (innermost last).
+    static current_origins: RefCell<Vec<Origin>> = RefCell::new(vec![]);
+    /// The macro expansions that produced the code where a runtime error happened.
+    static error_origins: RefCell<Option<Vec<Origin>>> = RefCell::new(None);
+    /// What went wrong, if a runtime error happened.
+    static error_message: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Fail evaluation, explaining why.
+/// (A plain `Err(())` is also how a pattern fails to match, which isn't an error.)
+pub fn runtime_error(msg: String) -> Result<Value, ()> {
+    error_message.with(|m| {
+        m.borrow_mut().get_or_insert(msg);
+    });
+    Err(())
+}
+
+/// Has a runtime error happened (as opposed to a pattern failing to match)?
+pub fn runtime_error_pending() -> bool { error_message.with(|m| m.borrow().is_some()) }
+
+/// What went wrong in the last runtime error. This forgets it, so call it before evaluating, too.
+pub fn take_runtime_error() -> Option<String> { error_message.with(|m| m.borrow_mut().take()) }
+
+/// Evaluate some code that `origin` produced (using `eval_body`).
+pub fn eval_from_origin(
+    origin: Origin,
+    eval_body: impl FnOnce() -> Result<Value, ()>,
+) -> Result<Value, ()>
+{
+    // Runtime errors are often panics, so make sure to clean up while unwinding:
+    struct PopOrigin;
+    impl Drop for PopOrigin {
+        fn drop(&mut self) { current_origins.with(|origins| origins.borrow_mut().pop()); }
+    }
+
+    current_origins.with(|origins| origins.borrow_mut().push(origin));
+    let _pop = PopOrigin;
+    let res = eval_body();
+    if res.is_err() {
+        // Only the innermost expansion gets to say where the error happened:
+        error_origins.with(|err_origins| {
+            err_origins
+                .borrow_mut()
+                .get_or_insert_with(|| current_origins.with(|origins| origins.borrow().clone()));
+        });
+    }
+    res
+}
+
+fn describe_origins(origins: &[Origin]) -> String {
+    origins.iter().rev().map(|origin| format!("\n    {}", origin)).collect()
+}
+
+/// The chain of macro expansions that produced the code being evaluated, innermost first.
+/// (Empty if the code was written by hand.)
+pub fn origin_chain() -> String {
+    current_origins.with(|origins| describe_origins(&origins.borrow()))
+}
+
+/// The chain of macro expansions that produced the code where the last runtime error happened.
+/// This forgets that error, so call it before evaluating, too.
+pub fn take_error_origin_chain() -> String {
+    error_origins
+        .with(|err_origins| err_origins.borrow_mut().take())
+        .map(|origins| describe_origins(&origins))
+        .unwrap_or_default()
+}
+
 custom_derive! {
     #[derive(Copy, Clone, Debug, Reifiable)]
     pub struct QQuote {}
@@ -197,6 +289,9 @@ impl WalkMode for QQuote {
     }
     // TODO #26: Just special-case "unquote" and "dotdotdot"
     fn get_walk_rule(f: &Form) -> WalkRule<QQuote> { f.quasiquote.pos().clone() }
+    // Quoted syntax keeps its structure, even inside forms that need a `Custom` rule.
+    // The exception is `unquote`, whose body isn't quoted.
+    fn walks_wrappers_literally(f: &Form) -> bool { f.name != n("unquote") }
     fn automatically_extend_env() -> bool { false }
 }
 
@@ -209,7 +209,7 @@ impl<T0: Reifiable, T1: Reifiable> Reifiable for (T0, T1) {
     }
 
     fn reify(&self) -> Value {
-        Value::Sequence(vec![Rc::new(self.0.reify()), Rc::new(self.1.reify())])
+        Value::Sequence(vec![Rc::new(self.0.reify()), Rc::new(self.1.reify())].into())
     }
 
     fn reflect(v: &Value) -> Self {
@@ -464,7 +464,8 @@ custom_derive! {
     #[derive(Debug, PartialEq, Eq, Reifiable, Clone)]
     enum BasicEnum {
         Jefferson(BigInt, BigInt), // TODO: change the first one to String
-        Burr(BigInt)
+        Burr(BigInt),
+        Madison
     }
 }
 
@@ -505,6 +506,7 @@ fn basic_r_and_r_roundtrip() {
 
     assert_eq!(bev0, BasicEnum::reflect(&bev0.reify()));
     assert_eq!(bev1, BasicEnum::reflect(&bev1.reify()));
+    assert_eq!(BasicEnum::Madison, BasicEnum::reflect(&BasicEnum::Madison.reify()));
 
     // assert_eq!(None, Option::reflect(&None.reify()));
     assert_eq!(Some(BigInt::from(5)), Option::reflect(&Some(BigInt::from(5)).reify()));
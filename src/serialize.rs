@@ -0,0 +1,1153 @@
+// Serialization of compiler data (`Ast`s, types, grammars, and `Value`s) as S-expressions
+//  (or the equivalent JSON), for caching compiled modules and for other tools to read.
+//
+// Most of this is straightforward, but two things need care:
+//  * `Name`s are interned, and freshened names are distinguished only by identity.
+//    Global names are written as their spelling.
+//    Other names are numbered, and each number becomes a single new `gensym` when read back in.
+//  * `Form`s are mostly Rust closures.
+//    Core forms are written as the nonterminal and name that they can be found under,
+//     unquotation forms (which are made on the fly) as their arguments,
+//     and macro invocation forms as their definitions (the first time they appear).
+//    The Rust closures in `SyntaxExtension`s only ever appear in the grammars of core forms,
+//     so they never need to be written out.
+
+use crate::{
+    ast::Ast,
+    beta::{Beta, ExportBeta},
+    core_macro_forms::{invoked_macro_name, macro_from_definition, MacroDefinition},
+    core_qq_forms::QQForm,
+    form::Form,
+    grammar::{FormPat, Scanner},
+    name::*,
+    runtime::eval::{Closure, Value},
+    ty::Ty,
+    util::assoc::Assoc,
+};
+use num::bigint::BigInt;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    rc::Rc,
+};
+
+/// An S-expression: atoms, strings, and lists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sexp {
+    Atom(String),
+    Str(String),
+    List(Vec<Sexp>),
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Sexp::Atom(ref a) => write!(f, "{}", a),
+            Sexp::Str(ref s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        _ => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Sexp::List(ref elts) => {
+                write!(f, "(")?;
+                for (i, elt) in elts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", elt)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Sexp {
+    pub fn parse(text: &str) -> Result<Sexp, String> {
+        let mut chars = text.chars().peekable();
+        let res = Sexp::parse_rec(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(res),
+            Some(c) => Err(format!("unexpected `{}` after the end", c)),
+        }
+    }
+
+    fn parse_rec(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Sexp, String> {
+        skip_whitespace(chars);
+        match chars.next() {
+            None => Err("unexpected end of input".to_owned()),
+            Some('(') => {
+                let mut elts = vec![];
+                loop {
+                    skip_whitespace(chars);
+                    if chars.peek() == Some(&')') {
+                        chars.next();
+                        return Ok(Sexp::List(elts));
+                    }
+                    elts.push(Sexp::parse_rec(chars)?);
+                }
+            }
+            Some(')') => Err("unexpected `)`".to_owned()),
+            Some('"') => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated string".to_owned()),
+                        Some('"') => return Ok(Sexp::Str(s)),
+                        Some('\\') => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some(c) => s.push(c),
+                            None => return Err("unterminated string".to_owned()),
+                        },
+                        Some(c) => s.push(c),
+                    }
+                }
+            }
+            Some(c) => {
+                let mut a = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    a.push(c);
+                    chars.next();
+                }
+                Ok(Sexp::Atom(a))
+            }
+        }
+    }
+
+    /// The same thing, in JSON: lists are arrays, atoms are strings,
+    ///  and strings are objects like `{"str": "x"}`.
+    pub fn to_json(&self) -> String {
+        match *self {
+            Sexp::Atom(ref a) => json_string(a),
+            Sexp::Str(ref s) => format!("{{\"str\": {}}}", json_string(s)),
+            Sexp::List(ref elts) => {
+                format!("[{}]", elts.iter().map(Sexp::to_json).collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+
+    /// Undoes `to_json`.
+    pub fn parse_json(text: &str) -> Result<Sexp, String> {
+        let mut chars = text.chars().peekable();
+        let res = Sexp::parse_json_rec(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(res),
+            Some(c) => Err(format!("unexpected `{}` after the end", c)),
+        }
+    }
+
+    fn parse_json_rec(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Sexp, String> {
+        fn expect(
+            chars: &mut std::iter::Peekable<std::str::Chars>,
+            expected: char,
+        ) -> Result<(), String> {
+            skip_whitespace(chars);
+            match chars.next() {
+                Some(c) if c == expected => Ok(()),
+                Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+                None => Err(format!("expected `{}`, found the end", expected)),
+            }
+        }
+        skip_whitespace(chars);
+        match chars.next() {
+            None => Err("unexpected end of input".to_owned()),
+            Some('[') => {
+                let mut elts = vec![];
+                skip_whitespace(chars);
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                    return Ok(Sexp::List(elts));
+                }
+                loop {
+                    elts.push(Sexp::parse_json_rec(chars)?);
+                    skip_whitespace(chars);
+                    match chars.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Sexp::List(elts)),
+                        _ => return Err("expected `,` or `]`".to_owned()),
+                    }
+                }
+            }
+            Some('{') => {
+                expect(chars, '"')?;
+                if parse_json_string(chars)? != "str" {
+                    return Err("the only object key is \"str\"".to_owned());
+                }
+                expect(chars, ':')?;
+                expect(chars, '"')?;
+                let s = parse_json_string(chars)?;
+                expect(chars, '}')?;
+                Ok(Sexp::Str(s))
+            }
+            Some('"') => Ok(Sexp::Atom(parse_json_string(chars)?)),
+            Some(c) => Err(format!("unexpected `{}`", c)),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut res = "\"".to_owned();
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// Reads the rest of a JSON string (after the opening `"`).
+fn parse_json_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    fn hex4(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<u32, String> {
+        let digits: String = chars.take(4).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("bad escape `\\u{}`", digits))
+    }
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_owned()),
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => {
+                    let mut code = hex4(chars)?;
+                    // Characters outside the BMP are written as surrogate pairs:
+                    if (0xD800..0xDC00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("unpaired surrogate".to_owned());
+                        }
+                        code = 0x10000 + ((code - 0xD800) << 10) + (hex4(chars)? - 0xDC00);
+                    }
+                    s.push(std::char::from_u32(code).ok_or("bad character escape")?);
+                }
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_owned()),
+            },
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// `(tag args ⋯)`, or just `tag` if there are no arguments.
+pub fn tagged(tag: &str, args: Vec<Sexp>) -> Sexp {
+    if args.is_empty() {
+        Sexp::Atom(tag.to_owned())
+    } else {
+        Sexp::List(std::iter::once(Sexp::Atom(tag.to_owned())).chain(args).collect())
+    }
+}
+
+/// The tag and arguments of `sexp` (undoes `tagged`).
+pub fn untag(sexp: &Sexp) -> Result<(&str, &[Sexp]), String> {
+    match *sexp {
+        Sexp::Atom(ref tag) => Ok((tag, &[])),
+        Sexp::List(ref elts) => match elts.split_first() {
+            Some((Sexp::Atom(ref tag), args)) => Ok((tag, args)),
+            _ => malformed("tagged list", sexp),
+        },
+        Sexp::Str(_) => malformed("tagged list", sexp),
+    }
+}
+
+fn malformed<T>(what: &str, sexp: &Sexp) -> Result<T, String> {
+    Err(format!("malformed {}: {}", what, sexp))
+}
+
+/// Things that can be written as `Sexp`s and read back.
+/// Reading and writing happen in a context, so that identity (of names and forms) is preserved
+///  within a single document.
+pub trait Serializable: Sized {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String>;
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String>;
+}
+
+#[derive(Default)]
+pub struct Serializer {
+    /// Non-global names, numbered in order of appearance.
+    fresh_names: HashMap<Name, usize>,
+    /// Macro invocation forms (by address), numbered in order of appearance.
+    macro_forms: HashMap<usize, usize>,
+    /// The names of the built-in functions in the core value environment.
+    built_ins: Option<HashSet<Name>>,
+}
+
+#[derive(Default)]
+pub struct Deserializer {
+    fresh_names: HashMap<usize, Name>,
+    macro_forms: HashMap<usize, Rc<Form>>,
+    qq_forms: HashMap<QQForm, Rc<Form>>,
+    core_values: Option<Assoc<Name, Value>>,
+}
+
+/// Write `x` as a self-contained document.
+pub fn to_sexp<T: Serializable>(x: &T) -> Result<Sexp, String> {
+    x.serialize(&mut Serializer::default())
+}
+
+/// Read a self-contained document written by `to_sexp`.
+pub fn from_sexp<T: Serializable>(sexp: &Sexp) -> Result<T, String> {
+    T::deserialize(sexp, &mut Deserializer::default())
+}
+
+/// A core form's nonterminal, its name, and (in case of duplicate names) which one it is.
+type CoreFormName = (Name, Name, usize);
+
+thread_local! {
+    /// Every form that the core grammar contains, by address and by `CoreFormName`.
+    static core_form_names: (HashMap<usize, CoreFormName>, HashMap<CoreFormName, Rc<Form>>)
+        = find_core_forms();
+}
+
+fn find_core_forms() -> (HashMap<usize, CoreFormName>, HashMap<CoreFormName, Rc<Form>>) {
+    fn find_rec(pat: &FormPat, nt: Name, found: &mut Vec<(Name, Rc<Form>)>) {
+        use crate::grammar::FormPat::*;
+        match *pat {
+            Scope(ref f, _) => {
+                if !found.iter().any(|(_, seen)| Rc::ptr_eq(seen, f)) {
+                    found.push((nt, f.clone()));
+                    find_rec(&f.grammar, nt, found);
+                }
+            }
+            Anyways(_) | Impossible | Scan(_) | Call(_) => {}
+            Common(ref body)
+            | Reserved(ref body, _)
+            | Literal(ref body, _)
+            | VarRef(ref body)
+            | Star(ref body)
+            | Plus(ref body)
+            | Named(_, ref body)
+            | Pick(ref body, _)
+            | NameImport(ref body, _)
+            | NameImportPhaseless(ref body, _)
+            | QuoteDeepen(ref body, _)
+            | QuoteEscape(ref body, _) => find_rec(body, nt, found),
+            Seq(ref bodies) | Alt(ref bodies) => {
+                for body in bodies {
+                    find_rec(body, nt, found)
+                }
+            }
+            Biased(ref lhs, ref rhs) | SynImport(ref lhs, ref rhs, _) => {
+                find_rec(lhs, nt, found);
+                find_rec(rhs, nt, found)
+            }
+        }
+    }
+
+    // Visit nonterminals in a consistent order, so that the names are the same from run to run:
+    let core_forms = crate::core_forms::get_core_forms();
+    let mut nts: Vec<(Name, Rc<FormPat>)> =
+        core_forms.iter_pairs().map(|(nt, pat)| (*nt, pat.clone())).collect();
+    nts.sort_by_key(|(nt, _)| nt.sp());
+
+    let mut found = vec![];
+    for (nt, pat) in nts {
+        find_rec(&pat, nt, &mut found);
+    }
+    // Not in the grammar, but used for types and in expanded code:
+    found.push((n("Type"), crate::core_type_forms::primitive_type.with(|p_t| p_t.clone())));
+    found.push((n("Type"), crate::core_type_forms::unused_type.with(|u_t| u_t.clone())));
+    found.push((
+        n("Expr"),
+        crate::core_macro_forms::expansion_origin_form.with(|e_o_f| e_o_f.clone()),
+    ));
+
+    let mut names = HashMap::new();
+    let mut forms = HashMap::new();
+    for (nt, form) in found {
+        let mut idx = 0;
+        while forms.contains_key(&(nt, form.name, idx)) {
+            idx += 1;
+        }
+        names.insert(Rc::as_ptr(&form) as usize, (nt, form.name, idx));
+        forms.insert((nt, form.name, idx), form);
+    }
+    (names, forms)
+}
+
+/// Every core form's `CoreFormName` and grammar, in a consistent order.
+pub fn core_forms_description() -> String {
+    core_form_names.with(|cfn| {
+        let mut forms: Vec<String> = cfn
+            .1
+            .iter()
+            .map(|((nt, name, idx), f)| format!("{} {} {} {:?}", nt.sp(), name.sp(), idx, f.grammar))
+            .collect();
+        forms.sort();
+        forms.join("\n")
+    })
+}
+
+impl Serializer {
+    fn form(&mut self, f: &Rc<Form>) -> Result<Sexp, String> {
+        let addr = Rc::as_ptr(f) as usize;
+        if let Some((nt, name, idx)) = core_form_names.with(|cfn| cfn.0.get(&addr).cloned()) {
+            let mut args = vec![nt.serialize(self)?, name.serialize(self)?];
+            if idx > 0 {
+                args.push(idx.serialize(self)?);
+            }
+            return Ok(tagged("form", args));
+        }
+        match crate::core_qq_forms::qq_form(f) {
+            Some(QQForm::Unquote(nt, pos_quot, depth)) => {
+                return Ok(tagged("unquote", vec![
+                    nt.serialize(self)?,
+                    pos_quot.serialize(self)?,
+                    depth.serialize(self)?,
+                ]))
+            }
+            Some(QQForm::DotDotDot(nt)) => {
+                return Ok(tagged("dotdotdot", vec![nt.serialize(self)?]))
+            }
+            None => {}
+        }
+        if let Some(&id) = self.macro_forms.get(&addr) {
+            return Ok(tagged("macro", vec![id.serialize(self)?]));
+        }
+        let def = match f.macro_definition {
+            Some(ref def) => def.clone(),
+            None => return Err(format!("the form `{}` isn't a core form or a macro", f.name)),
+        };
+        let id = self.macro_forms.len();
+        self.macro_forms.insert(addr, id);
+        Ok(tagged("macro", vec![
+            id.serialize(self)?,
+            def.nt.serialize(self)?,
+            def.macro_name.serialize(self)?,
+            def.grammar.serialize(self)?,
+            def.implementation.serialize(self)?,
+            def.export_names.serialize(self)?,
+        ]))
+    }
+
+    fn is_built_in(&mut self, name: Name, value: &Value) -> bool {
+        let built_ins = self.built_ins.get_or_insert_with(|| {
+            crate::runtime::core_values::core_values()
+                .iter_pairs()
+                .filter(|(_, v)| matches!(v, Value::BuiltInFunction(_)))
+                .map(|(name, _)| *name)
+                .collect()
+        });
+        matches!(value, Value::BuiltInFunction(_)) && built_ins.contains(&name)
+    }
+}
+
+impl Deserializer {
+    fn qq_form(&mut self, how: QQForm) -> Rc<Form> {
+        self.qq_forms
+            .entry(how)
+            .or_insert_with(|| match how {
+                QQForm::Unquote(nt, pos_quot, depth) => {
+                    crate::core_qq_forms::unquote_form(nt, pos_quot, depth)
+                }
+                QQForm::DotDotDot(nt) => crate::core_qq_forms::dotdotdot_form(nt),
+            })
+            .clone()
+    }
+
+    fn form(&mut self, sexp: &Sexp) -> Result<Rc<Form>, String> {
+        match untag(sexp)? {
+            ("form", args) if args.len() == 2 || args.len() == 3 => {
+                let nt = Name::deserialize(&args[0], self)?;
+                let name = Name::deserialize(&args[1], self)?;
+                let idx = match args.get(2) {
+                    Some(idx) => usize::deserialize(idx, self)?,
+                    None => 0,
+                };
+                core_form_names
+                    .with(|cfn| cfn.1.get(&(nt, name, idx)).cloned())
+                    .ok_or_else(|| format!("there's no core form named {}", sexp))
+            }
+            ("unquote", [nt, pos_quot, depth]) => {
+                let how = QQForm::Unquote(
+                    Name::deserialize(nt, self)?,
+                    bool::deserialize(pos_quot, self)?,
+                    u8::deserialize(depth, self)?,
+                );
+                Ok(self.qq_form(how))
+            }
+            ("dotdotdot", [nt]) => {
+                let how = QQForm::DotDotDot(Name::deserialize(nt, self)?);
+                Ok(self.qq_form(how))
+            }
+            ("macro", [id]) => {
+                let id = usize::deserialize(id, self)?;
+                self.macro_forms
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| format!("macro {} is used before it's defined", id))
+            }
+            ("macro", [id, nt, macro_name, grammar, implementation, export_names]) => {
+                let id = usize::deserialize(id, self)?;
+                let def = MacroDefinition {
+                    macro_name: Name::deserialize(macro_name, self)?,
+                    nt: Name::deserialize(nt, self)?,
+                    grammar: FormPat::deserialize(grammar, self)?,
+                    implementation: Closure::deserialize(implementation, self)?,
+                    export_names: Vec::<Name>::deserialize(export_names, self)?,
+                    // Only needed for unparsing (in expansion traces), so it isn't saved:
+                    syntax: crate::core_forms::get_core_forms(),
+                };
+                let form = macro_from_definition(def);
+                self.macro_forms.insert(id, form.clone());
+                Ok(form)
+            }
+            _ => malformed("form", sexp),
+        }
+    }
+}
+
+// Things that are written as atoms, the way that `Display` writes them:
+macro_rules! atomic_serializability {
+    ( $( $t:ty => $what:expr ),* ) => {
+        $(
+        impl Serializable for $t {
+            fn serialize(&self, _: &mut Serializer) -> Result<Sexp, String> {
+                Ok(Sexp::Atom(self.to_string()))
+            }
+            fn deserialize(sexp: &Sexp, _: &mut Deserializer) -> Result<Self, String> {
+                match *sexp {
+                    Sexp::Atom(ref a) => a.parse().or_else(|_| malformed($what, sexp)),
+                    _ => malformed($what, sexp),
+                }
+            }
+        }
+        )*
+    }
+}
+
+atomic_serializability!(usize => "number", u64 => "number", u8 => "number", bool => "boolean",
+                        BigInt => "integer");
+
+impl Serializable for String {
+    fn serialize(&self, _: &mut Serializer) -> Result<Sexp, String> { Ok(Sexp::Str(self.clone())) }
+    fn deserialize(sexp: &Sexp, _: &mut Deserializer) -> Result<Self, String> {
+        match *sexp {
+            Sexp::Str(ref s) => Ok(s.clone()),
+            _ => malformed("string", sexp),
+        }
+    }
+}
+
+impl<T: Serializable> Serializable for Option<T> {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        match *self {
+            None => Ok(tagged("none", vec![])),
+            Some(ref x) => Ok(tagged("some", vec![x.serialize(s)?])),
+        }
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        match untag(sexp)? {
+            ("none", []) => Ok(None),
+            ("some", [x]) => Ok(Some(T::deserialize(x, d)?)),
+            _ => malformed("option", sexp),
+        }
+    }
+}
+
+impl<T: Serializable> Serializable for Vec<T> {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        Ok(Sexp::List(self.iter().map(|x| x.serialize(s)).collect::<Result<_, _>>()?))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        match *sexp {
+            Sexp::List(ref elts) => elts.iter().map(|elt| T::deserialize(elt, d)).collect(),
+            _ => malformed("list", sexp),
+        }
+    }
+}
+
+impl<T: Serializable> Serializable for Rc<T> {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> { (**self).serialize(s) }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        Ok(Rc::new(T::deserialize(sexp, d)?))
+    }
+}
+
+impl<T: Serializable> Serializable for Box<T> {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> { (**self).serialize(s) }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        Ok(Box::new(T::deserialize(sexp, d)?))
+    }
+}
+
+impl<A: Serializable, B: Serializable> Serializable for (A, B) {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        Ok(Sexp::List(vec![self.0.serialize(s)?, self.1.serialize(s)?]))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        match *sexp {
+            Sexp::List(ref elts) if elts.len() == 2 => {
+                Ok((A::deserialize(&elts[0], d)?, B::deserialize(&elts[1], d)?))
+            }
+            _ => malformed("pair", sexp),
+        }
+    }
+}
+
+impl<A: Serializable, B: Serializable, C: Serializable> Serializable for (A, B, C) {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        Ok(Sexp::List(vec![self.0.serialize(s)?, self.1.serialize(s)?, self.2.serialize(s)?]))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        match *sexp {
+            Sexp::List(ref elts) if elts.len() == 3 => Ok((
+                A::deserialize(&elts[0], d)?,
+                B::deserialize(&elts[1], d)?,
+                C::deserialize(&elts[2], d)?,
+            )),
+            _ => malformed("triple", sexp),
+        }
+    }
+}
+
+impl<K, V> Serializable for Assoc<K, V>
+where
+    K: Serializable + Eq + std::hash::Hash + Clone,
+    V: Serializable + Clone,
+{
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        let mut keys = self
+            .iter_pairs()
+            .map(|(k, v)| Ok((k.serialize(s)?, v)))
+            .collect::<Result<Vec<_>, String>>()?;
+        // `Assoc`s are unordered; pick an order so that the output
+        //  (including the numbering of fresh names in the values) is predictable:
+        keys.sort_by_cached_key(|(k, _)| format!("{}", k));
+        Ok(Sexp::List(
+            keys.into_iter()
+                .map(|(k, v)| Ok(Sexp::List(vec![k, v.serialize(s)?])))
+                .collect::<Result<_, String>>()?,
+        ))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        let mut res = Assoc::new();
+        for (k, v) in Vec::<(K, V)>::deserialize(sexp, d)? {
+            res = res.set(k, v);
+        }
+        Ok(res)
+    }
+}
+
+impl Serializable for Name {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        if self.is_global() {
+            return Ok(Sexp::Str(self.sp()));
+        }
+        let next_idx = s.fresh_names.len();
+        let idx = *s.fresh_names.entry(*self).or_insert(next_idx);
+        Ok(tagged("fresh", vec![Sexp::Str(self.orig_sp()), idx.serialize(s)?]))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        if let Sexp::Str(ref spelling) = *sexp {
+            return Ok(n(spelling));
+        }
+        match untag(sexp)? {
+            ("fresh", [Sexp::Str(ref orig), idx]) => {
+                let idx = usize::deserialize(idx, d)?;
+                Ok(*d.fresh_names.entry(idx).or_insert_with(|| Name::gensym(orig)))
+            }
+            _ => malformed("name", sexp),
+        }
+    }
+}
+
+impl Serializable for Ast {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        use crate::ast::Ast::*;
+        Ok(match *self {
+            Trivial => tagged("trivial", vec![]),
+            Atom(name) => tagged("atom", vec![name.serialize(s)?]),
+            VariableReference(name) => tagged("vr", vec![name.serialize(s)?]),
+            QuoteMore(ref body, pos) => {
+                tagged("quote-more", vec![pos.serialize(s)?, body.serialize(s)?])
+            }
+            QuoteLess(ref body, depth) => {
+                tagged("quote-less", vec![depth.serialize(s)?, body.serialize(s)?])
+            }
+            Node(ref f, ref parts, ref export) => tagged("node", vec![
+                s.form(f)?,
+                parts.serialize(s)?,
+                export.serialize(s)?,
+            ]),
+            IncompleteNode(ref parts) => tagged("incomplete-node", vec![parts.serialize(s)?]),
+            Shape(ref subs) => Sexp::List(
+                std::iter::once(Ok(Sexp::Atom("shape".to_owned())))
+                    .chain(subs.iter().map(|sub| sub.serialize(s)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ExtendEnv(ref body, ref beta) => {
+                tagged("extend-env", vec![beta.serialize(s)?, body.serialize(s)?])
+            }
+            ExtendEnvPhaseless(ref body, ref beta) => {
+                tagged("extend-env-phaseless", vec![beta.serialize(s)?, body.serialize(s)?])
+            }
+        })
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        use crate::ast::Ast::*;
+        Ok(match untag(sexp)? {
+            ("trivial", []) => Trivial,
+            ("atom", [name]) => Atom(Name::deserialize(name, d)?),
+            ("vr", [name]) => VariableReference(Name::deserialize(name, d)?),
+            ("quote-more", [pos, body]) => {
+                QuoteMore(Box::new(Ast::deserialize(body, d)?), bool::deserialize(pos, d)?)
+            }
+            ("quote-less", [depth, body]) => {
+                QuoteLess(Box::new(Ast::deserialize(body, d)?), u8::deserialize(depth, d)?)
+            }
+            ("node", [f, parts, export]) => {
+                let f = d.form(f)?;
+                Node(f, Serializable::deserialize(parts, d)?, ExportBeta::deserialize(export, d)?)
+            }
+            ("incomplete-node", [parts]) => IncompleteNode(Serializable::deserialize(parts, d)?),
+            ("shape", subs) => {
+                Shape(subs.iter().map(|sub| Ast::deserialize(sub, d)).collect::<Result<_, _>>()?)
+            }
+            ("extend-env", [beta, body]) => {
+                ExtendEnv(Box::new(Ast::deserialize(body, d)?), Beta::deserialize(beta, d)?)
+            }
+            ("extend-env-phaseless", [beta, body]) => ExtendEnvPhaseless(
+                Box::new(Ast::deserialize(body, d)?),
+                Beta::deserialize(beta, d)?,
+            ),
+            _ => return malformed("AST", sexp),
+        })
+    }
+}
+
+impl Serializable for Ty {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> { self.0.serialize(s) }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        Ok(Ty(Ast::deserialize(sexp, d)?))
+    }
+}
+
+impl Serializable for Beta {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        use crate::beta::Beta::*;
+        Ok(match *self {
+            Basic(name, ty) => tagged("basic", vec![name.serialize(s)?, ty.serialize(s)?]),
+            SameAs(name, ref expr) => {
+                tagged("same-as", vec![name.serialize(s)?, expr.serialize(s)?])
+            }
+            BoundButNotUsable(name) => tagged("bound-but-not-usable", vec![name.serialize(s)?]),
+            Underspecified(name) => tagged("underspecified", vec![name.serialize(s)?]),
+            Protected(name) => tagged("protected", vec![name.serialize(s)?]),
+            Shadow(ref lhs, ref rhs) => {
+                tagged("shadow", vec![lhs.serialize(s)?, rhs.serialize(s)?])
+            }
+            ShadowAll(ref sub, ref drivers) => {
+                tagged("shadow-all", vec![sub.serialize(s)?, drivers.serialize(s)?])
+            }
+            Nothing => tagged("nothing", vec![]),
+        })
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        use crate::beta::Beta::*;
+        Ok(match untag(sexp)? {
+            ("basic", [name, ty]) => Basic(Name::deserialize(name, d)?, Name::deserialize(ty, d)?),
+            ("same-as", [name, expr]) => {
+                SameAs(Name::deserialize(name, d)?, Serializable::deserialize(expr, d)?)
+            }
+            ("bound-but-not-usable", [name]) => BoundButNotUsable(Name::deserialize(name, d)?),
+            ("underspecified", [name]) => Underspecified(Name::deserialize(name, d)?),
+            ("protected", [name]) => Protected(Name::deserialize(name, d)?),
+            ("shadow", [lhs, rhs]) => {
+                Shadow(Serializable::deserialize(lhs, d)?, Serializable::deserialize(rhs, d)?)
+            }
+            ("shadow-all", [sub, drivers]) => {
+                let sub = Serializable::deserialize(sub, d)?;
+                ShadowAll(sub, Serializable::deserialize(drivers, d)?)
+            }
+            ("nothing", []) => Nothing,
+            _ => return malformed("beta", sexp),
+        })
+    }
+}
+
+impl Serializable for ExportBeta {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        use crate::beta::ExportBeta::*;
+        Ok(match *self {
+            Use(name) => tagged("use", vec![name.serialize(s)?]),
+            Shadow(ref lhs, ref rhs) => {
+                tagged("shadow", vec![lhs.serialize(s)?, rhs.serialize(s)?])
+            }
+            ShadowAll(ref sub, ref drivers) => {
+                tagged("shadow-all", vec![sub.serialize(s)?, drivers.serialize(s)?])
+            }
+            Nothing => tagged("nothing", vec![]),
+        })
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        use crate::beta::ExportBeta::*;
+        Ok(match untag(sexp)? {
+            ("use", [name]) => Use(Name::deserialize(name, d)?),
+            ("shadow", [lhs, rhs]) => {
+                Shadow(Serializable::deserialize(lhs, d)?, Serializable::deserialize(rhs, d)?)
+            }
+            ("shadow-all", [sub, drivers]) => {
+                let sub = Serializable::deserialize(sub, d)?;
+                ShadowAll(sub, Serializable::deserialize(drivers, d)?)
+            }
+            ("nothing", []) => Nothing,
+            _ => return malformed("export beta", sexp),
+        })
+    }
+}
+
+impl Serializable for FormPat {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        use crate::grammar::FormPat::*;
+        Ok(match *self {
+            Anyways(ref ast) => tagged("anyways", vec![ast.serialize(s)?]),
+            Impossible => tagged("impossible", vec![]),
+            Scan(Scanner(ref regex)) => tagged("scan", vec![Sexp::Str(regex.as_str().to_owned())]),
+            Common(ref body) => tagged("common", vec![body.serialize(s)?]),
+            Reserved(ref body, ref names) => {
+                tagged("reserved", vec![body.serialize(s)?, names.serialize(s)?])
+            }
+            Literal(ref body, name) => {
+                tagged("literal", vec![body.serialize(s)?, name.serialize(s)?])
+            }
+            VarRef(ref body) => tagged("varref", vec![body.serialize(s)?]),
+            Seq(ref bodies) => tagged("seq", vec![bodies.serialize(s)?]),
+            Star(ref body) => tagged("star", vec![body.serialize(s)?]),
+            Plus(ref body) => tagged("plus", vec![body.serialize(s)?]),
+            Alt(ref bodies) => tagged("alt", vec![bodies.serialize(s)?]),
+            Biased(ref lhs, ref rhs) => {
+                tagged("biased", vec![lhs.serialize(s)?, rhs.serialize(s)?])
+            }
+            Call(nt) => tagged("call", vec![nt.serialize(s)?]),
+            SynImport(_, _, _) => {
+                return Err("syntax extensions (which are Rust code) can't be serialized".to_owned())
+            }
+            Scope(ref f, ref export) => tagged("scope", vec![s.form(f)?, export.serialize(s)?]),
+            Named(name, ref body) => tagged("named", vec![name.serialize(s)?, body.serialize(s)?]),
+            Pick(ref body, name) => tagged("pick", vec![body.serialize(s)?, name.serialize(s)?]),
+            NameImport(ref body, ref beta) => {
+                tagged("import", vec![body.serialize(s)?, beta.serialize(s)?])
+            }
+            NameImportPhaseless(ref body, ref beta) => {
+                tagged("import-phaseless", vec![body.serialize(s)?, beta.serialize(s)?])
+            }
+            QuoteDeepen(ref body, pos) => {
+                tagged("quote-deepen", vec![body.serialize(s)?, pos.serialize(s)?])
+            }
+            QuoteEscape(ref body, depth) => {
+                tagged("quote-escape", vec![body.serialize(s)?, depth.serialize(s)?])
+            }
+        })
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        use crate::grammar::FormPat::*;
+        fn pat(sexp: &Sexp, d: &mut Deserializer) -> Result<Rc<FormPat>, String> {
+            Rc::<FormPat>::deserialize(sexp, d)
+        }
+        Ok(match untag(sexp)? {
+            ("anyways", [ast]) => Anyways(Ast::deserialize(ast, d)?),
+            ("impossible", []) => Impossible,
+            ("scan", [Sexp::Str(ref regex)]) => {
+                Scan(Scanner(regex::Regex::new(regex).map_err(|e| e.to_string())?))
+            }
+            ("common", [body]) => Common(pat(body, d)?),
+            ("reserved", [body, names]) => {
+                Reserved(pat(body, d)?, Serializable::deserialize(names, d)?)
+            }
+            ("literal", [body, name]) => Literal(pat(body, d)?, Name::deserialize(name, d)?),
+            ("varref", [body]) => VarRef(pat(body, d)?),
+            ("seq", [bodies]) => Seq(Serializable::deserialize(bodies, d)?),
+            ("star", [body]) => Star(pat(body, d)?),
+            ("plus", [body]) => Plus(pat(body, d)?),
+            ("alt", [bodies]) => Alt(Serializable::deserialize(bodies, d)?),
+            ("biased", [lhs, rhs]) => Biased(pat(lhs, d)?, pat(rhs, d)?),
+            ("call", [nt]) => Call(Name::deserialize(nt, d)?),
+            ("scope", [f, export]) => Scope(d.form(f)?, ExportBeta::deserialize(export, d)?),
+            ("named", [name, body]) => Named(Name::deserialize(name, d)?, pat(body, d)?),
+            ("pick", [body, name]) => Pick(pat(body, d)?, Name::deserialize(name, d)?),
+            ("import", [body, beta]) => NameImport(pat(body, d)?, Beta::deserialize(beta, d)?),
+            ("import-phaseless", [body, beta]) => {
+                NameImportPhaseless(pat(body, d)?, Beta::deserialize(beta, d)?)
+            }
+            ("quote-deepen", [body, pos]) => QuoteDeepen(pat(body, d)?, bool::deserialize(pos, d)?),
+            ("quote-escape", [body, depth]) => {
+                QuoteEscape(pat(body, d)?, u8::deserialize(depth, d)?)
+            }
+            _ => return malformed("grammar", sexp),
+        })
+    }
+}
+
+/// The names that `ast` refers to (or might; this doesn't bother to check what's bound).
+fn referenced_names(ast: &Ast) -> Vec<Name> {
+    let concat = |lhs: &Vec<Name>, rhs: &Vec<Name>| lhs.iter().chain(rhs.iter()).cloned().collect();
+    match *ast {
+        Ast::VariableReference(name) => vec![name],
+        Ast::Node(ref f, ref parts, _) => {
+            // A macro invocation's `macro_name` isn't evaluated:
+            let is_macro = invoked_macro_name(f).is_some();
+            parts
+                .named_map(&mut |part: &Name, sub: &Ast| {
+                    if is_macro && *part == n("macro_name") {
+                        vec![]
+                    } else {
+                        referenced_names(sub)
+                    }
+                })
+                .map_reduce(&|names: &Vec<Name>| names.clone(), &concat, vec![])
+        }
+        Ast::IncompleteNode(ref parts) => parts.map_reduce(&referenced_names, &concat, vec![]),
+        Ast::Shape(ref subs) => subs.iter().flat_map(referenced_names).collect(),
+        Ast::QuoteMore(ref body, _)
+        | Ast::QuoteLess(ref body, _)
+        | Ast::ExtendEnv(ref body, _)
+        | Ast::ExtendEnvPhaseless(ref body, _) => referenced_names(body),
+        Ast::Trivial | Ast::Atom(_) => vec![],
+    }
+}
+
+impl Serializable for Closure {
+    /// Only the part of the environment that the body might use is written out,
+    ///  and built-in functions are assumed to be available when it's read back in.
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        let mut env = vec![];
+        for name in referenced_names(&self.body) {
+            if self.params.contains(&name) || env.iter().any(|(bound, _)| *bound == name) {
+                continue;
+            }
+            if let Some(value) = self.env.find(&name) {
+                if !s.is_built_in(name, value) {
+                    env.push((name, value.clone()));
+                }
+            }
+        }
+        Ok(tagged("closure", vec![
+            self.params.serialize(s)?,
+            self.body.serialize(s)?,
+            env.serialize(s)?,
+        ]))
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        match untag(sexp)? {
+            ("closure", [params, body, env]) => {
+                let mut full_env = d
+                    .core_values
+                    .get_or_insert_with(crate::runtime::core_values::core_values)
+                    .clone();
+                for (name, value) in Vec::<(Name, Value)>::deserialize(env, d)? {
+                    full_env = full_env.set(name, value);
+                }
+                Ok(Closure {
+                    params: Serializable::deserialize(params, d)?,
+                    body: Ast::deserialize(body, d)?,
+                    env: full_env,
+                })
+            }
+            _ => malformed("closure", sexp),
+        }
+    }
+}
+
+impl Serializable for Value {
+    fn serialize(&self, s: &mut Serializer) -> Result<Sexp, String> {
+        Ok(match *self {
+            Value::Int(ref i) => tagged("int", vec![i.serialize(s)?]),
+            Value::Sequence(ref elts) => {
+                tagged("sequence", vec![elts.iter().cloned().collect::<Vec<_>>().serialize(s)?])
+            }
+            Value::Function(ref closure) => closure.serialize(s)?,
+            Value::BuiltInFunction(_) => {
+                return Err("built-in functions (which are Rust code) can't be serialized".into())
+            }
+            Value::AbstractSyntax(ref ast) => tagged("syntax", vec![ast.serialize(s)?]),
+            Value::Struct(ref parts) => tagged("struct", vec![parts.serialize(s)?]),
+            Value::Enum(choice, ref parts) => {
+                tagged("enum", vec![choice.serialize(s)?, parts.serialize(s)?])
+            }
+        })
+    }
+    fn deserialize(sexp: &Sexp, d: &mut Deserializer) -> Result<Self, String> {
+        Ok(match untag(sexp)? {
+            ("int", [i]) => Value::Int(BigInt::deserialize(i, d)?),
+            ("sequence", [elts]) => Value::Sequence(Vec::deserialize(elts, d)?.into()),
+            ("closure", _) => Value::Function(Rc::new(Closure::deserialize(sexp, d)?)),
+            ("syntax", [ast]) => Value::AbstractSyntax(Ast::deserialize(ast, d)?),
+            ("struct", [parts]) => Value::Struct(Serializable::deserialize(parts, d)?),
+            ("enum", [choice, parts]) => {
+                Value::Enum(Name::deserialize(choice, d)?, Serializable::deserialize(parts, d)?)
+            }
+            _ => return malformed("value", sexp),
+        })
+    }
+}
+
+#[test]
+fn sexp_parsing() {
+    let sexp = Sexp::parse(r#" (a "b c" (d "\"\\") () e🍅) "#).unwrap();
+    assert_eq!(
+        sexp,
+        Sexp::List(vec![
+            Sexp::Atom("a".to_owned()),
+            Sexp::Str("b c".to_owned()),
+            Sexp::List(vec![Sexp::Atom("d".to_owned()), Sexp::Str("\"\\".to_owned())]),
+            Sexp::List(vec![]),
+            Sexp::Atom("e🍅".to_owned()),
+        ])
+    );
+    assert_eq!(Sexp::parse(&format!("{}", sexp)), Ok(sexp));
+
+    assert!(Sexp::parse("(a b").is_err());
+    assert!(Sexp::parse("a)").is_err());
+    assert!(Sexp::parse(r#""abc"#).is_err());
+}
+
+#[test]
+fn serialize_grammar() {
+    let x = n("x");
+    let fresh_x = x.freshen();
+    let pat = form_pat!([(lit "let"), (named "x", (import ["x" = "y"], (call "Expr"))),
+                         (named "y", (scope crate::core_forms::find("Expr", "apply"))),
+                         (alt (scan r"\s*(\d+)"), (anyways (, Ast::VariableReference(fresh_x))))]);
+
+    let sexp = to_sexp(&pat).unwrap();
+    // Round-tripping through text doesn't matter:
+    assert_eq!(Sexp::parse(&format!("{}", sexp)), Ok(sexp.clone()));
+
+    // The fresh name gets replaced by another fresh name, so `pat` doesn't come back exactly:
+    let round_tripped: FormPat = from_sexp(&sexp).unwrap();
+    assert_ne!(round_tripped, pat);
+    assert_eq!(to_sexp(&round_tripped), Ok(sexp));
+
+    // Syntax extensions are Rust code:
+    assert!(to_sexp(&crate::core_forms::find("Expr", "extend_syntax").grammar).is_err());
+}
+
+#[test]
+fn serialize_names() {
+    let x = n("x");
+    let fresh_x = x.freshen();
+    let another_fresh_x = x.freshen();
+    let names = vec![x, fresh_x, another_fresh_x, fresh_x];
+
+    let round_tripped: Vec<Name> = from_sexp(&to_sexp(&names).unwrap()).unwrap();
+    assert_eq!(round_tripped[0], x);
+    assert_eq!(round_tripped[1].orig_sp(), "x");
+    assert_ne!(round_tripped[1], fresh_x);
+    assert_ne!(round_tripped[1], x);
+    assert_ne!(round_tripped[1], round_tripped[2]);
+    assert_eq!(round_tripped[1], round_tripped[3]);
+}
+
+#[test]
+fn json_encoding() {
+    let sexp = Sexp::parse("(node \"x\ty\" (\"\\\"🍅\\\"\" ()) nothing)").unwrap();
+    assert_eq!(
+        sexp.to_json(),
+        r#"["node", {"str": "x\ty"}, [{"str": "\"🍅\""}, []], "nothing"]"#
+    );
+    assert_eq!(Sexp::parse_json(&sexp.to_json()), Ok(sexp));
+
+    // Other JSON writers might use different escapes and spacing:
+    assert_eq!(
+        Sexp::parse_json(r#" [ "a" ,{"str":"é🍅"} ] "#),
+        Ok(Sexp::List(vec![Sexp::Atom("a".to_owned()), Sexp::Str("é🍅".to_owned())]))
+    );
+
+    assert!(Sexp::parse_json(r#"["a", "b""#).is_err());
+    assert!(Sexp::parse_json(r#"{"string": "a"}"#).is_err());
+    assert!(Sexp::parse_json("7").is_err());
+}
+
+#[test]
+fn serialize_round_trip() {
+    use crate::runtime::core_values;
+
+    let parse = |program: &str| {
+        crate::grammar::parse(
+            &crate::core_forms::outermost_form(),
+            &crate::core_forms::get_core_forms(),
+            core_values::get_core_envs(),
+            program,
+        )
+        .unwrap()
+    };
+    let round_trip = |sexp: &Sexp| -> Sexp {
+        // Through text and JSON, as well as through the data structure:
+        let sexp = Sexp::parse(&format!("{}", sexp)).unwrap();
+        let sexp = Sexp::parse_json(&sexp.to_json()).unwrap();
+        to_sexp(&from_sexp::<Ast>(&sexp).unwrap()).unwrap()
+    };
+
+    // Core forms keep their identity, and binding information is preserved:
+    let program = parse(
+        "let_type Pt = *[x : Int  y : Int]* in
+         (.[ p : Pt . match p { *[x : a  y : b]* => (plus a b) } ]. *[x : one  y : two]*)",
+    );
+    let sexp = to_sexp(&program).unwrap();
+    assert_eq!(from_sexp::<Ast>(&sexp), Ok(program.clone()));
+    assert_eq!(round_trip(&sexp), sexp);
+    assert_eq!(
+        crate::runtime::eval::eval(&from_sexp(&sexp).unwrap(), core_values::core_values()),
+        Ok(val!(i 3))
+    );
+
+    // Quotation (the forms for unquotation are made during parsing, so they aren't `==`):
+    let quoted = parse("'[Expr | .[ a : Int . '[Expr | (plus a ,,[ one ],,)]' ]. ]'");
+    let sexp = to_sexp(&quoted).unwrap();
+    assert!(format!("{}", sexp).contains("(unquote \"Expr\" true 2)"));
+    assert_eq!(round_trip(&sexp), sexp);
+
+    // Expansion produces fresh names, which stay distinct from each other and from globals:
+    let expanded = crate::expand::expand(&parse(
+        "extend_syntax
+             Expr ::=also forall T . '{ [
+                 lit ,{ DefaultToken }, = 'twice'  e := ( ,{ Expr<T> }, )
+             ] }' twice -> .{ '[Expr | (.[ x : Int . (plus x x) ]. ,[e], ) ]' }. ;
+         in .[ x : Int . twice (plus x one) ].",
+    ))
+    .unwrap();
+    let sexp = to_sexp(&expanded).unwrap();
+    assert!(format!("{}", sexp).contains("(fresh \"x\" 0)"));
+    assert_eq!(round_trip(&sexp), sexp);
+
+    // Types:
+    let ty = crate::ty::synth_type(&program, core_values::core_types()).unwrap();
+    assert_eq!(from_sexp::<Ty>(&to_sexp(&ty).unwrap()), Ok(ty));
+
+    // First-order values:
+    let value = Value::Struct(
+        Assoc::new()
+            .set(n("a"), val!(enum "Some", (i 7)))
+            .set(n("b"), val!(seq (i 1) (i -2)))
+            .set(n("c"), Value::AbstractSyntax(program)),
+    );
+    assert_eq!(from_sexp::<Value>(&to_sexp(&value).unwrap()), Ok(value));
+    assert!(to_sexp(&core_values::core_values().find_or_panic(&n("plus")).clone()).is_err());
+}
@@ -12,12 +12,13 @@ use crate::{
         walk, LazyWalkReses,
         WalkRule::{self, *},
     },
-    form::Form,
+    form::{Both, Form, Positive},
+    grammar::FormPat,
     name::*,
-    util::assoc::Assoc,
+    util::{assoc::Assoc, mbe::EnvMBE},
     walk_mode::WalkMode,
 };
-use std::{fmt, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc};
 
 #[derive(PartialEq, Clone)]
 pub struct Ty(pub Ast);
@@ -95,7 +96,10 @@ impl WalkMode for SynthTy {
     ) -> Result<Ty, TypeError>
     {
         match parts.env.find(&name) {
-            None => Err(crate::util::err::sp(TyErr::UnboundName(name), parts.this_ast.clone())),
+            None => Err(crate::util::err::sp(
+                TyErr::UnboundName(name, did_you_mean(name, parts.env.iter_keys().cloned())),
+                parts.this_ast.clone(),
+            )),
             // If name is protected, stop:
             Some(ty) if &Ty(VariableReference(name)) == ty => Ok(ty.clone()),
             Some(ty) => synth_type(&ty.concrete(), parts.env.clone()),
@@ -104,6 +108,22 @@ impl WalkMode for SynthTy {
 
     // Simply protect the name; don't try to unify it.
     fn underspecified(name: Name) -> Ty { Ty(VariableReference(name)) }
+
+    // If we're collecting errors, record this one and pretend the term has the error type.
+    fn recover(res: Result<Ty, TypeError>) -> Result<Ty, TypeError> {
+        match res {
+            // Errors caused by other errors are just noise, so don't record those:
+            Err(e) if e.body.mentions_error_ty() && collecting_errors() => Ok(error_ty()),
+            Err(e) => {
+                if record_error(e.clone()) {
+                    Ok(error_ty())
+                } else {
+                    Err(e)
+                }
+            }
+            ok => ok,
+        }
+    }
 }
 
 impl WalkMode for UnpackTy {
@@ -131,13 +151,199 @@ pub fn synth_type_top(expr: &Ast) -> TypeResult {
 }
 
 pub fn synth_type(expr: &Ast, env: Assoc<Name, Ty>) -> TypeResult {
-    walk::<SynthTy>(expr, &LazyWalkReses::new_wrapper(env))
+    if collecting_errors() {
+        return walk::<SynthTy>(expr, &LazyWalkReses::new_wrapper(env));
+    }
+    // Report holes after the whole expression has been seen, so they know what they need to be:
+    let outer = pending_holes.with(|holes| holes.replace(Some(vec![])));
+    let res = walk::<SynthTy>(expr, &LazyWalkReses::new_wrapper(env.clone()));
+    let holes = pending_holes.with(|holes| holes.replace(outer)).unwrap_or_default();
+
+    match holes.into_iter().next() {
+        Some(mut hole) => {
+            resolve_hole_type(&mut hole, &env);
+            Err(hole)
+        }
+        None => res,
+    }
 }
 
 pub fn neg_synth_type(pat: &Ast, env: Assoc<Name, Ty>) -> Result<Assoc<Name, Ty>, TypeError> {
     walk::<UnpackTy>(pat, &LazyWalkReses::new_wrapper(env))
 }
 
+thread_local! {
+    /// When `Some`, type errors are recorded here instead of ending type synthesis.
+    static collected_errors: RefCell<Option<Vec<TypeError>>> = RefCell::new(None);
+    /// When `Some` (and we're not collecting errors), holes are recorded here instead.
+    static pending_holes: RefCell<Option<Vec<TypeError>>> = const { RefCell::new(None) };
+
+    /// The type of a term that failed to typecheck.
+    /// It's compatible with every type, so one mistake doesn't cause a pile of others.
+    pub static error_type_form: Rc<Form> = Rc::new(Form {
+        name: n("<error>"),
+        grammar: Rc::new(form_pat!((lit "<error>"))),
+        type_compare: Both(LiteralLike, LiteralLike), // `Subtype::pre_match` accepts it
+        synth_type: Positive(LiteralLike),
+        eval: Both(NotWalked, NotWalked),
+        quasiquote: Both(LiteralLike, LiteralLike),
+        macro_definition: None,
+    })
+}
+
+/// Runs `f` with error recovery turned off, for walks whose results bind names.
+/// (A recovered error would quietly drop the names that the failed part was meant to bind.)
+pub fn without_recovery<T>(f: impl FnOnce() -> T) -> T {
+    let outer = collected_errors.with(|errs| errs.replace(None));
+    let res = f();
+    collected_errors.with(|errs| errs.replace(outer));
+    res
+}
+
+pub fn error_ty() -> Ty {
+    Ty(Node(error_type_form.with(|f| f.clone()), EnvMBE::new(), crate::beta::ExportBeta::Nothing))
+}
+
+pub fn is_error_ty(a: &Ast) -> bool {
+    match *a {
+        Node(ref f, _, _) => error_type_form.with(|e_f| f == e_f),
+        _ => false,
+    }
+}
+
+fn contains_error_ty(a: &Ast) -> bool {
+    match *a {
+        Node(_, ref parts, _) => {
+            is_error_ty(a) || parts.map_reduce(&contains_error_ty, &|l, r| *l || *r, false)
+        }
+        ExtendEnv(ref body, _)
+        | ExtendEnvPhaseless(ref body, _)
+        | QuoteMore(ref body, _)
+        | QuoteLess(ref body, _) => contains_error_ty(body),
+        Shape(ref v) => v.iter().any(contains_error_ty),
+        _ => false,
+    }
+}
+
+fn collecting_errors() -> bool { collected_errors.with(|errs| errs.borrow().is_some()) }
+
+/// How many errors have been recorded so far (always 0 if we're not collecting errors).
+pub fn recorded_error_count() -> usize {
+    collected_errors.with(|errs| errs.borrow().as_ref().map_or(0, |errs| errs.len()))
+}
+
+/// Record `err` without stopping type synthesis, if we're collecting errors.
+/// Returns whether it was recorded (if not, the caller should fail with it).
+pub fn record_error(err: TypeError) -> bool {
+    collected_errors.with(|errs| match *errs.borrow_mut() {
+        Some(ref mut errs) => {
+            if !errs.contains(&err) {
+                errs.push(err);
+            }
+            true
+        }
+        None => false,
+    })
+}
+
+/// Like `record_error`, but for a `Hole`, which can be recorded even if we're not collecting.
+pub fn record_hole(err: TypeError) -> bool {
+    record_error(err.clone())
+        || pending_holes.with(|holes| match *holes.borrow_mut() {
+            Some(ref mut holes) => {
+                holes.push(err);
+                true
+            }
+            None => false,
+        })
+}
+
+/// Now that the whole program has been seen, we know what the hole needed to be.
+fn resolve_hole_type(err: &mut TypeError, env: &Assoc<Name, Ty>) {
+    if let TyErr::Hole(_, ref mut expected, _) = err.body {
+        *expected = expected
+            .take()
+            .and_then(|ty| crate::ty_compare::canonicalize(&ty, env.clone()).ok())
+            .filter(|ty| !contains_error_ty(&ty.0));
+    }
+}
+
+/// Like `synth_type`, but keeps going after an error, and returns every error found,
+///  ordered by their position in `expr`.
+pub fn synth_type_all(expr: &Ast, env: Assoc<Name, Ty>) -> Result<Ty, Vec<TypeError>> {
+    let outer = collected_errors.with(|errs| errs.replace(Some(vec![])));
+    let res = synth_type(expr, env.clone());
+    let mut errs = collected_errors.with(|errs| errs.replace(outer)).unwrap_or_default();
+
+    for err in &mut errs {
+        resolve_hole_type(err, &env);
+    }
+
+    match res {
+        Ok(ty) if errs.is_empty() => Ok(ty),
+        res => {
+            if let Err(e) = res {
+                errs.push(e); // It was something other than a `Node` that failed
+            }
+            errs.sort_by_key(|e| source_position(expr, &e.loc).unwrap_or(usize::MAX));
+            Err(errs)
+        }
+    }
+}
+
+/// Where `target` is in `a`, counting subterms in the order they were written.
+/// (We don't have spans yet, so this is how errors get ordered.)
+fn source_position(a: &Ast, target: &Ast) -> Option<usize> {
+    fn in_term(a: &Ast, target: &Ast, count: &mut usize) -> Option<usize> {
+        if a == target {
+            return Some(*count);
+        }
+        *count += 1;
+        match *a {
+            Node(ref f, ref parts, _) => in_grammar(&f.grammar, parts, target, count),
+            ExtendEnv(ref body, _)
+            | ExtendEnvPhaseless(ref body, _)
+            | QuoteMore(ref body, _)
+            | QuoteLess(ref body, _) => in_term(body, target, count),
+            Shape(ref v) => v.iter().find_map(|sub| in_term(sub, target, count)),
+            _ => None,
+        }
+    }
+    // Follow the grammar, since the parts of a `Node` aren't stored in order:
+    fn in_grammar(
+        pat: &FormPat,
+        parts: &EnvMBE<Ast>,
+        target: &Ast,
+        count: &mut usize,
+    ) -> Option<usize>
+    {
+        use crate::grammar::FormPat::*;
+        match *pat {
+            Named(name, _) => parts.get_leaf(name).and_then(|sub| in_term(sub, target, count)),
+            Star(ref body) | Plus(ref body) => {
+                let names: Vec<Name> = body.binders().into_iter().map(|(n, _)| n).collect();
+                parts.march_all(&names).iter().find_map(|rep| in_grammar(body, rep, target, count))
+            }
+            Seq(ref bodies) | Alt(ref bodies) => {
+                bodies.iter().find_map(|body| in_grammar(body, parts, target, count))
+            }
+            Biased(ref lhs, ref rhs) => in_grammar(lhs, parts, target, count)
+                .or_else(|| in_grammar(rhs, parts, target, count)),
+            Common(ref body)
+            | Reserved(ref body, _)
+            | Literal(ref body, _)
+            | VarRef(ref body)
+            | SynImport(ref body, _, _)
+            | NameImport(ref body, _)
+            | NameImportPhaseless(ref body, _)
+            | QuoteDeepen(ref body, _)
+            | QuoteEscape(ref body, _) => in_grammar(body, parts, target, count),
+            _ => None,
+        }
+    }
+    in_term(a, target, &mut 0)
+}
+
 custom_derive! {
     #[derive(Reifiable, Clone, PartialEq)]
     pub enum TyErr {
@@ -146,14 +352,40 @@ custom_derive! {
         NtInterpMismatch(Name, Name),
         NonexistentEnumArm(Name, Ty),
         NonexistentStructField(Name, Ty),
-        NonExhaustiveMatch(Ty),
+        NonExhaustiveMatch(Ty, String), // scrutinee type, example of an unmatched value
         UnableToDestructure(Ty, Name),
-        UnboundName(Name),
-        // TODO: the reification macros can't handle empty `enum` cases. Fix that!
-        AnnotationRequired(()),
-        NeedsDriver(()),
-        // TODO: replace all uses of `Other` with more specific errors:
-        Other(String)
+        UnboundName(Name, Option<Name>), // the name, and a similar name that is defined
+        TypeEscapes(Name, Ty),
+        InconsistentBinding(Name), // bound in some alternatives of an or-pattern, but not all
+        AnnotationRequired(Ast), // the negative syntax that needs the annotation
+        NeedsDriver,
+        WrongNumberOfTypeArgs(Ty, usize, usize), // the type function, got, expected
+        NotATypeFunction(Ty),
+        Hole(Name, Option<Ty>, Vec<(Name, Ty)>), // expected type (if known), local variables
+        InMacro(Name, Box<TyErr>), // an error in the invocation of the named macro
+        MacroNameCollision(Name),
+        NonBinderCapture(Name), // the nonterminal that a macro tried to `capture`
+        ImportFailed(String, String) // the path, and what went wrong
+    }
+}
+
+impl TyErr {
+    /// Is this error (probably) just a consequence of some earlier error?
+    fn mentions_error_ty(&self) -> bool {
+        use self::TyErr::*;
+        match *self {
+            Mismatch(ref got, ref exp) => contains_error_ty(&got.0) || contains_error_ty(&exp.0),
+            LengthMismatch(ref got, _) => got.iter().any(|t| contains_error_ty(&t.0)),
+            NonexistentEnumArm(_, ref ty)
+            | NonexistentStructField(_, ref ty)
+            | NonExhaustiveMatch(ref ty, _)
+            | UnableToDestructure(ref ty, _)
+            | TypeEscapes(_, ref ty)
+            | WrongNumberOfTypeArgs(ref ty, _, _)
+            | NotATypeFunction(ref ty) => contains_error_ty(&ty.0),
+            InMacro(_, ref err) => err.mentions_error_ty(),
+            _ => false,
+        }
     }
 }
 
@@ -162,7 +394,12 @@ impl fmt::Display for TyErr {
         use self::TyErr::*;
         match *self {
             Mismatch(ref got, ref exp) => {
-                write!(f, "[Mismatch] got:\n  `{}`\n   expected:\n  `{}`\n", got, exp)
+                let (path, got_part, exp_part) = smallest_difference(&got.0, &exp.0);
+                write!(f, "[Mismatch] got:\n  `{}`\n   expected:\n  `{}`\n", got_part, exp_part)?;
+                if !path.is_empty() {
+                    write!(f, "   (at {} of the expected type)\n", path.join(" → "))?;
+                }
+                Ok(())
             }
             LengthMismatch(ref got, exp_len) => {
                 write!(f, "[LengthMismatch] got:\n  ")?;
@@ -176,32 +413,195 @@ impl fmt::Display for TyErr {
                 "[NtInterpMismatch] expected the nonterminal `{}`, but `{}` was interpolated",
                 exp, got
             ),
-            NonexistentEnumArm(got_name, ref ty) => write!(
-                f,
-                "[NonexistentEnumArm] the enum `{}` doesn't have an arm named `{}`",
-                ty, got_name
-            ),
-            NonexistentStructField(got_name, ref ty) => write!(
+            NonexistentEnumArm(got_name, ref ty) => {
+                write!(
+                    f,
+                    "[NonexistentEnumArm] the enum `{}` doesn't have an arm named `{}`",
+                    ty, got_name
+                )?;
+                write_suggestion(f, did_you_mean(got_name, part_names(ty, n("name")).into_iter()))
+            }
+            NonexistentStructField(got_name, ref ty) => {
+                write!(
+                    f,
+                    "[NonexistentStructField] the struct `{}` doesn't have a field named `{}`",
+                    ty, got_name
+                )?;
+                write_suggestion(f, did_you_mean(got_name, part_names(ty, n("component_name")).into_iter()))
+            }
+            NonExhaustiveMatch(ref ty, ref missing) => write!(
                 f,
-                "[NonexistentStructField] the struct `{}` doesn't have a field named `{}`",
-                ty, got_name
+                "[NonExhaustiveMatch] non-exhaustive match of `{}`: `{}` not covered",
+                ty, missing
             ),
-            NonExhaustiveMatch(ref ty) => {
-                write!(f, "[NonExhaustiveMatch] non-exhaustive match of `{}`", ty)
-            }
             UnableToDestructure(ref ty, expected_name) => {
                 write!(f, "[UnableToDestructure] expected a `{}` type, got `{}`", expected_name, ty)
             }
-            UnboundName(name) => write!(f, "[UnboundName] `{}` is not defined", name),
-            AnnotationRequired(()) => write!(
+            UnboundName(name, suggestion) => {
+                write!(f, "[UnboundName] `{}` is not defined", name)?;
+                write_suggestion(f, suggestion)
+            }
+            TypeEscapes(name, ref ty) => write!(
+                f,
+                "[TypeEscapes] the abstract type `{}` escapes its `unpack` in `{}`",
+                name, ty
+            ),
+            InconsistentBinding(name) => write!(
+                f,
+                "[InconsistentBinding] `{}` must be bound in every alternative of the or-pattern",
+                name
+            ),
+            AnnotationRequired(ref pat) => write!(
+                f,
+                "[AnnotationRequired] `{}` is negative syntax (e.g. a pattern) \
+                 inside positive syntax (e.g. an expression), \
+                 so its type can't be inferred; it requires a type annotation",
+                pat
+            ),
+            NeedsDriver => write!(f, "[NeedsDriver] Repetition needs a driver"),
+            WrongNumberOfTypeArgs(ref ty, got, exp) => write!(
+                f,
+                "[WrongNumberOfTypeArgs] `{}` takes {} type arguments, but got {}",
+                ty, exp, got
+            ),
+            NotATypeFunction(ref ty) => write!(
+                f,
+                "[NotATypeFunction] `{}` is not a `forall` type, so it can't take type arguments",
+                ty
+            ),
+            Hole(name, ref expected, ref locals) => {
+                match *expected {
+                    Some(ref ty) => write!(f, "[Hole] `{}` needs to have the type `{}`", name, ty)?,
+                    None => write!(f, "[Hole] `{}` can have any type", name)?,
+                }
+                if !locals.is_empty() {
+                    write!(f, "\n   in scope:")?;
+                    for (local, ty) in locals {
+                        write!(f, "\n     {} : {}", local, ty)?;
+                    }
+                }
+                Ok(())
+            }
+            InMacro(name, ref err) => write!(f, "[InMacro] in this invocation of `{}`: {}", name, err),
+            MacroNameCollision(name) => write!(
                 f,
-                "[AnnotationRequired] Negative syntax (e.g. a pattern) inside positive syntax \
-                 (e.g. an expression) requires a type annotation."
+                "[MacroNameCollision] more than one macro named `{}` is defined here",
+                name
             ),
-            NeedsDriver(()) => write!(f, "[NeedsDriver] Repetition needs a driver"),
-            Other(ref s) => write!(f, "[Other] {}", s),
+            NonBinderCapture(nt) => write!(
+                f,
+                "[NonBinderCapture] only an `Atom` or a `Pat` can be captured, not a `{}`",
+                nt
+            ),
+            ImportFailed(ref path, ref why) => {
+                write!(f, "[ImportFailed] couldn't import `{}`: {}", path, why)
+            }
+        }
+    }
+}
+
+fn write_suggestion(f: &mut fmt::Formatter, suggestion: Option<Name>) -> fmt::Result {
+    match suggestion {
+        Some(name) => write!(f, "; did you mean `{}`?", name),
+        None => Ok(()),
+    }
+}
+
+/// The names (e.g. enum arms or struct fields) under `part` in `ty`, if it's a `Node`.
+fn part_names(ty: &Ty, part: Name) -> Vec<Name> {
+    match ty.0 {
+        Node(_, ref parts, _) => parts
+            .get_rep_leaf(part)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|a| if let Atom(name) = *a { Some(name) } else { None })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Find the smallest subterm where `got` and `exp` differ.
+/// Returns the path to it (as `form.part` steps), and the two differing subterms.
+fn smallest_difference(got: &Ast, exp: &Ast) -> (Vec<String>, Ty, Ty) {
+    match (got, exp) {
+        (&Node(ref f, ref got_parts, _), &Node(ref f_exp, ref exp_parts, _))
+            if f == f_exp && got_parts.can_map_with(exp_parts) =>
+        {
+            let differing: Vec<(Name, Ast, Ast)> = got_parts
+                .named_map_with(exp_parts, &|part, g, e| (*part, g.clone(), e.clone()))
+                .map_reduce(
+                    &|diff| if diff.1 != diff.2 { vec![diff.clone()] } else { vec![] },
+                    &|lhs, rhs| [&lhs[..], &rhs[..]].concat(),
+                    vec![],
+                );
+            // If more than one part differs, this is the smallest difference:
+            if let [(part, ref got_part, ref exp_part)] = differing[..] {
+                let (mut path, got_sub, exp_sub) = smallest_difference(got_part, exp_part);
+                let labels: Vec<String> = atoms_alongside(exp_parts, part, exp_part)
+                    .iter()
+                    .map(|a| format!("[{}]", a))
+                    .collect();
+                path.insert(0, format!("{}.{}{}", f.name, part, labels.concat()));
+                return (path, got_sub, exp_sub);
+            }
         }
+        (&ExtendEnv(ref got_body, ref got_beta), &ExtendEnv(ref exp_body, ref exp_beta))
+            if got_beta == exp_beta =>
+        {
+            return smallest_difference(got_body, exp_body);
+        }
+        _ => {}
+    }
+    (vec![], Ty(got.clone()), Ty(exp.clone()))
+}
+
+/// Atoms (e.g. the names of fields or arms) in the same repetition as `target`, to identify it.
+fn atoms_alongside(parts: &EnvMBE<Ast>, part: Name, target: &Ast) -> Vec<Name> {
+    for marched in parts.march_all(&[part]) {
+        let found = if marched.get_leaf(part) == Some(target) {
+            marched.map_reduce(
+                &|a| match *a {
+                    Atom(name) if a != target => vec![name],
+                    _ => vec![],
+                },
+                &|lhs, rhs| [&lhs[..], &rhs[..]].concat(),
+                vec![],
+            )
+        } else {
+            atoms_alongside(&marched, part, target)
+        };
+        if !found.is_empty() {
+            return found;
+        }
+    }
+    vec![]
+}
+
+/// Levenshtein distance, for suggesting corrections to misspelled names.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=rhs.len()).collect();
+    for (i, l_char) in lhs.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, r_char) in rhs.iter().enumerate() {
+            let substitution = prev_row[j] + if l_char == *r_char { 0 } else { 1 };
+            row.push(std::cmp::min(substitution, std::cmp::min(prev_row[j + 1], row[j]) + 1));
+        }
+        prev_row = row;
     }
+    prev_row[rhs.len()]
+}
+
+/// The closest (by spelling) of `candidates` to `name`, if any is close enough to be a typo.
+pub fn did_you_mean<I: Iterator<Item = Name>>(name: Name, candidates: I) -> Option<Name> {
+    let spelling = name.orig_sp();
+    let max_distance = std::cmp::max(1, spelling.chars().count() / 3);
+    candidates
+        .filter(|c| c.orig_sp() != negative_ret_val().orig_sp()) // not something the user can write
+        .map(|c| (edit_distance(&spelling, &c.orig_sp()), c.orig_sp()))
+        .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+        .min()
+        .map(|(_, closest)| n(&closest))
 }
 
 // temporary, until we get rid of `Debug` as the way of outputting errors
@@ -218,6 +618,43 @@ impl fmt::Debug for TyErr {
 
 pub type TypeError = crate::util::err::Spanned<TyErr>;
 
+/// Things that typecheck, but are probably mistakes.
+#[derive(Clone, PartialEq)]
+pub enum TyWarning {
+    RedundantArm,
+}
+
+impl fmt::Display for TyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::TyWarning::*;
+        match *self {
+            RedundantArm => {
+                write!(f, "[RedundantArm] this arm is unreachable; earlier arms match everything")
+            }
+        }
+    }
+}
+
+pub type TypeWarning = crate::util::err::Spanned<TyWarning>;
+
+thread_local! {
+    // Warnings don't stop typechecking, so they get collected on the side.
+    // (Typechecking can visit the same code more than once, so there may be duplicates.)
+    pub static ty_warnings: RefCell<Vec<TypeWarning>> = RefCell::new(vec![]);
+}
+
+pub fn warn(w: TyWarning, loc: Ast) {
+    ty_warnings.with(|ws| {
+        let w = crate::util::err::sp(w, loc);
+        if !ws.borrow().contains(&w) {
+            ws.borrow_mut().push(w)
+        }
+    })
+}
+
+/// Get (and forget) all the warnings produced since the last call.
+pub fn take_warnings() -> Vec<TypeWarning> { ty_warnings.with(|ws| ws.replace(vec![])) }
+
 pub type TypeResult = Result<Ty, TypeError>;
 
 pub fn expect_type(expected: &Ty, got: &Ty, loc: &Ast) -> Result<(), TypeError> {
@@ -325,3 +762,33 @@ fn type_specialization() {
     // TODO: test that ∀ X. ∀ Y. [ X → Y ] is a (sortof) sensible type (for transmogrify)
     //        and that ∀ X. [ X → ∀ Y . Y ] is ridiculously permissive
 }
+
+#[test]
+fn type_error_hints() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("five", "five"), 0);
+    let names = vec![n("five"), n("four"), n("fib"), n("width"), n("height")];
+    assert_eq!(did_you_mean(n("fiv"), names.clone().into_iter()), Some(n("fib")));
+    assert_eq!(did_you_mean(n("heigth"), names.clone().into_iter()), Some(n("height")));
+    assert_eq!(did_you_mean(n("x"), names.into_iter()), None);
+
+    let int_ty = ty!({ "Type" "Int" : });
+    let float_ty = ty!({ "Type" "Float" : });
+    let record = |last: &Ty| {
+        ty!({ "Type" "struct" :
+            "component_name" => [@"c" "a", "b"],
+            "component" => [@"c" (, int_ty.concrete()),
+                { "Type" "fn" : "param" => [(, int_ty.concrete())], "ret" => (, last.concrete())}]})
+    };
+    let (path, got, exp) = smallest_difference(&record(&int_ty).0, &record(&float_ty).0);
+    assert_eq!(path, vec!["struct.component[b]".to_string(), "fn.ret".to_string()]);
+    assert_eq!((got, exp), (int_ty.clone(), float_ty.clone()));
+
+    // If more than one part differs, there's no smaller difference:
+    let (path, _, _) = smallest_difference(
+        &ty!({ "Type" "fn" : "param" => [(, int_ty.concrete())], "ret" => (, int_ty.concrete())}).0,
+        &ty!({ "Type" "fn" : "param" => [(, float_ty.concrete())], "ret" => (, float_ty.concrete())})
+            .0,
+    );
+    assert_eq!(path, Vec::<String>::new());
+}
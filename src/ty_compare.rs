@@ -222,14 +222,15 @@ thread_local! {
                 unification.with(|unif| {
                     let unif = unif.borrow();
                     // TODO: don't use the id in an error message; it's user-hostile:
-                    let clo = unif.get(&id).ok_or(TyErr::UnboundName(id))?;
+                    let clo = unif.get(&id).ok_or(TyErr::UnboundName(id, None))?;
                     canonicalize(&clo.it, clo.env.clone())
                 })
             }),
             NotWalked),
         synth_type:   Both(NotWalked, NotWalked),
         eval:         Both(NotWalked, NotWalked),
-        quasiquote:   Both(NotWalked, NotWalked)
+        quasiquote:   Both(NotWalked, NotWalked),
+        macro_definition: None,
     })
 }
 
@@ -451,6 +452,9 @@ impl crate::walk_mode::NegativeWalkMode for Subtype {
                     unif.borrow_mut().insert(r, lhs);
                     None
                 }
+                // The error type is compatible with everything (the error's already been reported),
+                //  but underdetermined types still need to be determined as it, above:
+                _ if crate::ty::is_error_ty(&lhs.it.0) || crate::ty::is_error_ty(&rhs.it.0) => None,
                 // They are (potentially) different.
                 _ => Some((lhs, rhs)),
             }
@@ -473,7 +477,10 @@ pub fn is_subtype(
     parts: &LazyWalkReses<crate::ty::SynthTy>,
 ) -> Result<Assoc<Name, Ty>, TyErr>
 {
-    walk::<Subtype>(&sup.concrete(), &parts.switch_mode::<Subtype>().with_context(sub.clone()))
+    // A mismatch in here is the caller's to report (and recover from), not an expression's:
+    crate::ty::without_recovery(|| {
+        walk::<Subtype>(&sup.concrete(), &parts.switch_mode::<Subtype>().with_context(sub.clone()))
+    })
 }
 
 // `sub` must be a subtype of `sup`. (Note that `sub` becomes the context element!)
@@ -483,7 +490,7 @@ pub fn must_subtype(sub: &Ty, sup: &Ty, env: Assoc<Name, Ty>) -> Result<Assoc<Na
     // TODO: they might need different environments?
     let lwr_env = &LazyWalkReses::<Subtype>::new_wrapper(env).with_context(sub.clone());
 
-    walk::<Subtype>(&sup.concrete(), lwr_env)
+    crate::ty::without_recovery(|| walk::<Subtype>(&sup.concrete(), lwr_env))
 }
 
 // TODO: I think we need to route some other things (especially in macros.rs) through this...
@@ -877,3 +884,42 @@ fn basic_resolve() {
                           "type_rator" => (vr "List"), "arg" => [(,ud0.clone())]} ]]})})
     );
 }
+
+#[test]
+fn mutually_recursive_subtyping() {
+    // Two copies of the same pair of mutually recursive types, with different names:
+    fn group(ex: &str, st: &str) -> (Ty, Ty) {
+        let ex_body = ty!({"Type" "enum" :
+            "name" => [@"c" "Lit", "Block"],
+            "component" => [@"c" [{"Type" "Int" :}], [(vr st)]]});
+        let st_body = ty!({"Type" "enum" :
+            "name" => [@"c" "Eval", "Seq"],
+            "component" => [@"c" [(vr ex)], [(vr st)]]});
+        (
+            ty!({"Type" "mu_type" :
+                "param" => [(import [prot "param"] (vr ex)), (import [prot "param"] (vr st))],
+                "body" => (import [* [prot "param"]] (, ex_body.concrete()))}),
+            ty!({"Type" "mu_type" :
+                "param" => [(import [prot "param"] (vr st)), (import [prot "param"] (vr ex))],
+                "body" => (import [* [prot "param"]] (, st_body.concrete()))}),
+        )
+    }
+    let (ex1, st1) = group("Ex1", "St1");
+    let (ex2, st2) = group("Ex2", "St2");
+    let env = assoc_n!("Ex1" => ex1.clone(), "St1" => st1.clone(),
+                       "Ex2" => ex2.clone(), "St2" => st2.clone());
+
+    assert_m!(must_subtype(&ex1, &ex1, env.clone()), Ok(_));
+    assert_m!(must_subtype(&ex1, &ex2, env.clone()), Ok(_));
+    assert_m!(must_subtype(&st2, &st1, env.clone()), Ok(_));
+    assert_m!(must_subtype(&ty!((vr "Ex1")), &ty!((vr "Ex2")), env.clone()), Ok(_));
+    assert_m!(must_subtype(&ex1, &st1, env.clone()), Err(_));
+    assert_m!(must_subtype(&ex1, &st2, env.clone()), Err(_));
+
+    // Within a group, the order of the names doesn't matter:
+    let ex1_reordered = ty!({"Type" "mu_type" :
+        "param" => [(import [prot "param"] (vr "St1")), (import [prot "param"] (vr "Ex1"))],
+        "body" => (, ex1.destructure(find_core_form("Type", "mu_type"), &Trivial)
+                        .unwrap().get_leaf_or_panic(&n("body")).clone())});
+    assert_m!(must_subtype(&ex1, &ex1_reordered, env.clone()), Ok(_));
+}
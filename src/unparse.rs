@@ -5,7 +5,7 @@ use crate::{
         SynEnv,
     },
     name::*,
-    util::mbe::EnvMBE,
+    util::{assoc::Assoc, mbe::EnvMBE},
 };
 
 fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
@@ -43,6 +43,30 @@ fn node_names_mentioned(pat: &FormPat) -> Vec<Name> {
     }
 }
 
+/// Can `pat` be unparsed with `s`? (Not if it calls nonterminals that `s` doesn't have.)
+fn calls_available(pat: &FormPat, s: &SynEnv) -> bool {
+    match *pat {
+        Call(nt) => s.find(&nt).is_some(),
+        Named(_, ref body)
+        | Star(ref body)
+        | Plus(ref body)
+        | NameImport(ref body, _)
+        | NameImportPhaseless(ref body, _)
+        | VarRef(ref body)
+        | Literal(ref body, _)
+        | QuoteDeepen(ref body, _)
+        | QuoteEscape(ref body, _)
+        | Common(ref body)
+        | Pick(ref body, _)
+        | Reserved(ref body, _) => calls_available(&*body, s),
+        Seq(ref sub_pats) | Alt(ref sub_pats) => sub_pats.iter().all(|pat| calls_available(pat, s)),
+        Biased(ref lhs, ref rhs) | SynImport(ref lhs, ref rhs, _) => {
+            calls_available(&*lhs, s) && calls_available(&*rhs, s)
+        }
+        Anyways(_) | Impossible | Scan(_) | Scope(_, _) => true,
+    }
+}
+
 pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv) -> String {
     // HACK: handle underdetermined forms
     let undet = crate::ty_compare::underdetermined_form.with(|u| u.clone());
@@ -79,14 +103,13 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
         }
         (&VarRef(_), _) => "".to_string(), // HACK for `Alt`
         (&Seq(ref sub_pats), _) => {
-            let mut prev_empty = true;
             let mut res = String::new();
             for sub_pat in sub_pats {
                 let sub_res = unparse_mbe(&*sub_pat, actl, context, s);
-                if !prev_empty && sub_res != "" {
+                // (Parts that print as nothing, like `Anyways`, shouldn't glue their neighbors)
+                if !res.is_empty() && sub_res != "" {
                     res.push(' ');
                 }
-                prev_empty = sub_res == "";
                 res.push_str(&sub_res);
             }
             res
@@ -98,6 +121,16 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
                     any_scopes = true;
                     continue;
                 }
+                // Don't print the literal parts of an omitted optional part (e.g. a `match` guard):
+                let mentioned = node_names_mentioned(&*sub_pat);
+                if !mentioned.is_empty()
+                    && mentioned.iter().all(|name| {
+                        context.get_leaf(*name).is_none()
+                            && context.get_rep_leaf(*name).map_or(true, |reps| reps.is_empty())
+                    })
+                {
+                    continue;
+                }
 
                 let sub_res = unparse_mbe(&*sub_pat, actl, context, s);
                 if sub_res != "" {
@@ -137,7 +170,12 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             }
         }
         (&Scope(_, _), _) => "".to_string(), // Non-match
-        (&Pick(ref body, _), _) | (&Common(ref body), _) => unparse_mbe(&*body, actl, context, s),
+        (&Pick(ref body, pick_name), _) => {
+            // `actl` is whatever `pick_name` matched; the rest of `body` isn't in `context`:
+            let picked = EnvMBE::new_from_leaves(Assoc::single(pick_name, actl.clone()));
+            unparse_mbe(body, &Trivial, &picked, s)
+        }
+        (&Common(ref body), _) => unparse_mbe(&*body, actl, context, s),
         (&NameImport(ref body, _), &ExtendEnv(ref actl_body, _)) => {
             unparse_mbe(&*body, &*actl_body, context, s)
         }
@@ -154,9 +192,19 @@ pub fn unparse_mbe(pat: &FormPat, actl: &Ast, context: &EnvMBE<Ast>, s: &SynEnv)
             unparse_mbe(&*body, &*actl_body, context, s)
         }
         (&QuoteEscape(_, _), _) => format!("[Missing ql]{:#?}", actl),
-        (&SynImport(ref _lhs_grammar, ref _rhs, _), &Node(_, _, _)) => {
-            // TODO: I think we need to store the LHS or the new SynEnv to make this pretty.
-            format!("?synax import? {:#?} ?si?", actl)
+        (&SynImport(ref lhs, ref rhs, _), &Node(_, _, _)) => {
+            if calls_available(pat, s) {
+                let lhs_res = unparse_mbe(&*lhs, actl, context, s);
+                let rhs_res = unparse_mbe(&*rhs, actl, context, s);
+                if lhs_res.is_empty() || rhs_res.is_empty() {
+                    format!("{}{}", lhs_res, rhs_res)
+                } else {
+                    format!("{} {}", lhs_res, rhs_res)
+                }
+            } else {
+                // TODO: I think we need to store the LHS or the new SynEnv to make this pretty.
+                format!("?synax import? {:#?} ?si?", actl)
+            }
         }
         (&SynImport(_, _, _), _) => "".to_string(),
         (&Reserved(ref body, _), _) => unparse_mbe(body, actl, context, s),
@@ -50,7 +50,7 @@ impl<K: Eq + Hash + Clone + Reifiable, V: Clone + Reifiable> Reifiable for Assoc
     }
 
     fn reify(&self) -> crate::runtime::eval::Value {
-        let res: Vec<_> =
+        let res: im_rc::Vector<_> =
             self.hamt.iter().map(|(k, v)| Rc::new((k.clone(), v.clone()).reify())).collect();
 
         crate::runtime::eval::Value::Sequence(res)
@@ -108,8 +108,10 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
 
     pub fn set(&self, key: K, value: V) -> Self { Self::from_hamt(self.hamt.update(key, value)) }
 
+    /// `other`'s entries win.
+    /// (As of im-rc 15, `union` keeps the entries of whichever map is bigger, so we can't use it.)
     pub fn set_assoc(&self, other: &Self) -> Self {
-        Self::from_hamt(other.hamt.clone().union(self.hamt.clone()))
+        Self::from_hamt(self.hamt.clone().union_with(other.hamt.clone(), |_, other_v| other_v))
     }
 
     pub fn single(key: K, value: V) -> Self { Self::new().set(key, value) }
@@ -130,13 +132,13 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
     pub fn map_borrow_f<NewV: Clone, F>(&self, f: &mut F) -> Assoc<K, NewV>
     where F: FnMut(&V) -> NewV {
         Assoc::<K, NewV>::from_hamt(
-            self.hamt.iter().map(|(ref k, ref v)| (k.clone(), f(v))).collect(),
+            self.hamt.iter().map(|(k, v)| (k.clone(), f(v))).collect(),
         )
     }
     pub fn keyed_map_borrow_f<NewV: Clone, F>(&self, f: &mut F) -> Assoc<K, NewV>
     where F: FnMut(&K, &V) -> NewV {
         Assoc::<K, NewV>::from_hamt(
-            self.hamt.iter().map(|(ref k, ref v)| (k.clone(), f(k, v))).collect(),
+            self.hamt.iter().map(|(k, v)| (k.clone(), f(k, v))).collect(),
         )
     }
 
@@ -170,7 +172,7 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
 
     pub fn find_value<'assoc, 'f>(&'assoc self, target: &'f V) -> Option<&'assoc K>
     where V: PartialEq {
-        self.hamt.iter().find(|(_, v)| v == target).map(|(k, _)| k)
+        self.hamt.iter().find(|(_, v)| *v == target).map(|(k, _)| k)
     }
 
     pub fn find_or_panic<'assoc, 'f>(&'assoc self, target: &'f K) -> &'assoc V
@@ -185,7 +187,11 @@ impl<K: Eq + Hash + Clone, V: Clone> Assoc<K, V> {
     pub fn cut_common(&self, other: &Assoc<K, V>) -> Assoc<K, V>
     where V: PartialEq {
         Self::from_hamt(
-            self.hamt.iter().filter(|(k, v)| other.find(k) != Some(v)).cloned().collect(),
+            self.hamt
+                .iter()
+                .filter(|(k, v)| other.find(k) != Some(v))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
         )
     }
 
@@ -237,6 +243,12 @@ fn basic_assoc() {
     assert_eq!(a_override.unset(&5).find(&6), Some(&7));
 
     assert_eq!(a_override.unset(&-111).find(&5), Some(&500));
+
+    // The argument of `set_assoc` wins, regardless of which side is bigger:
+    let big = a2.set(7, 8).set(8, 9);
+    assert_eq!(big.set_assoc(&mt.set(5, 500)).find(&5), Some(&500));
+    assert_eq!(mt.set(5, 500).set_assoc(&big).find(&5), Some(&6));
+    assert_eq!(mt.set(5, 500).set_assoc(&big).find(&8), Some(&9));
 }
 
 #[test]
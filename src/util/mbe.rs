@@ -115,7 +115,8 @@ impl<T: Clone + crate::runtime::reify::Reifiable> crate::runtime::reify::Reifiab
             Rc::new(self.ddd_rep_idxes.reify()),
             Rc::new(self.leaf_locations.reify()),
             Rc::new(self.named_repeats.reify()),
-        ])
+        ]
+        .into())
     }
     fn reflect(v: &crate::runtime::eval::Value) -> Self {
         extract!((v) crate::runtime::eval::Value::Sequence = (ref parts) => {
@@ -130,6 +131,72 @@ impl<T: Clone + crate::runtime::reify::Reifiable> crate::runtime::reify::Reifiab
     }
 }
 
+// Likewise, this needs to see the private fields.
+// It's written as `(mbe ‹leaves› (‹repetition› ⋯))`, where each repetition is
+//  `(repeat ‹repetition names› ‹leaf names› ‹ddd index› (‹mbe› ⋯))`,
+//  listing the names that are found by looking in it.
+impl<T: Clone + crate::serialize::Serializable> crate::serialize::Serializable for EnvMBE<T> {
+    fn serialize(
+        &self,
+        s: &mut crate::serialize::Serializer,
+    ) -> Result<crate::serialize::Sexp, String>
+    {
+        use crate::serialize::{tagged, Sexp};
+        let names_at = |locations: &Assoc<Name, Option<usize>>, idx: usize| {
+            let mut names: Vec<Name> = locations
+                .iter_pairs()
+                .filter(|(_, loc)| **loc == Some(idx))
+                .map(|(name, _)| *name)
+                .collect();
+            // `Assoc`s are unordered, but the output should be predictable:
+            names.sort_by_key(|name| name.sp());
+            names
+        };
+        let leaves = self.leaves.serialize(s)?;
+        let mut repeats = vec![];
+        for (idx, (rep, ddd_idx)) in self.repeats.iter().zip(&self.ddd_rep_idxes).enumerate() {
+            repeats.push(tagged("repeat", vec![
+                names_at(&self.named_repeats, idx).serialize(s)?,
+                names_at(&self.leaf_locations, idx).serialize(s)?,
+                ddd_idx.serialize(s)?,
+                rep.serialize(s)?,
+            ]))
+        }
+        Ok(tagged("mbe", vec![leaves, Sexp::List(repeats)]))
+    }
+    fn deserialize(
+        sexp: &crate::serialize::Sexp,
+        d: &mut crate::serialize::Deserializer,
+    ) -> Result<Self, String>
+    {
+        use crate::serialize::{untag, Serializable, Sexp};
+        match untag(sexp)? {
+            ("mbe", [leaves, Sexp::List(repeats)]) => {
+                let mut res = EnvMBE::new_from_leaves(Serializable::deserialize(leaves, d)?);
+                for repeat in repeats {
+                    let (repeat_names, leaf_names, ddd_idx, rep) = match untag(repeat)? {
+                        ("repeat", [repeat_names, leaf_names, ddd_idx, rep]) => {
+                            (repeat_names, leaf_names, ddd_idx, rep)
+                        }
+                        _ => return Err(format!("malformed MBE repetition: {}", repeat)),
+                    };
+                    let idx = res.repeats.len();
+                    for name in Vec::<Name>::deserialize(repeat_names, d)? {
+                        res.named_repeats = res.named_repeats.set(name, Some(idx));
+                    }
+                    for name in Vec::<Name>::deserialize(leaf_names, d)? {
+                        res.leaf_locations = res.leaf_locations.set(name, Some(idx));
+                    }
+                    res.ddd_rep_idxes.push(Serializable::deserialize(ddd_idx, d)?);
+                    res.repeats.push(Serializable::deserialize(rep, d)?);
+                }
+                Ok(res)
+            }
+            _ => Err(format!("malformed MBE: {}", sexp)),
+        }
+    }
+}
+
 impl<T: PartialEq + Clone> PartialEq for EnvMBE<T> {
     fn eq(&self, other: &EnvMBE<T>) -> bool {
         fn assoc_eq_modulo_none<K: Eq + std::hash::Hash + Clone, V: PartialEq + Clone>(
@@ -711,6 +778,10 @@ impl<T: Clone> EnvMBE<T> {
         if lhs_keys != rhs_keys {
             return false;
         }
+        // A repeat that's empty on one side isn't stored at all, so they can't be lined up:
+        if self.repeats.len() != o.repeats.len() {
+            return false;
+        }
 
         for ((subs, subs_ddd), (o_subs, o_subs_ddd)) in self
             .repeats
@@ -857,13 +928,19 @@ impl<T: Clone> EnvMBE<T> {
             }
             already_processed[self_idx] = true;
 
-            let other_idx = other.leaf_locations.find_or_panic(leaf_name).unwrap();
+            let empty = Rc::new(vec![]);
+            let (other_repeat, other_ddd_idx) = match other.leaf_locations.find(leaf_name) {
+                Some(&Some(other_idx)) => {
+                    (&other.repeats[other_idx], &other.ddd_rep_idxes[other_idx])
+                }
+                _ => (&empty, &None), // repeated zero times
+            };
 
             reduced = Self::match_collapse_ddd(
                 &self.repeats[self_idx],
                 &self.ddd_rep_idxes[self_idx],
-                &other.repeats[other_idx],
-                &other.ddd_rep_idxes[other_idx],
+                other_repeat,
+                other_ddd_idx,
                 f,
                 col,
                 red,
@@ -934,9 +1011,33 @@ impl<T: Clone> EnvMBE<T> {
             }
             *repeat = Rc::new(cur_repeat)
         }
+        self.drop_empty_repeats();
         Ok(())
     }
 
+    /// Empty repeats aren't stored (see `add_anon_repeat`), but splicing can empty one out.
+    fn drop_empty_repeats(&mut self) {
+        if self.repeats.iter().all(|rep| !rep.is_empty()) {
+            return;
+        }
+        let mut new_locs = vec![];
+        let mut repeats = vec![];
+        let mut ddd_rep_idxes = vec![];
+        for (rep, ddd_idx) in self.repeats.iter().zip(self.ddd_rep_idxes.iter()) {
+            if rep.is_empty() {
+                new_locs.push(None);
+            } else {
+                new_locs.push(Some(repeats.len()));
+                repeats.push(rep.clone());
+                ddd_rep_idxes.push(*ddd_idx);
+            }
+        }
+        self.repeats = repeats;
+        self.ddd_rep_idxes = ddd_rep_idxes;
+        self.leaf_locations = self.leaf_locations.map(|loc| loc.and_then(|l| new_locs[l]));
+        self.named_repeats = self.named_repeats.map(|loc| loc.and_then(|l| new_locs[l]));
+    }
+
     // TODO: this should return a usable error
     pub fn heal_splices__with<E, S: Clone>(
         &mut self,
@@ -946,20 +1047,24 @@ impl<T: Clone> EnvMBE<T> {
     where
         T: std::fmt::Debug,
     {
-        for repeat in &mut self.repeats {
-            // Find the same repeat in `other`:
+        for (rep_idx, repeat) in self.repeats.iter_mut().enumerate() {
+            // Find the same repeat in `other`, using a leaf of this one:
             let mut names_needed = vec![];
-            for (name, _) in self.leaf_locations.iter_pairs() {
-                names_needed.push(name);
-            }
-            let other__rep_loc = match other.leaf_locations.find(names_needed[0]) {
-                Some(Some(l)) => *l,
-                _ => {
-                    return Ok(()); // Should become a mismatch error elsewhere (TODO: make an `E`)
+            for (name, loc) in self.leaf_locations.iter_pairs() {
+                if *loc == Some(rep_idx) {
+                    names_needed.push(name);
                 }
-            };
-
-            let other__cur_repeat: &Vec<EnvMBE<S>> = &*other.repeats[other__rep_loc];
+            }
+            let no_repeat = vec![];
+            let other__cur_repeat: &Vec<EnvMBE<S>> =
+                match names_needed.first().and_then(|name| other.leaf_locations.find(name)) {
+                    Some(Some(l)) => &*other.repeats[*l],
+                    // Not stored because it's empty (see `add_anon_repeat`),
+                    //  which only a single splice can line up with:
+                    _ if repeat.len() <= 1 => &no_repeat,
+                    // Should become a mismatch error elsewhere (TODO: make an `E`)
+                    _ => return Ok(()),
+                };
             let mut cur_repeat: Vec<EnvMBE<T>> = (**repeat).clone();
 
             // If an item splices, how wide does the other side need to be
@@ -969,8 +1074,11 @@ impl<T: Clone> EnvMBE<T> {
 
             let mut i = 0;
             let mut other_i = 0;
-            while i < cur_repeat.len() && other_i < other__cur_repeat.len() {
-                cur_repeat[i].heal_splices__with(&other__cur_repeat[other_i], f)?;
+            // (`<=` because a splice can match zero items at the end.)
+            while i < cur_repeat.len() && other_i <= other__cur_repeat.len() {
+                if other_i < other__cur_repeat.len() {
+                    cur_repeat[i].heal_splices__with(&other__cur_repeat[other_i], f)?;
+                }
 
                 let mut splices = vec![];
                 {
@@ -1069,7 +1177,8 @@ impl<T: Clone + fmt::Debug> EnvMBE<T> {
         }
         let leaf_loc = match self.leaf_locations.find(&n) {
             Some(&Some(ll)) => ll,
-            _ => panic!("Leaf {} not found", n),
+            // Like in `get_rep_leaf`, this could be a leaf that was repeated 0 times:
+            _ => return f(vec![]),
         };
 
         f(self.repeats[leaf_loc]
@@ -1350,3 +1459,23 @@ fn mbe_ddd_map_with() {
         );
     }
 }
+
+#[test]
+fn mbe_serialization() {
+    use crate::{
+        ast::Ast,
+        serialize::{from_sexp, to_sexp},
+    };
+
+    let orig: EnvMBE<Ast> = mbe!(
+        "rator" => (vr "add"),
+        "rand" => [(vr "a"), (vr "b")],
+        "pat" => [@"arm" (vr "c"), (vr "d")], "body" => [@"arm" (vr "e"), (vr "f")],
+        "ddd" => ["0" ...("1")..., "2"]
+    );
+    let sexp = to_sexp(&orig).unwrap();
+    // Each repetition says which names it has:
+    assert!(format!("{}", sexp).contains(r#"(repeat ("arm") ("body" "pat") none ((mbe"#));
+    assert!(format!("{}", sexp).contains(r#"(repeat () ("ddd") (some 1) ((mbe"#));
+    assert_eq!(from_sexp::<EnvMBE<Ast>>(&sexp), Ok(orig));
+}
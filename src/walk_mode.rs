@@ -70,6 +70,13 @@ pub trait WalkMode: Debug + Copy + Clone + Reifiable {
     fn get_walk_rule(form: &Form) -> WalkRule<Self>
     where Self: Sized;
 
+    /// Should `ExtendEnv`, `QuoteMore`, etc. directly inside `form` be preserved
+    ///  (as opposed to walked through)?
+    fn walks_wrappers_literally(form: &Form) -> bool
+    where Self: Sized {
+        Self::get_walk_rule(form).is_literally()
+    }
+
     /// Should the walker extend the environment based on imports?
     /// Only `QQ` and `Expand` have this as false; it's not 100% clear what's special about them.
     /// (This evolved over time; it used to be false for `Eval`, because of lambda).
@@ -133,6 +140,11 @@ pub trait WalkMode: Debug + Copy + Clone + Reifiable {
     /// Side-effects under the covers make this work.
     fn underspecified(_: Name) -> Self::Elt { icp!("no underspecified_elt") }
 
+    /// Called on the result of walking a `Node` or a `VariableReference`.
+    /// A mode that can keep going after an error (say, to report more than one)
+    ///  can replace the error with some placeholder `Out` here.
+    fn recover(res: Res<Self>) -> Res<Self> { res }
+
     fn name() -> &'static str;
 }
 